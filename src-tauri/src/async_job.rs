@@ -50,7 +50,7 @@ pub(crate) struct JobCancellation {
 }
 
 impl JobCancellation {
-  fn cancel(&self) {
+  pub(crate) fn cancel(&self) {
     if !self.cancelled.swap(true, Ordering::AcqRel) {
       self.notify.notify_waiters();
     }
@@ -72,6 +72,39 @@ impl JobCancellation {
   }
 }
 
+/// Cancellations for a job that runs once at a time, where starting a new run cancels the
+/// previous one. Runs are told apart by a generation ID.
+#[derive(Default)]
+pub(crate) struct LatestCancellation {
+  latest: Mutex<(u64, Option<Arc<JobCancellation>>)>,
+}
+
+impl LatestCancellation {
+  pub fn start(&self) -> (u64, Arc<JobCancellation>) {
+    let mut latest = self.latest.lock().unwrap();
+    if let Some(previous) = latest.1.take() {
+      previous.cancel();
+    }
+    let cancellation = Arc::new(JobCancellation::default());
+    latest.0 += 1;
+    latest.1 = Some(cancellation.clone());
+    (latest.0, cancellation)
+  }
+
+  /// Cancels the running run. Returns whether there was one.
+  pub fn cancel(&self) -> bool {
+    let running = self.latest.lock().unwrap().1.take();
+    running.inspect(|running| running.cancel()).is_some()
+  }
+
+  pub fn finish(&self, generation_id: u64) {
+    let mut latest = self.latest.lock().unwrap();
+    if latest.0 == generation_id {
+      latest.1 = None;
+    }
+  }
+}
+
 #[derive(Clone)]
 pub(crate) struct QueuedJob<J> {
   pub job: J,
@@ -89,7 +122,9 @@ struct RunningJob<K, I> {
 
 struct LatestJobQueueState<J: LatestJob> {
   pending: VecDeque<QueuedJob<J>>,
-  running: Option<RunningJob<J::Key, J::Identity>>,
+  /// At most one job per key.
+  running: Vec<RunningJob<J::Key, J::Identity>>,
+  max_running: usize,
   latest_generation_by_key: HashMap<J::Key, u64>,
 }
 
@@ -97,7 +132,8 @@ impl<J: LatestJob> Default for LatestJobQueueState<J> {
   fn default() -> Self {
     Self {
       pending: VecDeque::new(),
-      running: None,
+      running: Vec::new(),
+      max_running: 1,
       latest_generation_by_key: HashMap::new(),
     }
   }
@@ -125,6 +161,12 @@ impl<J: LatestJob> LatestJobQueue<J> {
     }
   }
 
+  /// Sets how many jobs run at once. Jobs already running beyond it are left to finish.
+  pub fn set_max_running(&self, max_running: usize) {
+    self.state.lock().unwrap().max_running = max_running.max(1);
+    self.notify.notify_one();
+  }
+
   pub fn enqueue(&self, job: J) -> Vec<QueueEvent<J::Identity>> {
     let mut state = self.state.lock().unwrap();
     let mut events = Vec::new();
@@ -142,8 +184,8 @@ impl<J: LatestJob> LatestJobQueue<J> {
     }
     if let Some(running) = state
       .running
-      .as_ref()
-      .filter(|running| &running.identity == job.identity())
+      .iter()
+      .find(|running| &running.identity == job.identity())
     {
       events.push(QueueEvent::new(
         job.identity().clone(),
@@ -172,7 +214,7 @@ impl<J: LatestJob> LatestJobQueue<J> {
       .latest_generation_by_key
       .insert(job.key().clone(), job.generation_id());
 
-    let superseded_running = state.running.as_mut().filter(|running| {
+    let superseded_running = state.running.iter_mut().find(|running| {
       &running.key == job.key() && running.cancel_when_superseded && !running.cancelled
     });
     if let Some(running) = superseded_running {
@@ -244,13 +286,24 @@ impl<J: LatestJob> LatestJobQueue<J> {
     self.pop_next_inner()
   }
 
+  /// Takes the oldest pending job whose key has no running job, while fewer than `max_running`
+  /// jobs run.
   fn pop_next_inner(&self) -> Option<QueuedJob<J>> {
     let mut state = self.state.lock().unwrap();
-    if state.running.is_some() {
+    if state.running.len() >= state.max_running {
       return None;
     }
-    let queued = state.pending.pop_front()?;
-    state.running = Some(RunningJob {
+    let position = state.pending.iter().position(|pending| {
+      !state
+        .running
+        .iter()
+        .any(|running| &running.key == pending.job.key())
+    })?;
+    let queued = state
+      .pending
+      .remove(position)
+      .expect("pending job disappeared while locked");
+    state.running.push(RunningJob {
       key: queued.job.key().clone(),
       generation_id: queued.job.generation_id(),
       identity: queued.job.identity().clone(),
@@ -263,15 +316,14 @@ impl<J: LatestJob> LatestJobQueue<J> {
 
   pub fn finish(&self, identity: &J::Identity) -> bool {
     let mut state = self.state.lock().unwrap();
-    let Some(running) = state
+    let Some(position) = state
       .running
-      .as_ref()
-      .filter(|running| &running.identity == identity)
+      .iter()
+      .position(|running| &running.identity == identity)
     else {
       return false;
     };
-    let completed_without_cancellation = !running.cancelled;
-    state.running = None;
+    let completed_without_cancellation = !state.running.remove(position).cancelled;
     drop(state);
     self.notify.notify_one();
     completed_without_cancellation
@@ -300,9 +352,9 @@ impl<J: LatestJob> LatestJobQueue<J> {
     }
     state.pending = retained;
 
-    if let Some(running) = state
+    for running in state
       .running
-      .as_mut()
+      .iter_mut()
       .filter(|running| matches(&running.key, running.generation_id) && !running.cancelled)
     {
       events.push(QueueEvent::new(
@@ -419,6 +471,35 @@ mod tests {
     assert_eq!(queue.pop_next().unwrap().job.identity.generation_id, 2);
   }
 
+  #[test]
+  fn parallel_jobs_run_for_different_keys_only() {
+    let queue = LatestJobQueue::new(8);
+    queue.set_max_running(2);
+    let mut first = TestJob::new("first", 1);
+    first.cancellable = false;
+    queue.enqueue(first);
+    let running_first = queue.pop_next().unwrap();
+    queue.enqueue(TestJob::new("first", 2));
+    queue.enqueue(TestJob::new("second", 1));
+    queue.enqueue(TestJob::new("third", 1));
+
+    // the newer generation of "first" waits for the running one
+    let running_second = queue.pop_next().unwrap();
+    assert_eq!(running_second.job.identity.key, "second");
+    assert!(queue.pop_next().is_none());
+
+    let cancelled = queue.cancel(&"second".into(), None);
+    assert_eq!(cancelled.len(), 1);
+    assert!(running_second.cancellation.is_cancelled());
+    assert!(!queue.finish(&running_second.job.identity));
+    assert_eq!(queue.pop_next().unwrap().job.identity.key, "third");
+
+    assert!(queue.finish(&running_first.job.identity));
+    let next = queue.pop_next().unwrap();
+    assert_eq!(next.job.identity.key, "first");
+    assert_eq!(next.job.identity.generation_id, 2);
+  }
+
   #[test]
   fn queue_capacity_and_explicit_cancellation_are_keyed() {
     let queue = LatestJobQueue::new(2);
@@ -436,6 +517,23 @@ mod tests {
     assert_eq!(cancelled[0].identity.key, "second");
   }
 
+  #[test]
+  fn starting_a_run_cancels_the_previous_one() {
+    let runs = LatestCancellation::default();
+    let (first_id, first) = runs.start();
+    let (second_id, second) = runs.start();
+
+    assert!(first.is_cancelled());
+    assert!(!second.is_cancelled());
+    assert!(second_id > first_id);
+
+    // a late finish of the first run keeps the second cancellable
+    runs.finish(first_id);
+    assert!(runs.cancel());
+    assert!(second.is_cancelled());
+    assert!(!runs.cancel());
+  }
+
   #[test]
   fn cancellable_runner_drops_superseded_work() {
     tauri::async_runtime::block_on(async {
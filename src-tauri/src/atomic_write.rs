@@ -0,0 +1,71 @@
+//! Crash-safe file replacement shared by everything that rewrites files in place.
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+
+/// Writes `bytes` to a temporary file next to `path`, syncs it and renames it over `path`, so a
+/// crash or a full disk leaves either the old or the new contents behind. `what` names the file
+/// in error messages.
+pub(crate) fn write_atomically(path: &Path, bytes: &[u8], what: &str) -> Result<(), String> {
+  let file_name = path
+    .file_name()
+    .and_then(|name| name.to_str())
+    .ok_or_else(|| format!("The {what} path has an invalid filename"))?;
+  let temporary_path = path.with_file_name(format!(
+    ".{file_name}.{}.tmp",
+    uuid::Uuid::new_v4().as_hyphenated()
+  ));
+  let write_result = (|| -> Result<(), String> {
+    let mut file = OpenOptions::new()
+      .write(true)
+      .create_new(true)
+      .open(&temporary_path)
+      .map_err(|error| format!("Failed to create temporary {what}: {error}"))?;
+    file
+      .write_all(bytes)
+      .map_err(|error| format!("Failed to write temporary {what}: {error}"))?;
+    file
+      .sync_all()
+      .map_err(|error| format!("Failed to sync temporary {what}: {error}"))?;
+    drop(file);
+    fs::rename(&temporary_path, path)
+      .map_err(|error| format!("Failed to commit {what}: {error}"))?;
+    Ok(())
+  })();
+  if write_result.is_err() {
+    let _ = fs::remove_file(&temporary_path);
+  }
+  write_result
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn replaces_contents_without_leaving_temporary_files() {
+    let directory = tempfile::tempdir().unwrap();
+    let path = directory.path().join("file.txt");
+
+    write_atomically(&path, b"first", "test file").unwrap();
+    write_atomically(&path, b"second", "test file").unwrap();
+
+    assert_eq!(fs::read(&path).unwrap(), b"second");
+    assert_eq!(fs::read_dir(directory.path()).unwrap().count(), 1);
+  }
+
+  #[test]
+  fn failed_writes_keep_the_original_and_clean_up() {
+    let directory = tempfile::tempdir().unwrap();
+    // a directory cannot be replaced by a file
+    let path = directory.path().join("occupied");
+    fs::create_dir(&path).unwrap();
+    fs::write(path.join("inside"), b"kept").unwrap();
+
+    let error = write_atomically(&path, b"new", "test file").unwrap_err();
+
+    assert!(error.contains("Failed to commit test file"), "{error}");
+    assert_eq!(fs::read(path.join("inside")).unwrap(), b"kept");
+    assert_eq!(fs::read_dir(directory.path()).unwrap().count(), 1);
+  }
+}
@@ -0,0 +1,276 @@
+//! Self-contained `.azpz` project bundles.
+//!
+//! A bundle is a zip archive holding the project file, the rendered take of every block, the
+//! speaker icons it shows and a manifest of the voices needed to edit it further:
+//!
+//! ```text
+//! manifest.json
+//! project.azp
+//! takes/<hash>.wav
+//! icons/<speaker uuid>-<style id>.png
+//! ```
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::{Read, Seek, Write};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+pub(crate) const BUNDLE_FORMAT_VERSION: u32 = 1;
+
+const MANIFEST_ENTRY: &str = "manifest.json";
+const PROJECT_ENTRY: &str = "project.azp";
+/// Largest an entry may be once decompressed, far above any take of a single block.
+const MAX_ENTRY_BYTES: u64 = 256 * 1024 * 1024;
+
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+pub(crate) struct BundleManifest {
+  pub format_version: u32,
+  /// Every style the project synthesizes with.
+  pub style_ids: Vec<u32>,
+  pub speaker_uuids: Vec<String>,
+  /// File names of the voice models that provide those styles.
+  pub vvm_files: Vec<String>,
+  pub takes: Vec<BundleTake>,
+  pub icons: Vec<BundleIcon>,
+}
+
+/// The rendered audio of one block, stored under the hash of its `waveform_cache_key`, so takes
+/// are named the same by every build and match the waveform caches in memory and on disk.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub(crate) struct BundleTake {
+  pub block_id: String,
+  pub style_id: u32,
+  /// The serialized query the take was synthesized from, as used in the waveform cache.
+  pub query: String,
+  pub hash: String,
+  /// Identity of the voice model the take was synthesized with. Bundles written before it was
+  /// recorded have none.
+  #[serde(default)]
+  pub model_identity: Option<String>,
+}
+
+impl BundleTake {
+  /// Whether the take was synthesized with the voice model of `model_identity`. A take without a
+  /// recorded model matches none.
+  pub fn made_with(&self, model_identity: Option<&str>) -> bool {
+    self.model_identity.is_some() && self.model_identity.as_deref() == model_identity
+  }
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub(crate) struct BundleIcon {
+  pub speaker_uuid: String,
+  pub style_id: u32,
+}
+
+impl BundleIcon {
+  fn entry_name(&self) -> String {
+    format!("icons/{}-{}.png", self.speaker_uuid, self.style_id)
+  }
+}
+
+pub(crate) struct Bundle {
+  pub manifest: BundleManifest,
+  pub project_toml: String,
+  /// Rendered WAVs by take hash.
+  pub takes: BTreeMap<String, Vec<u8>>,
+  /// PNG icons in the order of `manifest.icons`.
+  pub icons: Vec<Vec<u8>>,
+}
+
+fn take_entry_name(hash: &str) -> String {
+  format!("takes/{hash}.wav")
+}
+
+pub(crate) fn write_bundle(bundle: &Bundle, writer: impl Write + Seek) -> Result<(), String> {
+  if bundle.icons.len() != bundle.manifest.icons.len() {
+    return Err("Every bundled icon needs a manifest entry".into());
+  }
+  let mut zip = ZipWriter::new(writer);
+  let text = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+  // audio and PNGs hardly compress, storing them keeps bundling fast
+  let binary = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+  let mut add = |name: &str, options: SimpleFileOptions, bytes: &[u8]| {
+    zip
+      .start_file(name, options)
+      .and_then(|()| zip.write_all(bytes).map_err(Into::into))
+      .map_err(|e| format!("Failed to add {name} to the bundle: {e}"))
+  };
+
+  let manifest = serde_json::to_vec_pretty(&bundle.manifest).map_err(|e| e.to_string())?;
+  add(MANIFEST_ENTRY, text, &manifest)?;
+  add(PROJECT_ENTRY, text, bundle.project_toml.as_bytes())?;
+  for (hash, wav) in &bundle.takes {
+    add(&take_entry_name(hash), binary, wav)?;
+  }
+  for (icon, png) in bundle.manifest.icons.iter().zip(&bundle.icons) {
+    add(&icon.entry_name(), binary, png)?;
+  }
+  zip
+    .finish()
+    .map_err(|e| format!("Failed to finish the bundle: {e}"))?;
+  Ok(())
+}
+
+fn read_entry<R: Read + Seek>(archive: &mut ZipArchive<R>, name: &str) -> Result<Vec<u8>, String> {
+  read_entry_within(archive, name, MAX_ENTRY_BYTES)
+}
+
+/// Reads an entry of at most `limit` bytes. The size the archive declares is not trusted, so the
+/// entry is read no further than one byte past the limit.
+fn read_entry_within<R: Read + Seek>(
+  archive: &mut ZipArchive<R>,
+  name: &str,
+  limit: u64,
+) -> Result<Vec<u8>, String> {
+  let entry = archive
+    .by_name(name)
+    .map_err(|e| format!("Bundle entry {name} is unreadable: {e}"))?;
+  let mut bytes = Vec::new();
+  entry
+    .take(limit + 1)
+    .read_to_end(&mut bytes)
+    .map_err(|e| format!("Bundle entry {name} is unreadable: {e}"))?;
+  if bytes.len() as u64 > limit {
+    return Err(format!("Bundle entry {name} is larger than {limit} bytes"));
+  }
+  Ok(bytes)
+}
+
+/// Reads a bundle, requiring every take and icon its manifest lists.
+pub(crate) fn read_bundle(reader: impl Read + Seek) -> Result<Bundle, String> {
+  let mut archive =
+    ZipArchive::new(reader).map_err(|e| format!("Not a valid project bundle: {e}"))?;
+  let manifest: BundleManifest = serde_json::from_slice(&read_entry(&mut archive, MANIFEST_ENTRY)?)
+    .map_err(|e| format!("Bundle manifest is invalid: {e}"))?;
+  if manifest.format_version == 0 || manifest.format_version > BUNDLE_FORMAT_VERSION {
+    return Err(format!(
+      "Unsupported bundle format version {}",
+      manifest.format_version
+    ));
+  }
+  let project_toml = String::from_utf8(read_entry(&mut archive, PROJECT_ENTRY)?)
+    .map_err(|_| "Bundled project is not valid UTF-8".to_string())?;
+
+  let mut takes = BTreeMap::new();
+  for take in &manifest.takes {
+    if take.hash.is_empty() || !take.hash.chars().all(|c| c.is_ascii_hexdigit()) {
+      return Err(format!("Bundle take {:?} has an invalid hash", take.hash));
+    }
+    if !takes.contains_key(&take.hash) {
+      let wav = read_entry(&mut archive, &take_entry_name(&take.hash))?;
+      takes.insert(take.hash.clone(), wav);
+    }
+  }
+  let icons = manifest
+    .icons
+    .iter()
+    .map(|icon| read_entry(&mut archive, &icon.entry_name()))
+    .collect::<Result<_, _>>()?;
+  Ok(Bundle {
+    manifest,
+    project_toml,
+    takes,
+    icons,
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::io::Cursor;
+
+  fn bundle() -> Bundle {
+    let query = r#"{"speedScale":1.0}"#.to_string();
+    let hash = "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08".to_string();
+    let take = |block_id: &str| BundleTake {
+      block_id: block_id.into(),
+      style_id: 3,
+      query: query.clone(),
+      hash: hash.clone(),
+      model_identity: Some("model@1".into()),
+    };
+    Bundle {
+      manifest: BundleManifest {
+        format_version: BUNDLE_FORMAT_VERSION,
+        style_ids: vec![3],
+        speaker_uuids: vec!["speaker-uuid".into()],
+        vvm_files: vec!["0.vvm".into()],
+        // both blocks say the same thing, so they share one take
+        takes: vec![take("block-1"), take("block-2")],
+        icons: vec![BundleIcon {
+          speaker_uuid: "speaker-uuid".into(),
+          style_id: 3,
+        }],
+      },
+      project_toml: "schema_version = 1\n".into(),
+      takes: BTreeMap::from([(hash, b"RIFF take".to_vec())]),
+      icons: vec![b"\x89PNG icon".to_vec()],
+    }
+  }
+
+  fn written(bundle: &Bundle) -> Vec<u8> {
+    let mut bytes = Cursor::new(Vec::new());
+    write_bundle(bundle, &mut bytes).unwrap();
+    bytes.into_inner()
+  }
+
+  #[test]
+  fn bundles_round_trip_with_shared_takes_stored_once() {
+    let original = bundle();
+    let bytes = written(&original);
+
+    let archive = ZipArchive::new(Cursor::new(&bytes)).unwrap();
+    assert_eq!(archive.len(), 4);
+    let read = read_bundle(Cursor::new(bytes)).unwrap();
+
+    assert_eq!(read.manifest, original.manifest);
+    assert_eq!(read.project_toml, original.project_toml);
+    assert_eq!(read.takes, original.takes);
+    assert_eq!(read.icons, original.icons);
+  }
+
+  #[test]
+  fn takes_match_only_the_model_they_were_made_with() {
+    let mut take = bundle().manifest.takes.remove(0);
+    assert!(take.made_with(Some("model@1")));
+    assert!(!take.made_with(Some("model@2")));
+    assert!(!take.made_with(None));
+
+    // takes of older bundles do not say which model made them
+    take.model_identity = None;
+    assert!(!take.made_with(None));
+    let json = r#"{"block_id":"block-1","style_id":3,"query":"{}","hash":"0"}"#;
+    let take: BundleTake = serde_json::from_str(json).unwrap();
+    assert_eq!(take.model_identity, None);
+  }
+
+  #[test]
+  fn incomplete_or_unknown_bundles_are_rejected() {
+    let error = |bundle: &Bundle| read_bundle(Cursor::new(written(bundle))).err().unwrap();
+
+    let mut newer = bundle();
+    newer.manifest.format_version = BUNDLE_FORMAT_VERSION + 1;
+    assert!(error(&newer).contains("Unsupported bundle format version"));
+
+    let mut missing_take = bundle();
+    missing_take.takes.clear();
+    assert!(error(&missing_take).contains("takes/"));
+
+    let mut bad_hash = bundle();
+    bad_hash.manifest.takes[0].hash = "../project".into();
+    assert!(error(&bad_hash).contains("invalid hash"));
+
+    assert!(read_bundle(Cursor::new(b"not a zip".to_vec())).is_err());
+  }
+
+  #[test]
+  fn entries_past_the_limit_are_rejected_without_reading_them_whole() {
+    let mut archive = ZipArchive::new(Cursor::new(written(&bundle()))).unwrap();
+
+    let project_toml = read_entry_within(&mut archive, PROJECT_ENTRY, 19).unwrap();
+    assert_eq!(project_toml, b"schema_version = 1\n");
+    let error = read_entry_within(&mut archive, PROJECT_ENTRY, 18).unwrap_err();
+    assert!(error.contains("larger than 18 bytes"), "{error}");
+  }
+}
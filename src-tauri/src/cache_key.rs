@@ -0,0 +1,175 @@
+//! Keys of the query and waveform caches, in memory and on disk.
+//!
+//! A key is a hash of everything the cached value depends on, the voice model included, so a
+//! changed model never serves what the one before it made. Queries are hashed by value rather
+//! than as JSON text: numbers by their bits as the `f32` they are parsed into, with negative zero
+//! folded into zero, and object fields in the order of their names.
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use voicevox_core::{AudioQuery, StyleId};
+
+/// A hash in hex and the style it was made for.
+pub(crate) type CacheKey = (String, StyleId);
+
+/// The key of the query made from `text` in `style_id` by the model `model_identity` with the
+/// dictionary `dictionary_identity`.
+pub(crate) fn query_cache_key(
+  text: &str,
+  style_id: StyleId,
+  model_identity: &str,
+  dictionary_identity: &str,
+) -> CacheKey {
+  let mut hasher = Sha256::new();
+  for part in [text, model_identity, dictionary_identity] {
+    hash_bytes(&mut hasher, part.as_bytes());
+  }
+  hasher.update(style_id.0.to_le_bytes());
+  (format!("{:x}", hasher.finalize()), style_id)
+}
+
+/// The key of the waveform synthesized from `query` in `style_id` by the model `model_identity`.
+/// The waveform cache on disk names its files after the same hash.
+pub(crate) fn waveform_cache_key(
+  query: &AudioQuery,
+  style_id: StyleId,
+  model_identity: &str,
+) -> Result<CacheKey, String> {
+  let query = serde_json::to_value(query).map_err(|e| e.to_string())?;
+  let mut hasher = Sha256::new();
+  hash_value(&mut hasher, &query);
+  hash_bytes(&mut hasher, model_identity.as_bytes());
+  hasher.update(style_id.0.to_le_bytes());
+  Ok((format!("{:x}", hasher.finalize()), style_id))
+}
+
+// lengths keep the parts from running into each other
+fn hash_bytes(hasher: &mut Sha256, bytes: &[u8]) {
+  hasher.update((bytes.len() as u64).to_le_bytes());
+  hasher.update(bytes);
+}
+
+fn hash_value(hasher: &mut Sha256, value: &Value) {
+  match value {
+    Value::Null => hasher.update([0]),
+    Value::Bool(value) => hasher.update([1, *value as u8]),
+    Value::Number(number) => match number.as_u64() {
+      Some(number) => {
+        hasher.update([2]);
+        hasher.update(number.to_le_bytes());
+      }
+      None => {
+        hasher.update([3]);
+        hasher.update(canonical_float(number.as_f64().unwrap_or_default()).to_le_bytes());
+      }
+    },
+    Value::String(value) => {
+      hasher.update([4]);
+      hash_bytes(hasher, value.as_bytes());
+    }
+    Value::Array(items) => {
+      hasher.update([5]);
+      hasher.update((items.len() as u64).to_le_bytes());
+      for item in items {
+        hash_value(hasher, item);
+      }
+    }
+    Value::Object(fields) => {
+      hasher.update([6]);
+      hasher.update((fields.len() as u64).to_le_bytes());
+      let mut fields = fields.iter().collect::<Vec<_>>();
+      fields.sort_by_key(|(name, _)| *name);
+      for (name, field) in fields {
+        hash_bytes(hasher, name.as_bytes());
+        hash_value(hasher, field);
+      }
+    }
+  }
+}
+
+/// The bits of a query number, which holds no more than an `f32`.
+fn canonical_float(value: f64) -> u32 {
+  let value = value as f32;
+  if value == 0.0 {
+    0
+  } else {
+    value.to_bits()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use serde_json::json;
+
+  fn query(speed_scale: f32) -> AudioQuery {
+    serde_json::from_value(json!({
+      "accent_phrases": [],
+      "speedScale": speed_scale,
+      "pitchScale": 0.0,
+      "intonationScale": 1.0,
+      "volumeScale": 1.0,
+      "prePhonemeLength": 0.1,
+      "postPhonemeLength": 0.1,
+      "outputSamplingRate": 24000,
+      "outputStereo": false
+    }))
+    .unwrap()
+  }
+
+  #[test]
+  fn waveform_keys_tell_queries_styles_and_models_apart() {
+    let key = waveform_cache_key(&query(1.0), StyleId(1), "model@1").unwrap();
+
+    assert_eq!(key.0.len(), 64);
+    assert_eq!(key.1, StyleId(1));
+    assert_eq!(
+      key,
+      waveform_cache_key(&query(1.0), StyleId(1), "model@1").unwrap()
+    );
+    for other in [
+      waveform_cache_key(&query(1.5), StyleId(1), "model@1"),
+      waveform_cache_key(&query(1.0), StyleId(2), "model@1"),
+      waveform_cache_key(&query(1.0), StyleId(1), "model@2"),
+    ] {
+      assert_ne!(key.0, other.unwrap().0);
+    }
+  }
+
+  #[test]
+  fn equal_queries_share_a_key_however_their_numbers_were_written() {
+    let written = |pitch_scale: &str, speed_scale: &str| {
+      let query = serde_json::from_str::<AudioQuery>(&format!(
+        r#"{{"accent_phrases":[],"speedScale":{speed_scale},"pitchScale":{pitch_scale},
+        "intonationScale":1,"volumeScale":1.0,"prePhonemeLength":0.1,"postPhonemeLength":0.1,
+        "outputSamplingRate":24000,"outputStereo":false}}"#
+      ))
+      .unwrap();
+      waveform_cache_key(&query, StyleId(1), "model").unwrap()
+    };
+
+    let key = written("0.0", "1.1");
+    assert_eq!(key, written("-0", "1.1000000000000001"));
+    assert_eq!(key, written("0e3", "1.10000001"));
+    assert_ne!(key, written("0.0", "1.2"));
+  }
+
+  #[test]
+  fn query_keys_follow_the_model_and_the_dictionary() {
+    let key = query_cache_key("こんにちは", StyleId(1), "model@1", "/dictionary");
+
+    assert_eq!(
+      key,
+      query_cache_key("こんにちは", StyleId(1), "model@1", "/dictionary")
+    );
+    for other in [
+      query_cache_key("こんばんは", StyleId(1), "model@1", "/dictionary"),
+      query_cache_key("こんにちは", StyleId(2), "model@1", "/dictionary"),
+      query_cache_key("こんにちは", StyleId(1), "model@2", "/dictionary"),
+      query_cache_key("こんにちは", StyleId(1), "model@1", "/other"),
+      // the parts cannot run into each other
+      query_cache_key("こんにちはmodel@1", StyleId(1), "", "/dictionary"),
+    ] {
+      assert_ne!(key.0, other.0);
+    }
+  }
+}
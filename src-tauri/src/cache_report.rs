@@ -0,0 +1,236 @@
+//! What the query and waveform caches hold and how well they serve, for tuning their sizes.
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use voicevox_core::StyleId;
+
+use crate::synthesis::WaveformCache;
+use crate::QueryLruType;
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize, specta::Type)]
+pub enum CacheKind {
+  Query,
+  Waveform,
+}
+
+/// Counts how lookups in a cache went since the app started.
+#[derive(Default)]
+pub(crate) struct CacheStatistics {
+  hits: AtomicU64,
+  misses: AtomicU64,
+  evictions: AtomicU64,
+  coalesced_waits: AtomicU64,
+}
+
+impl CacheStatistics {
+  pub fn record_hit(&self) {
+    self.hits.fetch_add(1, Ordering::Relaxed);
+  }
+
+  pub fn record_miss(&self) {
+    self.misses.fetch_add(1, Ordering::Relaxed);
+  }
+
+  pub fn record_evictions(&self, count: usize) {
+    self.evictions.fetch_add(count as u64, Ordering::Relaxed);
+  }
+
+  pub fn record_coalesced_wait(&self) {
+    self.coalesced_waits.fetch_add(1, Ordering::Relaxed);
+  }
+
+  pub fn counts(&self) -> CacheCounts {
+    CacheCounts {
+      hits: self.hits.load(Ordering::Relaxed),
+      misses: self.misses.load(Ordering::Relaxed),
+      evictions: self.evictions.load(Ordering::Relaxed),
+      coalesced_waits: self.coalesced_waits.load(Ordering::Relaxed),
+    }
+  }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, specta::Type)]
+pub struct CacheCounts {
+  pub hits: u64,
+  pub misses: u64,
+  /// Entries dropped to stay within the limits or evicted one by one. Clearing a whole cache is
+  /// not counted.
+  pub evictions: u64,
+  /// Lookups that waited for a synthesis of the same waveform already running and got its
+  /// waveform. A lookup left to synthesize it after that synthesis was cancelled is a miss.
+  /// Queries are not shared while they are made, so this stays 0 for them.
+  pub coalesced_waits: u64,
+}
+
+/// A block waiting to hear about the eviction of a waveform.
+#[derive(Clone, Debug, PartialEq, Serialize, specta::Type)]
+pub struct CacheOwnerReport {
+  pub block_id: String,
+  pub generation_id: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, specta::Type)]
+pub struct CacheEntryReport {
+  /// The hash of the entry, which `evict_cache_entry` takes with the style.
+  pub key: String,
+  pub style_id: StyleId,
+  /// Size of the WAV file, or of the query as JSON. 0 while a waveform is synthesized.
+  pub bytes: u64,
+  /// Whether the waveform is synthesized. Queries always are.
+  pub initialized: bool,
+  pub owners: Vec<CacheOwnerReport>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, specta::Type)]
+pub struct CacheReport {
+  /// Cached waveforms, most recently used first.
+  pub waveforms: Vec<CacheEntryReport>,
+  pub waveform_bytes: u64,
+  pub waveform_budget_bytes: u64,
+  pub waveform_counts: CacheCounts,
+  /// Cached queries, most recently used first.
+  pub queries: Vec<CacheEntryReport>,
+  /// Most queries kept.
+  pub query_capacity: usize,
+  pub query_counts: CacheCounts,
+}
+
+impl CacheReport {
+  /// Reports the caches as they are. A cache that is disabled reports no entries.
+  pub(crate) fn new(
+    waveforms: Option<&WaveformCache>,
+    waveform_counts: CacheCounts,
+    queries: Option<&QueryLruType>,
+    query_counts: CacheCounts,
+  ) -> Self {
+    Self {
+      waveforms: waveforms
+        .into_iter()
+        .flat_map(|cache| cache.iter())
+        .map(|((key, style_id), entry)| CacheEntryReport {
+          key: key.clone(),
+          style_id: *style_id,
+          bytes: entry.bytes(),
+          initialized: entry.cell.get().is_some(),
+          owners: entry
+            .owners
+            .iter()
+            .map(|owner| CacheOwnerReport {
+              block_id: owner.identity.block_id.clone(),
+              generation_id: owner.identity.generation_id,
+            })
+            .collect(),
+        })
+        .collect(),
+      waveform_bytes: waveforms.map_or(0, |cache| cache.bytes()),
+      waveform_budget_bytes: waveforms.map_or(0, |cache| cache.budget_bytes()),
+      waveform_counts,
+      queries: queries
+        .into_iter()
+        .flat_map(|cache| cache.iter())
+        .map(|((key, style_id), query)| CacheEntryReport {
+          key: key.clone(),
+          style_id: *style_id,
+          bytes: serde_json::to_vec(query).map_or(0, |json| json.len() as u64),
+          initialized: true,
+          owners: Vec::new(),
+        })
+        .collect(),
+      query_capacity: queries.map_or(0, |cache| cache.cap().get()),
+      query_counts,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::cache_key::query_cache_key;
+  use crate::synthesis::{SynthesisJobIdentity, WaveformCacheEntry, WaveformCacheOwner};
+  use serde_json::json;
+  use std::num::NonZeroUsize;
+  use std::sync::Arc;
+  use tokio::sync::OnceCell;
+
+  #[test]
+  fn reports_list_entries_most_recently_used_first_with_their_owners() {
+    let mut waveforms = WaveformCache::new(1024);
+    let mut synthesized = WaveformCacheEntry::new(Arc::new(OnceCell::new_with(Some(vec![0; 44]))));
+    synthesized.add_owner(WaveformCacheOwner {
+      identity: SynthesisJobIdentity {
+        block_id: "block".into(),
+        generation_id: 3,
+        hash: "hash".into(),
+        query_key: "query".into(),
+        speaker_id: StyleId(1),
+      },
+    });
+    waveforms.push(("synthesized".into(), StyleId(1)), synthesized);
+    waveforms.push(
+      ("pending".into(), StyleId(2)),
+      WaveformCacheEntry::new(Arc::new(OnceCell::new())),
+    );
+    let mut queries = QueryLruType::new(NonZeroUsize::new(8).unwrap());
+    let query = serde_json::from_value(json!({
+      "accent_phrases": [],
+      "speedScale": 1.0,
+      "pitchScale": 0.0,
+      "intonationScale": 1.0,
+      "volumeScale": 1.0,
+      "prePhonemeLength": 0.1,
+      "postPhonemeLength": 0.1,
+      "outputSamplingRate": 24000,
+      "outputStereo": false
+    }))
+    .unwrap();
+    queries.put(
+      query_cache_key("text", StyleId(1), "model", "/dictionary"),
+      query,
+    );
+    let statistics = CacheStatistics::default();
+    statistics.record_hit();
+    statistics.record_hit();
+    statistics.record_miss();
+    statistics.record_evictions(2);
+    statistics.record_coalesced_wait();
+
+    let report = CacheReport::new(
+      Some(&waveforms),
+      statistics.counts(),
+      Some(&queries),
+      CacheCounts::default(),
+    );
+
+    let keys = report
+      .waveforms
+      .iter()
+      .map(|entry| (entry.key.as_str(), entry.initialized, entry.bytes))
+      .collect::<Vec<_>>();
+    assert_eq!(keys, [("pending", false, 0), ("synthesized", true, 44)]);
+    assert_eq!(
+      report.waveforms[1].owners,
+      [CacheOwnerReport {
+        block_id: "block".into(),
+        generation_id: 3
+      }]
+    );
+    assert_eq!(
+      (report.waveform_bytes, report.waveform_budget_bytes),
+      (44, 1024)
+    );
+    assert_eq!(
+      report.waveform_counts,
+      CacheCounts {
+        hits: 2,
+        misses: 1,
+        evictions: 2,
+        coalesced_waits: 1
+      }
+    );
+    assert_eq!(report.queries.len(), 1);
+    assert!(report.queries[0].bytes > 0);
+    assert_eq!(report.query_capacity, 8);
+
+    let disabled = CacheReport::new(None, CacheCounts::default(), None, CacheCounts::default());
+    assert!(disabled.waveforms.is_empty() && disabled.queries.is_empty());
+  }
+}
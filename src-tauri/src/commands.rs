@@ -1,12 +1,20 @@
 mod assets;
+mod bundle;
 mod config;
 mod core;
+mod preload;
 mod process;
 mod project;
+mod render;
+mod search;
 mod utils;
 
 pub use assets::*;
+pub use bundle::*;
 pub use config::*;
 pub use core::*;
+pub use preload::*;
 pub use process::*;
 pub use project::*;
+pub use render::*;
+pub use search::*;
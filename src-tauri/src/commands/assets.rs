@@ -1,15 +1,18 @@
-use crate::config::manager::assets_dir;
+use crate::atomic_write::write_atomically;
+use crate::config::manager::{assets_dir, waveform_store_dir};
+use crate::AppState;
 use base64::engine::general_purpose::STANDARD as BASE64_STANDARD;
 use base64::Engine;
 use futures_util::{stream, StreamExt};
 use reqwest::{Client, Response, Url};
 use serde::{Deserialize, Serialize};
 use specta::Type;
-use std::fs::{self, OpenOptions};
-use std::io::{ErrorKind, Write};
+use std::fs;
+use std::io::ErrorKind;
 use std::path::{Path, PathBuf};
 use std::sync::LazyLock;
 use std::time::Duration;
+use tauri::State;
 
 const CHARACTER_INFO_URL: &str =
   "https://api.github.com/repos/VOICEVOX/voicevox_resource/contents/character_info?ref=main";
@@ -154,36 +157,7 @@ fn write_icon_atomically(assets_root: &Path, path: &Path, bytes: &[u8]) -> Resul
   validate_png(bytes)?;
   ensure_directory(assets_root)?;
   ensure_directory(&speaker_icon_dir(assets_root))?;
-
-  let file_name = path
-    .file_name()
-    .and_then(|name| name.to_str())
-    .ok_or("Cached icon path has an invalid filename")?;
-  let temporary_path = path.with_file_name(format!(
-    ".{file_name}.{}.tmp",
-    uuid::Uuid::new_v4().as_hyphenated()
-  ));
-  let write_result = (|| -> Result<(), String> {
-    let mut file = OpenOptions::new()
-      .write(true)
-      .create_new(true)
-      .open(&temporary_path)
-      .map_err(|error| format!("Failed to create temporary icon file: {error}"))?;
-    file
-      .write_all(bytes)
-      .map_err(|error| format!("Failed to write temporary icon file: {error}"))?;
-    file
-      .sync_all()
-      .map_err(|error| format!("Failed to sync temporary icon file: {error}"))?;
-    drop(file);
-    fs::rename(&temporary_path, path)
-      .map_err(|error| format!("Failed to commit cached icon: {error}"))?;
-    Ok(())
-  })();
-  if write_result.is_err() {
-    let _ = fs::remove_file(&temporary_path);
-  }
-  write_result
+  write_atomically(path, bytes, "icon file")
 }
 
 async fn response_bytes_limited(mut response: Response, limit: usize) -> Result<Vec<u8>, String> {
@@ -280,6 +254,29 @@ async fn download_icon(client: &Client, directory: &str, style_id: u32) -> Resul
   Ok(bytes)
 }
 
+/// Reads a downloaded speaker icon from the assets cache, if there is a valid one.
+pub(crate) fn cached_speaker_icon_at(
+  assets_root: &Path,
+  request: &SpeakerIconRequest,
+) -> Result<Option<Vec<u8>>, String> {
+  read_cached_icon(&speaker_icon_path(assets_root, request)?)
+}
+
+/// Adds an icon obtained elsewhere to the assets cache, keeping a valid cached copy.
+pub(crate) fn store_speaker_icon_at(
+  assets_root: &Path,
+  request: &SpeakerIconRequest,
+  bytes: &[u8],
+) -> Result<(), String> {
+  let path = speaker_icon_path(assets_root, request)?;
+  match read_cached_icon(&path) {
+    Ok(Some(_)) => return Ok(()),
+    Ok(None) => {}
+    Err(_) => remove_invalid_cache_file(&path)?,
+  }
+  write_icon_atomically(assets_root, &path, bytes)
+}
+
 fn cached_speaker_icons_at(
   assets_root: &Path,
   requests: Vec<SpeakerIconRequest>,
@@ -472,6 +469,40 @@ pub async fn clear_assets() -> Result<(), String> {
   clear_assets_at(&assets_dir())
 }
 
+/// Size of the waveforms cached on disk in bytes, 0 while the cache is disabled.
+#[tauri::command]
+#[specta::specta]
+pub async fn get_waveform_cache_size(state: State<'_, AppState>) -> Result<u64, String> {
+  let store = state
+    .waveform_store
+    .read()
+    .map_err(|e| e.to_string())?
+    .clone();
+  Ok(store.map_or(0, |store| store.size()))
+}
+
+#[tauri::command]
+#[specta::specta]
+pub async fn clear_waveform_cache(state: State<'_, AppState>) -> Result<(), String> {
+  let _operation = ASSET_OPERATION_LOCK.lock().await;
+  clear_stored_waveforms(&state).await
+}
+
+/// Removes the waveforms cached on disk, including those left by a disabled cache.
+async fn clear_stored_waveforms(state: &AppState) -> Result<(), String> {
+  let store = state
+    .waveform_store
+    .read()
+    .map_err(|e| e.to_string())?
+    .clone();
+  tauri::async_runtime::spawn_blocking(move || match store {
+    Some(store) => store.clear(),
+    None => clear_assets_at(&waveform_store_dir()),
+  })
+  .await
+  .map_err(|e| format!("Waveform cache task failed: {e}"))?
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
@@ -498,6 +529,29 @@ mod tests {
     );
   }
 
+  #[test]
+  fn stored_icons_keep_valid_copies_and_replace_broken_ones() {
+    let directory = tempfile::tempdir().unwrap();
+    let root = directory.path().join("assets");
+    let icon = request("bundled-speaker", 2);
+    let other_png = b"\x89PNG\r\n\x1a\nother";
+
+    assert!(store_speaker_icon_at(&root, &icon, b"not a png").is_err());
+    store_speaker_icon_at(&root, &icon, VALID_PNG).unwrap();
+    store_speaker_icon_at(&root, &icon, other_png).unwrap();
+    assert_eq!(
+      cached_speaker_icon_at(&root, &icon).unwrap().as_deref(),
+      Some(VALID_PNG)
+    );
+
+    fs::write(speaker_icon_path(&root, &icon).unwrap(), b"broken").unwrap();
+    store_speaker_icon_at(&root, &icon, other_png).unwrap();
+    assert_eq!(
+      cached_speaker_icon_at(&root, &icon).unwrap().as_deref(),
+      Some(&other_png[..])
+    );
+  }
+
   #[test]
   fn cached_icons_require_small_regular_png_files() {
     let directory = tempfile::tempdir().unwrap();
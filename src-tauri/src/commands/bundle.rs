@@ -0,0 +1,244 @@
+use super::assets::{cached_speaker_icon_at, store_speaker_icon_at, SpeakerIconRequest};
+use super::core::{
+  cached_model_identity, installed_characters, seed_waveform_cache, synthesize_cached,
+  voice_model_files,
+};
+use super::project::{read_project_toml, write_project_toml};
+use super::render::resolve_project_blocks;
+use crate::atomic_write::write_atomically;
+use crate::bundle::{
+  read_bundle, write_bundle, Bundle, BundleIcon, BundleManifest, BundleTake, BUNDLE_FORMAT_VERSION,
+};
+use crate::cache_key::waveform_cache_key;
+use crate::config::manager::assets_dir;
+use crate::config::types::Project;
+use crate::synthesis::SynthesisBackend;
+use crate::AppState;
+
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+use std::io::Cursor;
+use std::path::Path;
+use tauri::{AppHandle, State};
+use voicevox_core::StyleId;
+
+#[derive(Serialize, specta::Type)]
+pub struct LoadedBundle {
+  pub project: Project,
+  /// The migrations that upgraded the bundled project file, oldest first.
+  pub migrations: Vec<String>,
+  /// Styles the project uses that no installed voice model provides.
+  pub missing_style_ids: Vec<StyleId>,
+  /// Voice model files the project was made with that are not loaded.
+  pub missing_vvm_files: Vec<String>,
+  /// How many rendered takes went into the waveform cache.
+  pub cached_takes: u32,
+}
+
+/// Packs a project into a `.azpz` bundle with the rendered take of every block, the icons of its
+/// speakers and the voice models it needs. Returns the path written.
+#[tauri::command]
+#[specta::specta]
+pub async fn export_bundle(
+  app: AppHandle,
+  state: State<'_, AppState>,
+  project: Project,
+  path: String,
+) -> Result<String, String> {
+  let project_toml = write_project_toml(&project)?;
+  let blocks = resolve_project_blocks(&state, &project).await?;
+
+  let mut takes = Vec::with_capacity(blocks.len());
+  let mut wavs = BTreeMap::new();
+  for block in blocks {
+    // the query the take was synthesized from, so loading the bundle can seed the cache with it
+    let query = serde_json::to_string(&block.audio_query).map_err(|e| e.to_string())?;
+    let model_identity = cached_model_identity(&state, block.speaker_id).await;
+    let hash = waveform_cache_key(
+      &block.audio_query,
+      block.speaker_id,
+      model_identity.as_deref().unwrap_or_default(),
+    )?
+    .0;
+    if !wavs.contains_key(&hash) {
+      let wav = synthesize_cached(
+        &app,
+        &state,
+        block.audio_query,
+        block.speaker_id,
+        None,
+        SynthesisBackend::Blocking,
+      )
+      .await?;
+      wavs.insert(hash.clone(), wav);
+    }
+    takes.push(BundleTake {
+      block_id: block.block_id,
+      style_id: block.speaker_id.0,
+      query,
+      hash,
+      model_identity,
+    });
+  }
+
+  let mut style_ids = takes.iter().map(|take| take.style_id).collect::<Vec<_>>();
+  style_ids.sort_unstable();
+  style_ids.dedup();
+  let characters = installed_characters(&state).await?;
+  let used_styles = characters
+    .iter()
+    .flat_map(|character| {
+      character
+        .styles
+        .iter()
+        .map(move |style| (character.speaker_uuid.clone(), style.id.0))
+    })
+    .filter(|(_, style_id)| style_ids.contains(style_id))
+    .collect::<Vec<_>>();
+  let mut speaker_uuids = used_styles
+    .iter()
+    .map(|(speaker_uuid, _)| speaker_uuid.clone())
+    .collect::<Vec<_>>();
+  speaker_uuids.sort();
+  speaker_uuids.dedup();
+
+  // only icons that were already downloaded, bundling never goes online
+  let assets_root = assets_dir();
+  let mut icons = Vec::new();
+  let mut pngs = Vec::new();
+  for (speaker_uuid, style_id) in used_styles {
+    let request = SpeakerIconRequest {
+      speaker_uuid,
+      style_id,
+    };
+    if let Ok(Some(png)) = cached_speaker_icon_at(&assets_root, &request) {
+      icons.push(BundleIcon {
+        speaker_uuid: request.speaker_uuid,
+        style_id,
+      });
+      pngs.push(png);
+    }
+  }
+
+  let vvm_files = voice_model_files(
+    &state,
+    &style_ids.iter().copied().map(StyleId).collect::<Vec<_>>(),
+  )
+  .await?;
+  let bundle = Bundle {
+    manifest: BundleManifest {
+      format_version: BUNDLE_FORMAT_VERSION,
+      style_ids,
+      speaker_uuids,
+      vvm_files,
+      takes,
+      icons,
+    },
+    project_toml,
+    takes: wavs,
+    icons: pngs,
+  };
+  let bytes = tauri::async_runtime::spawn_blocking(move || {
+    let mut bytes = Cursor::new(Vec::new());
+    write_bundle(&bundle, &mut bytes)?;
+    Ok::<_, String>(bytes.into_inner())
+  })
+  .await
+  .map_err(|e| format!("Bundle task failed: {e}"))??;
+
+  let path = if !path.ends_with(".azpz") {
+    format!("{path}.azpz")
+  } else {
+    path
+  };
+  write_atomically(Path::new(&path), &bytes, "project bundle")?;
+  Ok(path)
+}
+
+/// Opens a `.azpz` bundle. Its takes are put into the waveform cache so nothing has to be
+/// synthesized again, and its icons into the assets cache. Only takes made with the voice model
+/// installed for their style are cached.
+#[tauri::command]
+#[specta::specta]
+pub async fn load_bundle(
+  app: AppHandle,
+  state: State<'_, AppState>,
+  path: String,
+) -> Result<LoadedBundle, String> {
+  let bytes = std::fs::read(&path).map_err(|e| e.to_string())?;
+  let bundle = tauri::async_runtime::spawn_blocking(move || read_bundle(Cursor::new(bytes)))
+    .await
+    .map_err(|e| format!("Bundle task failed: {e}"))??;
+  let (project, migrations) = read_project_toml(&bundle.project_toml)?;
+  let manifest = &bundle.manifest;
+
+  let assets_root = assets_dir();
+  for (icon, png) in manifest.icons.iter().zip(&bundle.icons) {
+    let request = SpeakerIconRequest {
+      speaker_uuid: icon.speaker_uuid.clone(),
+      style_id: icon.style_id,
+    };
+    if let Err(error) = store_speaker_icon_at(&assets_root, &request, png) {
+      eprintln!("Failed to cache bundled speaker icon: {error}");
+    }
+  }
+
+  let installed = installed_characters(&state)
+    .await
+    .unwrap_or_default()
+    .iter()
+    .flat_map(|character| &character.styles)
+    .map(|style| style.id.0)
+    .collect::<HashSet<_>>();
+  let missing_style_ids = manifest
+    .style_ids
+    .iter()
+    .filter(|style_id| !installed.contains(style_id))
+    .map(|style_id| StyleId(*style_id))
+    .collect();
+  let loaded_files = voice_model_files(
+    &state,
+    &manifest
+      .style_ids
+      .iter()
+      .copied()
+      .map(StyleId)
+      .collect::<Vec<_>>(),
+  )
+  .await
+  .unwrap_or_default();
+  let missing_vvm_files = manifest
+    .vvm_files
+    .iter()
+    .filter(|file| !loaded_files.contains(file))
+    .cloned()
+    .collect();
+
+  let cached_takes = if state.wav_lru.read().await.is_some() {
+    let mut waveforms = Vec::new();
+    for take in &manifest.takes {
+      let style_id = StyleId(take.style_id);
+      let installed_model = cached_model_identity(&state, style_id).await;
+      if !take.made_with(installed_model.as_deref()) {
+        continue;
+      }
+      let Some(wav) = bundle.takes.get(&take.hash) else {
+        continue;
+      };
+      if let Ok(query) = serde_json::from_str(&take.query) {
+        waveforms.push((query, style_id, wav.clone()));
+      }
+    }
+    seed_waveform_cache(&app, &state, waveforms).await? as u32
+  } else {
+    0
+  };
+
+  Ok(LoadedBundle {
+    project,
+    migrations,
+    missing_style_ids,
+    missing_vvm_files,
+    cached_takes,
+  })
+}
@@ -1,6 +1,7 @@
 use super::utils::{state_mut, state_ref};
 use crate::config::range::{get_range as _get_range, RangeMap};
 use crate::config::{AzaleaConfig, ConfigManager};
+use crate::recovery::RecoverableSession;
 use crate::AppState;
 
 use tauri::{Manager, State};
@@ -11,8 +12,14 @@ pub struct InitializationEvent {
   pub config: Option<AzaleaConfig>,
   pub core_initialized: bool,
   pub metas: Option<VoiceModelMeta>,
+  /// The core profile `metas` came from.
+  #[serde(default)]
+  pub core_profile: Option<String>,
   pub range: Vec<(voicevox_core::StyleId, (f32, f32))>,
   pub error: Option<String>,
+  /// Unsaved work left behind by earlier sessions, newest first.
+  #[serde(default)]
+  pub recoverable_sessions: Vec<RecoverableSession>,
 }
 
 #[derive(Clone, serde::Deserialize, serde::Serialize, specta::Type, tauri_specta::Event)]
@@ -27,8 +34,10 @@ pub async fn initialize(app: tauri::AppHandle) -> InitializationEvent {
         config: None,
         core_initialized: false,
         metas: None,
+        core_profile: None,
         range: _get_range().into_iter().collect(),
         error: Some(error.to_string()),
+        recoverable_sessions: state.recovery.recoverable_sessions(),
       }
     }
   };
@@ -40,29 +49,34 @@ pub async fn initialize(app: tauri::AppHandle) -> InitializationEvent {
     .replace(config_manager);
 
   let error = if let Some(core_config) = config.core.clone() {
-    super::core::initialize_core(&state, core_config)
+    super::core::initialize_core(&state, core_config, config.active_core_profile.clone())
       .await
       .err()
   } else {
     None
   };
+  if error.is_none() {
+    super::core::watch_voice_models(&app).await;
+  }
   let core_initialized = error.is_none() && state.core.read().await.is_some();
-  let metas = if core_initialized {
-    state
-      .core
-      .read()
-      .await
-      .as_ref()
-      .map(|core| core.metas.values().flatten().cloned().collect())
+  let (metas, core_profile) = if core_initialized {
+    let guard = state.core.read().await;
+    let core = guard.as_ref();
+    (
+      core.map(|core| core.models().metas.values().flatten().cloned().collect()),
+      core.and_then(|core| core.profile.clone()),
+    )
   } else {
-    None
+    (None, None)
   };
   InitializationEvent {
     config: Some(config),
     core_initialized,
     metas,
+    core_profile,
     range: _get_range().into_iter().collect(),
     error,
+    recoverable_sessions: state.recovery.recoverable_sessions(),
   }
 }
 
@@ -91,6 +105,7 @@ pub async fn set_config(
   state: State<'_, AppState>,
   config: AzaleaConfig,
 ) -> std::result::Result<(), String> {
+  config.validate_core_profiles()?;
   state_mut!(state, config_manager).setter(config);
   state_mut!(state, config_manager)
     .save()
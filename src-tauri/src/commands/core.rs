@@ -1,78 +1,131 @@
-use super::utils::state_mut;
-use crate::async_job::run_cancellable;
+use super::utils::{state_mut, state_ref};
+use crate::async_job::{run_cancellable, QueuedJob};
+use crate::cache_key::{query_cache_key, waveform_cache_key};
+use crate::cache_report::{CacheKind, CacheReport};
+use crate::config::manager::waveform_store_dir;
+use crate::config::types::Preset;
 use crate::config::CoreConfig;
+use crate::model_residency::ModelResidencyReport;
+use crate::render::fit_block_duration;
 use crate::spectrogram::{
   create_spectrogram_preview, validate_spectrogram_request, SpectrogramJob, SpectrogramJobEvent,
   SpectrogramJobRequest,
 };
 use crate::synthesis::{
   eviction_events, SynthesisBackend, SynthesisJob, SynthesisJobEvent, SynthesisJobRequest,
-  SynthesisJobState, WaveformCacheEntry, WaveformCacheOwner,
+  SynthesisJobState, WaveformCache, WaveformCacheEntry, WaveformCacheKey, WaveformCacheOwner,
 };
-use crate::AppState;
+use crate::waveform_store::WaveformStore;
 use crate::{audio::AudioPlayer, core::Core};
+use crate::{AppState, QueryLruType, WavLruType};
 
 use std::future::Future;
 #[cfg(test)]
 use std::io::Cursor;
 use std::num::NonZeroUsize;
-use std::sync::Arc;
+use std::path::Path;
+use std::sync::{Arc, PoisonError};
+use std::time::Duration;
+
+use notify::{RecursiveMode, Watcher};
 
 use tauri::{AppHandle, Emitter, Manager, State};
 use tauri_plugin_dialog::DialogExt;
 use tauri_specta::Event;
-use tokio::sync::OnceCell;
+use tokio::sync::{OnceCell, OwnedSemaphorePermit};
 use voicevox_core::{AccentPhrase, AudioQuery, StyleId, VoiceModelMeta};
 
 /// Load the voicevox core and create lru cache
 #[tauri::command]
 #[specta::specta]
 pub async fn init_core(
+  app: AppHandle,
   state: State<'_, AppState>,
   config: CoreConfig,
 ) -> std::result::Result<(), String> {
-  initialize_core(&state, config).await
+  let profile = active_core_profile(&state);
+  initialize_core(&state, config, profile).await?;
+  watch_voice_models(&app).await;
+  Ok(())
+}
+
+/// The profile the saved config says is active.
+fn active_core_profile(state: &AppState) -> Option<String> {
+  state
+    .config_manager
+    .read()
+    .ok()?
+    .as_ref()?
+    .config
+    .active_core_profile
+    .clone()
+}
+
+async fn start_core(config: CoreConfig, profile: Option<String>) -> Result<Core, String> {
+  tauri::async_runtime::spawn_blocking(move || {
+    let mut core = Core::init(&config)?;
+    core.profile = profile;
+    Ok::<_, anyhow::Error>(core)
+  })
+  .await
+  .map_err(|e| format!("Core initialization task failed: {e}"))?
+  .map_err(|e| e.to_string())
 }
 
 pub async fn initialize_core(
   state: &AppState,
   config: CoreConfig,
+  profile: Option<String>,
 ) -> std::result::Result<(), String> {
+  config.validate_cache_sizes()?;
   if state.core.read().await.is_none() {
-    let core_config = config.clone();
-    let core = tauri::async_runtime::spawn_blocking(move || Core::init(&core_config))
-      .await
-      .map_err(|e| format!("Core initialization task failed: {e}"))?
-      .map_err(|e| e.to_string())?;
+    let core = start_core(config.clone(), profile).await?;
+    set_parallel_jobs(state, core.workers);
     state.core.write().await.replace(Arc::new(core));
   } else {
     return Err("Core already loaded".into());
   }
   // initialize LRU caches for waveforms
   if state.wav_lru.read().await.is_none() {
-    if config.cache_size != 0 {
-      let lru = lru::LruCache::new(
-        NonZeroUsize::new(config.cache_size).ok_or("cache_size must be non-zero")?,
-      );
-      state.wav_lru.write().await.replace(lru);
-    }
+    *state.wav_lru.write().await = new_waveform_cache(&config);
   } else {
     return Err("LRU cache already initialized".into());
   }
   if state.query_lru.read().map_err(|e| e.to_string())?.is_none() {
-    if config.cache_size != 0 {
-      let lru = lru::LruCache::new(
-        NonZeroUsize::new(config.cache_size).ok_or("cache_size must be non-zero")?,
-      );
-      state
-        .query_lru
-        .write()
-        .map_err(|e| e.to_string())?
-        .replace(lru);
-    }
+    *state.query_lru.write().map_err(|e| e.to_string())? = new_query_cache(&config);
   } else {
     return Err("LRU cache already initialized".into());
   }
+  open_waveform_store(state, &config).await
+}
+
+/// The waveform cache sized by `config`, or none when `cache_size` disables the caches. The
+/// sizes are checked by `CoreConfig::validate_cache_sizes` beforehand.
+fn new_waveform_cache(config: &CoreConfig) -> Option<WavLruType> {
+  if config.cache_size == 0 {
+    return None;
+  }
+  let budget_bytes = config.waveform_cache_mb.saturating_mul(1024 * 1024);
+  Some(WaveformCache::new(budget_bytes))
+}
+
+fn new_query_cache(config: &CoreConfig) -> Option<QueryLruType> {
+  NonZeroUsize::new(config.cache_size).map(lru::LruCache::new)
+}
+
+/// Replaces the query and waveform caches with empty ones sized by `config`. Owners of the
+/// dropped waveforms are told they were evicted.
+async fn reset_caches(
+  app: &AppHandle,
+  state: &AppState,
+  config: &CoreConfig,
+) -> Result<(), String> {
+  let waveform_cache = new_waveform_cache(config);
+  let evicted = std::mem::replace(&mut *state.wav_lru.write().await, waveform_cache)
+    .map(|mut cache| cache.clear())
+    .unwrap_or_default();
+  *state.query_lru.write().map_err(|e| e.to_string())? = new_query_cache(config);
+  emit_eviction_events(app, evicted);
   Ok(())
 }
 
@@ -81,45 +134,262 @@ pub async fn initialize_core(
 #[tauri::command]
 #[specta::specta]
 pub async fn reinit_core(
+  app: AppHandle,
   state: State<'_, AppState>,
   config: CoreConfig,
 ) -> std::result::Result<(), String> {
-  reinitialize_core(&state, config).await
+  let profile = active_core_profile(&state);
+  reinitialize_core(&app, &state, config, profile).await?;
+  watch_voice_models(&app).await;
+  Ok(())
 }
 
 pub async fn reinitialize_core(
+  app: &AppHandle,
   state: &AppState,
   config: CoreConfig,
+  profile: Option<String>,
 ) -> std::result::Result<(), String> {
-  let core_config = config.clone();
-  let core = tauri::async_runtime::spawn_blocking(move || Core::init(&core_config))
-    .await
-    .map_err(|e| format!("Core initialization task failed: {e}"))?
-    .map_err(|e| e.to_string())?;
+  config.validate_cache_sizes()?;
+  let budget_changed = waveform_store_budget(&config)
+    != state
+      .waveform_store
+      .read()
+      .map_err(|e| e.to_string())?
+      .as_ref()
+      .map_or(0, |store| store.budget_bytes());
+  let core = start_core(config.clone(), profile).await?;
+  set_parallel_jobs(state, core.workers);
   state.core.write().await.replace(Arc::new(core));
+  // what the caches hold may have come from models or a dictionary the new core does not use
+  reset_caches(app, state, &config).await?;
+  if budget_changed {
+    open_waveform_store(state, &config).await?;
+  }
   Ok(())
 }
 
+fn waveform_store_budget(config: &CoreConfig) -> u64 {
+  config.waveform_store_mb.saturating_mul(1024 * 1024)
+}
+
+/// Opens the waveform cache on disk with the budget of `config`, or closes it when the budget is
+/// 0. Stored waveforms are kept either way.
+async fn open_waveform_store(state: &AppState, config: &CoreConfig) -> Result<(), String> {
+  let budget_bytes = waveform_store_budget(config);
+  let store = if budget_bytes == 0 {
+    None
+  } else {
+    let store = tauri::async_runtime::spawn_blocking(move || {
+      WaveformStore::open(waveform_store_dir(), budget_bytes)
+    })
+    .await
+    .map_err(|e| format!("Waveform cache task failed: {e}"))?;
+    Some(Arc::new(store))
+  };
+  *state.waveform_store.write().map_err(|e| e.to_string())? = store;
+  Ok(())
+}
+
+/// Runs as many queued jobs at once as the core has workers.
+fn set_parallel_jobs(state: &AppState, workers: usize) {
+  state.synthesis_queue.set_max_running(workers);
+  state.spectrogram_queue.set_max_running(workers);
+}
+
+/// The characters of the running core, tagged with the profile it was started from.
+#[derive(Clone, serde::Serialize, specta::Type)]
+pub struct ProfileMetas {
+  pub profile: Option<String>,
+  pub metas: VoiceModelMeta,
+}
+
+/// Reloads the core from another profile and makes it the active one in the saved config. The
+/// config only changes once the core has loaded. ONNX Runtime is loaded once per run, so every
+/// profile keeps using the runtime library of the first one.
+#[tauri::command]
+#[specta::specta]
+pub async fn switch_core_profile(
+  app: AppHandle,
+  state: State<'_, AppState>,
+  name: String,
+) -> std::result::Result<ProfileMetas, String> {
+  let config = state_ref!(state, config_manager)
+    .getter()
+    .core_profile(&name)?
+    .config
+    .clone();
+  reinitialize_core(&app, &state, config, Some(name.clone())).await?;
+  watch_voice_models(&app).await;
+  state_mut!(state, config_manager)
+    .config
+    .activate_core_profile(&name)?;
+  state_mut!(state, config_manager)
+    .save()
+    .map_err(|e| e.to_string())?;
+  Ok(ProfileMetas {
+    profile: Some(name),
+    metas: installed_characters(&state).await?,
+  })
+}
+
 /// Gets metas from voicevox core
 #[tauri::command]
 #[specta::specta]
 pub async fn get_metas(state: State<'_, AppState>) -> std::result::Result<VoiceModelMeta, String> {
-  let metas = state
+  installed_characters(&state).await
+}
+
+/// Lists the voice models the core keeps loaded, most recently used first, with their estimated
+/// memory and the configured limits.
+#[tauri::command]
+#[specta::specta]
+pub async fn model_residency(
+  state: State<'_, AppState>,
+) -> std::result::Result<ModelResidencyReport, String> {
+  let core = state.core.read().await;
+  let core = core.as_ref().ok_or("core is not initialized")?;
+  Ok(core.model_residency())
+}
+
+/// Every character of the loaded core, flattened across voice models.
+pub(crate) async fn installed_characters(
+  state: &AppState,
+) -> std::result::Result<VoiceModelMeta, String> {
+  let core = state.core.read().await;
+  let core = core.as_ref().ok_or("core is not initialized")?;
+  let metas = core.models().metas.values().flatten().cloned().collect();
+  Ok(metas)
+}
+
+/// How many of `style_ids`, loaded in order, fit within the model limits together.
+pub(crate) async fn styles_within_model_limits(
+  state: &AppState,
+  style_ids: &[StyleId],
+) -> std::result::Result<usize, String> {
+  let core = state.core.read().await;
+  let core = core.as_ref().ok_or("core is not initialized")?;
+  Ok(core.speakers_within_limits(style_ids))
+}
+
+/// File names of the loaded voice models that provide any of `style_ids`, sorted.
+pub(crate) async fn voice_model_files(
+  state: &AppState,
+  style_ids: &[StyleId],
+) -> std::result::Result<Vec<String>, String> {
+  let core = state.core.read().await;
+  let core = core.as_ref().ok_or("core is not initialized")?;
+  let mut files = core
+    .models()
+    .metas
+    .iter()
+    .filter(|(_, characters)| {
+      characters
+        .iter()
+        .flat_map(|character| &character.styles)
+        .any(|style| style_ids.contains(&style.id))
+    })
+    .filter_map(|(path, _)| Some(Path::new(path).file_name()?.to_string_lossy().into_owned()))
+    .collect::<Vec<_>>();
+  files.sort();
+  files.dedup();
+  Ok(files)
+}
+
+/// Sent when voice models were added to, removed from or changed in the running core's model
+/// directory.
+#[derive(Clone, serde::Deserialize, serde::Serialize, specta::Type, tauri_specta::Event)]
+pub struct VoiceModelsChangedEvent {
+  pub profile: Option<String>,
+  pub metas: VoiceModelMeta,
+}
+
+/// How long the model directory has to stay quiet before it is reloaded, so a model that is
+/// still being copied in is read once it is complete.
+const MODEL_WATCH_DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Watches the model directory of the running core, replacing the watcher of any previous core.
+/// Failing to watch only loses live updates, so it is logged rather than returned.
+pub(crate) async fn watch_voice_models(app: &AppHandle) {
+  let state = app.state::<AppState>();
+  let Some(vvm_dir) = state
     .core
     .read()
     .await
     .as_ref()
-    .ok_or("core is not initialized")?
-    .metas
-    .clone();
-  Ok(metas.values().flatten().cloned().collect())
+    .map(|core| core.vvm_dir.clone())
+  else {
+    return;
+  };
+  let (sender, receiver) = std::sync::mpsc::channel();
+  let watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+    let Ok(event) = event else {
+      return;
+    };
+    // removing a folder only reports the folder, not the models inside it
+    let touches_models = event.kind.is_remove()
+      || event
+        .paths
+        .iter()
+        .any(|path| path.extension() == Some("vvm".as_ref()));
+    if !event.kind.is_access() && touches_models {
+      let _ = sender.send(());
+    }
+  });
+  let mut watcher = match watcher {
+    Ok(watcher) => watcher,
+    Err(error) => {
+      eprintln!("Failed to watch the voice model directory: {error}");
+      return;
+    }
+  };
+  if let Err(error) = watcher.watch(&vvm_dir, RecursiveMode::Recursive) {
+    eprintln!("Failed to watch the voice model directory: {error}");
+    return;
+  }
+  state
+    .model_watcher
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .replace(watcher);
+
+  let app = app.clone();
+  std::thread::spawn(move || {
+    // ends when the watcher, and with it the sender, is replaced
+    while receiver.recv().is_ok() {
+      while receiver.recv_timeout(MODEL_WATCH_DEBOUNCE).is_ok() {}
+      tauri::async_runtime::block_on(reload_voice_models(&app));
+    }
+  });
 }
 
-async fn run_core_task<T, F>(state: &AppState, task: F) -> Result<T, String>
-where
-  T: Send + 'static,
-  F: FnOnce(Arc<Core>) -> Result<T, String> + Send + 'static,
-{
+async fn reload_voice_models(app: &AppHandle) {
+  let state = app.state::<AppState>();
+  let reloaded = run_exclusive_core_task(&state, |core| {
+    let changed = core.reload_models().map_err(|e| e.to_string())?;
+    Ok(changed.then(|| VoiceModelsChangedEvent {
+      profile: core.profile.clone(),
+      metas: core.models().metas.values().flatten().cloned().collect(),
+    }))
+  })
+  .await;
+  match reloaded {
+    Ok(Some(event)) => {
+      if let Err(error) = event.emit(app) {
+        eprintln!("Failed to emit voice model change event: {error}");
+      }
+    }
+    Ok(None) => {}
+    Err(error) => eprintln!("Failed to reload voice models: {error}"),
+  }
+}
+
+/// The running core and a permit to run a task on it. An exclusive permit waits until no other
+/// task runs on the core.
+async fn core_task_permit(
+  state: &AppState,
+  exclusive: bool,
+) -> Result<(Arc<Core>, OwnedSemaphorePermit), String> {
   let core = state
     .core
     .read()
@@ -127,12 +397,44 @@ where
     .as_ref()
     .cloned()
     .ok_or("core is not initialized")?;
-  let permit = state
-    .core_task_gate
-    .clone()
-    .acquire_owned()
-    .await
-    .map_err(|_| "Core task worker is unavailable")?;
+  let gate = core.task_gate();
+  let permit = if exclusive {
+    gate.acquire_many_owned(core.workers as u32).await
+  } else {
+    gate.acquire_owned().await
+  }
+  .map_err(|_| "Core task worker is unavailable")?;
+  Ok((core, permit))
+}
+
+pub(crate) async fn run_core_task<T, F>(state: &AppState, task: F) -> Result<T, String>
+where
+  T: Send + 'static,
+  F: FnOnce(Arc<Core>) -> Result<T, String> + Send + 'static,
+{
+  let (core, permit) = core_task_permit(state, false).await?;
+  spawn_core_task(core, permit, task).await
+}
+
+/// Runs a task while no other task runs on the core, for work that unloads models.
+async fn run_exclusive_core_task<T, F>(state: &AppState, task: F) -> Result<T, String>
+where
+  T: Send + 'static,
+  F: FnOnce(Arc<Core>) -> Result<T, String> + Send + 'static,
+{
+  let (core, permit) = core_task_permit(state, true).await?;
+  spawn_core_task(core, permit, task).await
+}
+
+async fn spawn_core_task<T, F>(
+  core: Arc<Core>,
+  permit: OwnedSemaphorePermit,
+  task: F,
+) -> Result<T, String>
+where
+  T: Send + 'static,
+  F: FnOnce(Arc<Core>) -> Result<T, String> + Send + 'static,
+{
   tauri::async_runtime::spawn_blocking(move || {
     let _permit = permit;
     task(core)
@@ -146,44 +448,20 @@ async fn run_nonblocking_synthesis_task(
   audio_query: AudioQuery,
   speaker_id: StyleId,
 ) -> Result<Vec<u8>, String> {
-  let core = state
-    .core
-    .read()
-    .await
-    .as_ref()
-    .cloned()
-    .ok_or("core is not initialized")?;
-  let _permit = state
-    .core_task_gate
-    .clone()
-    .acquire_owned()
-    .await
-    .map_err(|_| "Core task worker is unavailable")?;
+  let (core, _permit) = core_task_permit(state, false).await?;
   core
     .synthesis_nonblocking_prepared(&audio_query, speaker_id)
     .await
     .map_err(|e| e.to_string())
 }
 
-async fn prepare_nonblocking_synthesis_task(
+pub(crate) async fn prepare_synthesis_task(
   state: &AppState,
   speaker_id: StyleId,
 ) -> Result<(), String> {
-  let core = state
-    .core
-    .read()
-    .await
-    .as_ref()
-    .cloned()
-    .ok_or("core is not initialized")?;
-  let _permit = state
-    .core_task_gate
-    .clone()
-    .acquire_owned()
-    .await
-    .map_err(|_| "Core task worker is unavailable")?;
+  let (core, _permit) = core_task_permit(state, false).await?;
   core
-    .prepare_nonblocking_synthesis(speaker_id)
+    .prepare_synthesis(speaker_id)
     .await
     .map_err(|e| e.to_string())
 }
@@ -196,17 +474,41 @@ pub async fn audio_query(
   text: String,
   speaker_id: StyleId,
 ) -> std::result::Result<AudioQuery, String> {
-  if let Some(cache) = state_mut!(state, query_lru).get(&(text.clone(), speaker_id)) {
+  cached_audio_query(&state, text, speaker_id).await
+}
+
+/// Creates an audio query through `query_lru`, shared by commands that need queries for text.
+pub(crate) async fn cached_audio_query(
+  state: &AppState,
+  text: String,
+  speaker_id: StyleId,
+) -> std::result::Result<AudioQuery, String> {
+  let cache_key = {
+    let core = state.core.read().await;
+    let core = core.as_ref().ok_or("core is not initialized")?;
+    let model_identity = core.model_identity(speaker_id).unwrap_or_default();
+    query_cache_key(
+      &text,
+      speaker_id,
+      &model_identity,
+      &core.dictionary_identity(),
+    )
+  };
+  if let Some(cache) = state_mut!(state, query_lru).get(&cache_key) {
+    state.query_cache_statistics.record_hit();
     return Ok(cache.clone());
   }
-  let cache_key = (text.clone(), speaker_id);
-  let query = run_core_task(&state, move |core| {
+  state.query_cache_statistics.record_miss();
+  let query = run_core_task(state, move |core| {
     core
       .audio_query(&text, speaker_id)
       .map_err(|e| e.to_string())
   })
   .await?;
-  state_mut!(state, query_lru).put(cache_key, query.clone());
+  let dropped = state_mut!(state, query_lru).push(cache_key.clone(), query.clone());
+  if dropped.is_some_and(|(key, _)| key != cache_key) {
+    state.query_cache_statistics.record_evictions(1);
+  }
   Ok(query)
 }
 
@@ -272,6 +574,19 @@ pub async fn replace_mora_duration(
   .await
 }
 
+/// Scales the phoneme lengths of a block's query so it lasts `target_duration` seconds once
+/// `preset` and its pause are applied, within 50-200% speed
+#[tauri::command]
+#[specta::specta]
+pub fn fit_query_duration(
+  query: AudioQuery,
+  preset: Preset,
+  pause_after_ms: Option<f32>,
+  target_duration: f64,
+) -> std::result::Result<AudioQuery, String> {
+  fit_block_duration(&query, &preset, pause_after_ms, target_duration)
+}
+
 #[tauri::command]
 #[specta::specta]
 /// Queues a synthesis request and returns without waiting for inference.
@@ -334,7 +649,10 @@ pub async fn cancel_synthesis(
   Ok(())
 }
 
-fn emit_synthesis_events(app: &AppHandle, events: impl IntoIterator<Item = SynthesisJobEvent>) {
+pub(crate) fn emit_synthesis_events(
+  app: &AppHandle,
+  events: impl IntoIterator<Item = SynthesisJobEvent>,
+) {
   for event in events {
     if let Err(error) = event.emit(app) {
       eprintln!("Failed to emit synthesis job event: {error}");
@@ -342,6 +660,7 @@ fn emit_synthesis_events(app: &AppHandle, events: impl IntoIterator<Item = Synth
   }
 }
 
+/// Starts queued synthesis jobs as the queue allows, running each in its own task.
 pub fn start_synthesis_worker(app: AppHandle) {
   tauri::async_runtime::spawn(async move {
     loop {
@@ -349,53 +668,55 @@ pub fn start_synthesis_worker(app: AppHandle) {
         let state = app.state::<AppState>();
         state.synthesis_queue.next().await
       };
-      let cancellation = job.cancellation;
-      let job = job.job;
-      emit_synthesis_events(&app, [job.identity.event(SynthesisJobState::Running, None)]);
+      tauri::async_runtime::spawn(run_synthesis_job(app.clone(), job));
+    }
+  });
+}
 
-      let result = {
-        let state = app.state::<AppState>();
-        let preparation = match job.backend {
-          SynthesisBackend::Blocking => Ok(()),
-          SynthesisBackend::Nonblocking => {
-            prepare_nonblocking_synthesis_task(&state, job.request.speaker_id).await
-          }
-        };
-        match preparation {
-          Err(error) => Some(Err(error)),
-          Ok(()) => {
-            let synthesis = synthesize_cached(
-              &app,
-              &state,
-              job.request.audio_query,
-              job.request.speaker_id,
-              Some(WaveformCacheOwner {
-                identity: job.identity.clone(),
-              }),
-              job.backend,
-            );
-            match job.backend {
-              SynthesisBackend::Blocking => Some(synthesis.await),
-              SynthesisBackend::Nonblocking => run_cancellable(&cancellation, synthesis).await,
-            }
-          }
-        }
-      };
+async fn run_synthesis_job(app: AppHandle, job: QueuedJob<SynthesisJob>) {
+  let cancellation = job.cancellation;
+  let job = job.job;
+  emit_synthesis_events(&app, [job.identity.event(SynthesisJobState::Running, None)]);
 
-      let is_current = {
-        let state = app.state::<AppState>();
-        state.synthesis_queue.finish(&job.identity)
-      };
-      if !is_current {
-        continue;
+  let result = {
+    let state = app.state::<AppState>();
+    let preparation = match job.backend {
+      SynthesisBackend::Blocking => Ok(()),
+      SynthesisBackend::Nonblocking => prepare_synthesis_task(&state, job.request.speaker_id).await,
+    };
+    match preparation {
+      Err(error) => Some(Err(error)),
+      Ok(()) => {
+        let synthesis = synthesize_cached(
+          &app,
+          &state,
+          job.request.audio_query,
+          job.request.speaker_id,
+          Some(WaveformCacheOwner {
+            identity: job.identity.clone(),
+          }),
+          job.backend,
+        );
+        match job.backend {
+          SynthesisBackend::Blocking => Some(synthesis.await),
+          SynthesisBackend::Nonblocking => run_cancellable(&cancellation, synthesis).await,
+        }
       }
-      let event = match result.expect("an uncancelled synthesis job must have a result") {
-        Ok(_) => job.identity.event(SynthesisJobState::Completed, None),
-        Err(error) => job.identity.event(SynthesisJobState::Failed, Some(error)),
-      };
-      emit_synthesis_events(&app, [event]);
     }
-  });
+  };
+
+  let is_current = {
+    let state = app.state::<AppState>();
+    state.synthesis_queue.finish(&job.identity)
+  };
+  if !is_current {
+    return;
+  }
+  let event = match result.expect("an uncancelled synthesis job must have a result") {
+    Ok(_) => job.identity.event(SynthesisJobState::Completed, None),
+    Err(error) => job.identity.event(SynthesisJobState::Failed, Some(error)),
+  };
+  emit_synthesis_events(&app, [event]);
 }
 
 #[tauri::command]
@@ -436,6 +757,7 @@ fn emit_spectrogram_events(app: &AppHandle, events: impl IntoIterator<Item = Spe
   }
 }
 
+/// Starts queued spectrogram jobs as the queue allows, running each in its own task.
 pub fn start_spectrogram_worker(app: AppHandle) {
   tauri::async_runtime::spawn(async move {
     loop {
@@ -443,64 +765,119 @@ pub fn start_spectrogram_worker(app: AppHandle) {
         let state = app.state::<AppState>();
         state.spectrogram_queue.next().await
       };
-      let cancellation = queued.cancellation;
-      let job = queued.job;
-      emit_spectrogram_events(
-        &app,
-        [job.identity.event(SynthesisJobState::Running, None, None)],
-      );
+      tauri::async_runtime::spawn(run_spectrogram_job(app.clone(), queued));
+    }
+  });
+}
 
-      let result = {
-        let state = app.state::<AppState>();
-        match prepare_nonblocking_synthesis_task(&state, job.request.speaker_id).await {
-          Err(error) => Some(Err(error)),
-          Ok(()) => {
-            let synthesis = synthesize_cached(
-              &app,
-              &state,
-              job.request.audio_query,
-              job.request.speaker_id,
-              None,
-              SynthesisBackend::Nonblocking,
-            );
-            match run_cancellable(&cancellation, synthesis).await {
-              None => None,
-              Some(Err(error)) => Some(Err(error)),
-              Some(Ok(wav)) => {
-                let extraction = async move {
-                  tauri::async_runtime::spawn_blocking(move || create_spectrogram_preview(wav))
-                    .await
-                    .map_err(|e| format!("Spectrogram task failed: {e}"))?
-                };
-                run_cancellable(&cancellation, extraction).await
-              }
-            }
+async fn run_spectrogram_job(app: AppHandle, queued: QueuedJob<SpectrogramJob>) {
+  let cancellation = queued.cancellation;
+  let job = queued.job;
+  emit_spectrogram_events(
+    &app,
+    [job.identity.event(SynthesisJobState::Running, None, None)],
+  );
+
+  let result = {
+    let state = app.state::<AppState>();
+    match prepare_synthesis_task(&state, job.request.speaker_id).await {
+      Err(error) => Some(Err(error)),
+      Ok(()) => {
+        let synthesis = synthesize_cached(
+          &app,
+          &state,
+          job.request.audio_query,
+          job.request.speaker_id,
+          None,
+          SynthesisBackend::Nonblocking,
+        );
+        match run_cancellable(&cancellation, synthesis).await {
+          None => None,
+          Some(Err(error)) => Some(Err(error)),
+          Some(Ok(wav)) => {
+            let extraction = async move {
+              tauri::async_runtime::spawn_blocking(move || create_spectrogram_preview(wav))
+                .await
+                .map_err(|e| format!("Spectrogram task failed: {e}"))?
+            };
+            run_cancellable(&cancellation, extraction).await
           }
         }
-      };
+      }
+    }
+  };
 
-      let is_current = {
-        let state = app.state::<AppState>();
-        state.spectrogram_queue.finish(&job.identity)
-      };
-      if !is_current {
+  let is_current = {
+    let state = app.state::<AppState>();
+    state.spectrogram_queue.finish(&job.identity)
+  };
+  if !is_current {
+    return;
+  }
+  let event = match result.expect("an uncancelled spectrogram job must have a result") {
+    Ok(preview) => job
+      .identity
+      .event(SynthesisJobState::Completed, None, Some(preview)),
+    Err(error) => job
+      .identity
+      .event(SynthesisJobState::Failed, Some(error), None),
+  };
+  emit_spectrogram_events(&app, [event]);
+}
+
+/// Adds waveforms synthesized elsewhere with the installed voice models to the cache so they are
+/// not synthesized again. Queries that are already cached are left alone. Returns how many
+/// waveforms were added.
+pub(crate) async fn seed_waveform_cache(
+  app: &AppHandle,
+  state: &AppState,
+  waveforms: impl IntoIterator<Item = (AudioQuery, StyleId, Vec<u8>)>,
+) -> std::result::Result<usize, String> {
+  let mut keyed = Vec::new();
+  for (query, speaker_id, wav) in waveforms {
+    keyed.push((cached_waveform_key(state, &query, speaker_id).await?, wav));
+  }
+  let mut evicted = Vec::new();
+  let mut added = 0;
+  {
+    let mut cache_guard = state.wav_lru.write().await;
+    let cache = cache_guard.as_mut().ok_or("wav_lru is not initialized")?;
+    for (key, wav) in keyed {
+      if cache.contains(&key) {
         continue;
       }
-      let event = match result.expect("an uncancelled spectrogram job must have a result") {
-        Ok(preview) => job
-          .identity
-          .event(SynthesisJobState::Completed, None, Some(preview)),
-        Err(error) => job
-          .identity
-          .event(SynthesisJobState::Failed, Some(error), None),
-      };
-      emit_spectrogram_events(&app, [event]);
+      let cell = Arc::new(OnceCell::new_with(Some(wav)));
+      evicted.extend(cache.push(key, WaveformCacheEntry::new(cell)));
+      added += 1;
     }
-  });
+  }
+  evict_waveforms(app, state, evicted);
+  Ok(added)
+}
+
+/// Counts waveforms dropped from the cache to make room and tells their owners.
+fn evict_waveforms(
+  app: &AppHandle,
+  state: &AppState,
+  evicted: Vec<(WaveformCacheKey, WaveformCacheEntry)>,
+) {
+  state
+    .waveform_cache_statistics
+    .record_evictions(evicted.len());
+  emit_eviction_events(app, evicted);
+}
+
+fn emit_eviction_events(app: &AppHandle, evicted: Vec<(WaveformCacheKey, WaveformCacheEntry)>) {
+  emit_synthesis_events(
+    app,
+    evicted
+      .into_iter()
+      .flat_map(|(_, entry)| eviction_events(entry)),
+  );
 }
 
 /// Synthesizes or retrieves a waveform without holding the shared cache lock during inference.
-async fn synthesize_cached(
+pub(crate) async fn synthesize_cached(
   app: &AppHandle,
   state: &AppState,
   audio_query: AudioQuery,
@@ -508,40 +885,46 @@ async fn synthesize_cached(
   owner: Option<WaveformCacheOwner>,
   backend: SynthesisBackend,
 ) -> std::result::Result<Vec<u8>, String> {
-  let query_string = serde_json::to_string(&audio_query).map_err(|e| e.to_string())?;
-  let cache_key = (query_string, speaker_id);
-  let (cell, evicted) = {
+  let model_identity = cached_model_identity(state, speaker_id).await;
+  let cache_key = waveform_cache_key(
+    &audio_query,
+    speaker_id,
+    model_identity.as_deref().unwrap_or_default(),
+  )?;
+  // waveforms of styles without a known voice model are never stored on disk
+  let store_key = model_identity.map(|_| cache_key.0.clone());
+  let (cell, evicted, waiting) = {
     let mut cache_guard = state.wav_lru.write().await;
     let cache = cache_guard.as_mut().ok_or("wav_lru is not initialized")?;
     if let Some(entry) = cache.get(&cache_key) {
-      (entry.cell.clone(), None)
+      // another task is synthesizing the same waveform, which is waited for below
+      let waiting = entry.cell.get().is_none();
+      if !waiting {
+        state.waveform_cache_statistics.record_hit();
+      }
+      (entry.cell.clone(), Vec::new(), waiting)
     } else {
+      state.waveform_cache_statistics.record_miss();
       let cell = Arc::new(OnceCell::new());
       let evicted = cache.push(cache_key.clone(), WaveformCacheEntry::new(cell.clone()));
-      (cell, evicted)
+      (cell, evicted, false)
     }
   };
-  if let Some((_, entry)) = evicted {
-    emit_synthesis_events(app, eviction_events(entry));
-  }
-
-  let query_for_task = audio_query.clone();
-  let wav = initialize_waveform_cell(&cell, || async {
-    match backend {
-      SynthesisBackend::Blocking => {
-        run_core_task(state, move |core| {
-          core
-            .synthesis(&query_for_task, speaker_id)
-            .map_err(|e| e.to_string())
-        })
-        .await
-      }
-      SynthesisBackend::Nonblocking => {
-        run_nonblocking_synthesis_task(state, query_for_task, speaker_id).await
-      }
-    }
+  evict_waveforms(app, state, evicted);
+
+  let (wav, initialized_here) = initialize_waveform_cell_here(&cell, || {
+    stored_or_synthesized(state, audio_query, speaker_id, store_key, backend)
   })
-  .await?;
+  .await;
+  if waiting {
+    // the task waited for may have been cancelled, leaving this one to synthesize the waveform
+    if initialized_here {
+      state.waveform_cache_statistics.record_miss();
+    } else {
+      state.waveform_cache_statistics.record_coalesced_wait();
+    }
+  }
+  let wav = wav?;
 
   let evicted = {
     let mut cache_guard = state.wav_lru.write().await;
@@ -557,7 +940,8 @@ async fn synthesize_cached(
           .expect("cache entry disappeared while locked")
           .add_owner(owner);
       }
-      None
+      // the waveform only has a size now that it is synthesized
+      cache.reweigh(&cache_key)
     } else {
       let mut entry = WaveformCacheEntry::new(cell.clone());
       if let Some(owner) = owner {
@@ -566,12 +950,80 @@ async fn synthesize_cached(
       cache.push(cache_key, entry)
     }
   };
-  if let Some((_, entry)) = evicted {
-    emit_synthesis_events(app, eviction_events(entry));
+  evict_waveforms(app, state, evicted);
+  Ok(wav)
+}
+
+/// Reads the waveform of `store_key` from the cache on disk, or synthesizes it and stores it
+/// there.
+async fn stored_or_synthesized(
+  state: &AppState,
+  audio_query: AudioQuery,
+  speaker_id: StyleId,
+  store_key: Option<String>,
+  backend: SynthesisBackend,
+) -> Result<Vec<u8>, String> {
+  let store = state
+    .waveform_store
+    .read()
+    .map_err(|e| e.to_string())?
+    .clone();
+  let stored = store.zip(store_key);
+  if let Some((store, key)) = stored.clone() {
+    let wav = tauri::async_runtime::spawn_blocking(move || store.get(&key))
+      .await
+      .ok()
+      .flatten();
+    if let Some(wav) = wav {
+      return Ok(wav);
+    }
+  }
+
+  let wav = match backend {
+    SynthesisBackend::Blocking => {
+      run_core_task(state, move |core| {
+        core
+          .synthesis(&audio_query, speaker_id)
+          .map_err(|e| e.to_string())
+      })
+      .await
+    }
+    SynthesisBackend::Nonblocking => {
+      run_nonblocking_synthesis_task(state, audio_query, speaker_id).await
+    }
+  }?;
+
+  if let Some((store, key)) = stored {
+    let stored_wav = wav.clone();
+    // written in the background, the caller already has the waveform
+    tauri::async_runtime::spawn_blocking(move || {
+      if let Err(error) = store.put(&key, &stored_wav) {
+        eprintln!("Failed to store a waveform: {error}");
+      }
+    });
   }
   Ok(wav)
 }
 
+/// The identity of the voice model of `speaker_id` in the running core, if it has one.
+pub(crate) async fn cached_model_identity(state: &AppState, speaker_id: StyleId) -> Option<String> {
+  state.core.read().await.as_ref()?.model_identity(speaker_id)
+}
+
+/// The key of the waveform of `audio_query` in the caches, for the running core.
+pub(crate) async fn cached_waveform_key(
+  state: &AppState,
+  audio_query: &AudioQuery,
+  speaker_id: StyleId,
+) -> Result<WaveformCacheKey, String> {
+  let model_identity = cached_model_identity(state, speaker_id).await;
+  waveform_cache_key(
+    audio_query,
+    speaker_id,
+    model_identity.as_deref().unwrap_or_default(),
+  )
+}
+
 /// Coalesces waveform and spectrogram consumers of the same cache entry so only one initializer
 /// performs inference. If that initializer is cancelled, `OnceCell` leaves the entry empty and a
 /// waiting consumer may take over.
@@ -586,6 +1038,25 @@ where
   cell.get_or_try_init(initialize).await.cloned()
 }
 
+/// Like `initialize_waveform_cell`, also telling whether `initialize` ran here rather than the
+/// waveform coming from another consumer.
+async fn initialize_waveform_cell_here<F, Fut>(
+  cell: &OnceCell<Vec<u8>>,
+  initialize: F,
+) -> (Result<Vec<u8>, String>, bool)
+where
+  F: FnOnce() -> Fut,
+  Fut: Future<Output = Result<Vec<u8>, String>>,
+{
+  let mut initialized_here = false;
+  let wav = initialize_waveform_cell(cell, || {
+    initialized_here = true;
+    initialize()
+  })
+  .await;
+  (wav, initialized_here)
+}
+
 #[tauri::command]
 #[specta::specta]
 pub async fn play_audio(
@@ -629,6 +1100,9 @@ pub struct AudioSequenceItem {
 #[tauri::command]
 #[specta::specta]
 /// Synthesizes and queues multiple audio queries for uninterrupted playback.
+///
+/// Items are played as sent: the editor applies presets and block pauses to each query and leaves
+/// muted blocks out, the same way `resolve_project_blocks` does for renders.
 pub async fn play_audio_sequence(
   app: AppHandle,
   state: State<'_, AppState>,
@@ -744,27 +1218,64 @@ pub async fn pick_core(app: AppHandle) -> Option<CoreConfig> {
 #[tauri::command]
 #[specta::specta]
 pub async fn clear_caches(app: AppHandle, state: State<'_, AppState>) -> Result<(), String> {
-  let eviction_events = {
+  let evicted = {
     let mut cache_guard = state.wav_lru.write().await;
     let cache = cache_guard.as_mut().ok_or("wav_lru is not initialized")?;
-    let events = cache
-      .iter()
-      .flat_map(|(_, entry)| {
-        entry
-          .owners
-          .iter()
-          .map(|owner| owner.identity.event(SynthesisJobState::Evicted, None))
-          .collect::<Vec<_>>()
-      })
-      .collect::<Vec<_>>();
-    cache.clear();
-    events
+    cache.clear()
   };
-  emit_synthesis_events(&app, eviction_events);
+  emit_eviction_events(&app, evicted);
   state_mut!(state, query_lru).clear();
   Ok(())
 }
 
+/// Lists what the query and waveform caches hold, most recently used first, and how lookups in
+/// them went since the app started.
+#[tauri::command]
+#[specta::specta]
+pub async fn inspect_caches(state: State<'_, AppState>) -> Result<CacheReport, String> {
+  let waveforms = state.wav_lru.read().await;
+  let queries = state.query_lru.read().map_err(|e| e.to_string())?;
+  Ok(CacheReport::new(
+    waveforms.as_ref(),
+    state.waveform_cache_statistics.counts(),
+    queries.as_ref(),
+    state.query_cache_statistics.counts(),
+  ))
+}
+
+/// Drops one entry listed by `inspect_caches`. The owners of an evicted waveform are told, the
+/// same as when the cache makes room. Returns whether the entry was cached.
+#[tauri::command]
+#[specta::specta]
+pub async fn evict_cache_entry(
+  app: AppHandle,
+  state: State<'_, AppState>,
+  cache: CacheKind,
+  key: String,
+  style_id: StyleId,
+) -> Result<bool, String> {
+  let key = (key, style_id);
+  match cache {
+    CacheKind::Waveform => {
+      let evicted = {
+        let mut cache_guard = state.wav_lru.write().await;
+        let waveforms = cache_guard.as_mut().ok_or("wav_lru is not initialized")?;
+        waveforms.pop(&key).map(|entry| (key, entry))
+      };
+      let found = evicted.is_some();
+      evict_waveforms(&app, &state, evicted.into_iter().collect());
+      Ok(found)
+    }
+    CacheKind::Query => {
+      let found = state_mut!(state, query_lru).pop(&key).is_some();
+      if found {
+        state.query_cache_statistics.record_evictions(1);
+      }
+      Ok(found)
+    }
+  }
+}
+
 #[derive(specta::Type, Clone, Debug, serde::Serialize)]
 pub enum SynthState {
   /// not started yet or not present in cache (dropped automatically)
@@ -785,10 +1296,10 @@ pub async fn synthesize_state(
   query: AudioQuery,
   speaker_id: StyleId,
 ) -> std::result::Result<SynthState, String> {
-  let query_string = serde_json::to_string(&query).map_err(|e| e.to_string())?;
+  let key = cached_waveform_key(&state, &query, speaker_id).await?;
   let mut cache_guard = state.wav_lru.write().await;
   let cache = cache_guard.as_mut().ok_or("wav_lru is not initialized")?;
-  if let Some(entry) = cache.get(&(query_string, speaker_id)) {
+  if let Some(entry) = cache.get(&key) {
     if entry.cell.get().is_some() {
       return Ok(SynthState::Done);
     } else {
@@ -802,6 +1313,7 @@ pub async fn synthesize_state(
 #[cfg(test)]
 mod tests {
   use super::*;
+  use futures_util::FutureExt;
   use serde_json::json;
   use std::sync::atomic::{AtomicUsize, Ordering};
 
@@ -900,6 +1412,26 @@ mod tests {
     });
   }
 
+  #[test]
+  fn a_consumer_taking_over_from_a_cancelled_initializer_initializes_the_cell_itself() {
+    tauri::async_runtime::block_on(async {
+      let cell = OnceCell::new();
+      // dropped while synthesizing, which leaves the cell empty
+      let cancelled = initialize_waveform_cell(&cell, std::future::pending);
+      assert!(cancelled.now_or_never().is_none());
+
+      let (wav, initialized_here) =
+        initialize_waveform_cell_here(&cell, || async { Ok(vec![1, 2, 3]) }).await;
+      assert_eq!(wav.unwrap(), vec![1, 2, 3]);
+      assert!(initialized_here);
+
+      let (wav, initialized_here) =
+        initialize_waveform_cell_here(&cell, || async { Ok(vec![9, 9, 9]) }).await;
+      assert_eq!(wav.unwrap(), vec![1, 2, 3]);
+      assert!(!initialized_here);
+    });
+  }
+
   #[test]
   fn spectrogram_preview_is_compact_normalized_and_duration_aware() {
     let preview = create_spectrogram_preview(wav(1, 24_000, 12_000)).unwrap();
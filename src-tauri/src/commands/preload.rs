@@ -0,0 +1,110 @@
+use super::core::{installed_characters, prepare_synthesis_task, styles_within_model_limits};
+use super::project::validate_project;
+use crate::async_job::run_cancellable;
+use crate::config::types::Project;
+use crate::render::project_style_ids;
+use crate::AppState;
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, State};
+use tauri_specta::Event;
+use voicevox_core::StyleId;
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize, specta::Type)]
+pub enum ModelPreloadState {
+  Running,
+  Completed,
+  Cancelled,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, specta::Type, tauri_specta::Event)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelPreloadEvent {
+  pub generation_id: u64,
+  pub state: ModelPreloadState,
+  /// The style whose model was just loaded.
+  pub style_id: Option<StyleId>,
+  pub completed: usize,
+  pub total: usize,
+  /// Why loading `style_id` failed. The preload goes on with the other styles.
+  pub error: Option<String>,
+  /// Styles left out because their models would push the ones loaded before them past
+  /// `max_loaded_models` or `model_memory_budget_mb`. Not counted in `total`.
+  pub skipped: Vec<StyleId>,
+}
+
+fn emit_model_preload(app: &AppHandle, event: ModelPreloadEvent) {
+  if let Err(error) = event.emit(app) {
+    eprintln!("Failed to emit model preload progress: {error}");
+  }
+}
+
+/// Loads the voice models a project speaks with in the background, so its first playback does
+/// not wait for them. Progress is reported through `ModelPreloadEvent`s, and a newer preload
+/// cancels this one. Loading stops before the first model that would unload one loaded earlier.
+/// Returns the generation ID of the preload.
+#[tauri::command]
+#[specta::specta]
+pub async fn preload_project_models(
+  app: AppHandle,
+  state: State<'_, AppState>,
+  project: Project,
+) -> Result<u64, String> {
+  validate_project(&project)?;
+  let characters = installed_characters(&state).await?;
+  let mut style_ids = project_style_ids(&project, &characters);
+  let fitting = styles_within_model_limits(&state, &style_ids).await?;
+  let skipped = style_ids.split_off(fitting);
+  let (generation_id, cancellation) = state.model_preload.start();
+
+  tauri::async_runtime::spawn(async move {
+    let state = app.state::<AppState>();
+    let mut event = ModelPreloadEvent {
+      generation_id,
+      state: ModelPreloadState::Running,
+      style_id: None,
+      completed: 0,
+      total: style_ids.len(),
+      error: None,
+      skipped,
+    };
+    for style_id in style_ids {
+      let load = prepare_synthesis_task(&state, style_id);
+      let Some(loaded) = run_cancellable(&cancellation, load).await else {
+        emit_model_preload(
+          &app,
+          ModelPreloadEvent {
+            state: ModelPreloadState::Cancelled,
+            style_id: None,
+            error: None,
+            ..event
+          },
+        );
+        return;
+      };
+      event.completed += 1;
+      event.style_id = Some(style_id);
+      event.error = loaded.err();
+      emit_model_preload(&app, event.clone());
+    }
+    state.model_preload.finish(generation_id);
+    emit_model_preload(
+      &app,
+      ModelPreloadEvent {
+        state: ModelPreloadState::Completed,
+        style_id: None,
+        error: None,
+        ..event
+      },
+    );
+  });
+  Ok(generation_id)
+}
+
+/// Cancels the running preload. A model that is already loading finishes first. Returns whether a
+/// preload was running.
+#[tauri::command]
+#[specta::specta]
+pub async fn cancel_model_preload(state: State<'_, AppState>) -> Result<bool, String> {
+  Ok(state.model_preload.cancel())
+}
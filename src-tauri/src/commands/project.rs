@@ -1,22 +1,41 @@
-use crate::config::types::{Preset, Project, TextBlockProps};
+use super::core::installed_characters;
+use super::render::resolve_project_blocks;
+use crate::atomic_write::write_atomically;
+use crate::config::types::{
+  BlockTiming, Preset, Project, ProjectMetadata, ProjectSection, TextBlockProps,
+};
+use crate::migration::{migrate_project, CURRENT_PROJECT_SCHEMA_VERSION};
+use crate::subtitle::parse_subtitles;
+use crate::vvproj::{parse_vvproj, write_vvproj, ImportedProject};
+use crate::AppState;
 use serde::{Deserialize, Serialize};
-use std::{collections::HashSet, fs, path::Path, result::Result};
+use std::{
+  cmp::Reverse,
+  collections::HashSet,
+  fs,
+  io::ErrorKind,
+  path::{Path, PathBuf},
+  result::Result,
+  time::{SystemTime, UNIX_EPOCH},
+};
+use tauri::State;
 use voicevox_core::AudioQuery;
 
-const CURRENT_PROJECT_SCHEMA_VERSION: u32 = 1;
-
 #[derive(Serialize)]
 struct ProjectFileRef<'a> {
   schema_version: u32,
+  metadata: &'a ProjectMetadata,
   blocks: Vec<ProjectBlockRef<'a>>,
   presets: &'a [Preset],
+  sections: &'a [ProjectSection],
 }
 
 #[derive(Deserialize)]
 struct ProjectFile {
-  schema_version: u32,
+  metadata: ProjectMetadata,
   blocks: Vec<ProjectBlock>,
   presets: Vec<Preset>,
+  sections: Vec<ProjectSection>,
 }
 
 #[derive(Serialize)]
@@ -26,6 +45,16 @@ struct ProjectBlockRef<'a> {
   #[serde(skip_serializing_if = "Option::is_none")]
   query_override: Option<&'a AudioQuery>,
   preset_id: Option<&'a str>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  timing: Option<&'a BlockTiming>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  comment: Option<&'a str>,
+  #[serde(skip_serializing_if = "std::ops::Not::not")]
+  muted: bool,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pause_after_ms: Option<f32>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  section_id: Option<&'a str>,
 }
 
 #[derive(Deserialize)]
@@ -35,9 +64,46 @@ struct ProjectBlock {
   #[serde(default)]
   query_override: Option<AudioQuery>,
   preset_id: Option<String>,
+  #[serde(default)]
+  timing: Option<BlockTiming>,
+  #[serde(default)]
+  comment: Option<String>,
+  #[serde(default)]
+  muted: bool,
+  #[serde(default)]
+  pause_after_ms: Option<f32>,
+  #[serde(default)]
+  section_id: Option<String>,
+}
+
+fn validate_metadata(metadata: &ProjectMetadata) -> Result<(), String> {
+  for (field, value) in [("title", &metadata.title), ("author", &metadata.author)] {
+    if value.contains(['\n', '\r']) {
+      return Err(format!("Project {field} must be a single line"));
+    }
+  }
+  let mut tags = HashSet::with_capacity(metadata.tags.len());
+  for (index, tag) in metadata.tags.iter().enumerate() {
+    if tag.trim().is_empty() || tag.trim() != tag {
+      return Err(format!(
+        "Project tag {index} is empty or has surrounding whitespace"
+      ));
+    }
+    if !tags.insert(tag) {
+      return Err(format!("Project tag {index} is a duplicate"));
+    }
+  }
+  if let (Some(created_ms), Some(modified_ms)) = (metadata.created_ms, metadata.modified_ms) {
+    if modified_ms < created_ms {
+      return Err("Project was modified before it was created".into());
+    }
+  }
+  Ok(())
 }
 
-fn validate_project(project: &Project) -> Result<(), String> {
+pub(crate) fn validate_project(project: &Project) -> Result<(), String> {
+  validate_metadata(&project.metadata)?;
+
   let mut preset_ids = HashSet::with_capacity(project.presets.len());
   for (index, preset) in project.presets.iter().enumerate() {
     if preset.id.trim().is_empty() {
@@ -63,6 +129,19 @@ fn validate_project(project: &Project) -> Result<(), String> {
     }
   }
 
+  let mut section_ids = HashSet::with_capacity(project.sections.len());
+  for (index, section) in project.sections.iter().enumerate() {
+    if section.id.trim().is_empty() {
+      return Err(format!("Project section {index} has an empty ID"));
+    }
+    if !section_ids.insert(&section.id) {
+      return Err(format!("Project section {index} has a duplicate ID"));
+    }
+    if section.name.trim().is_empty() {
+      return Err(format!("Project section {index} has an empty name"));
+    }
+  }
+
   let mut block_ids = HashSet::with_capacity(project.blocks.len());
   for (index, block) in project.blocks.iter().enumerate() {
     if block.id.trim().is_empty() {
@@ -76,6 +155,20 @@ fn validate_project(project: &Project) -> Result<(), String> {
         "Project block {index} marks a missing query as modified"
       ));
     }
+    if let Some(timing) = &block.timing {
+      if !timing.start.is_finite()
+        || !timing.end.is_finite()
+        || timing.start < 0.0
+        || timing.end < timing.start
+      {
+        return Err(format!("Project block {index} has an invalid timing"));
+      }
+    }
+    if let Some(pause_after_ms) = block.pause_after_ms {
+      if !pause_after_ms.is_finite() || pause_after_ms < 0.0 {
+        return Err(format!("Project block {index} has an invalid pause"));
+      }
+    }
     if let Some(preset_id) = &block.preset_id {
       if !preset_ids.contains(preset_id) {
         return Err(format!(
@@ -83,21 +176,24 @@ fn validate_project(project: &Project) -> Result<(), String> {
         ));
       }
     }
+    if let Some(section_id) = &block.section_id {
+      if !section_ids.contains(section_id) {
+        return Err(format!(
+          "Project block {index} references missing section {section_id}"
+        ));
+      }
+    }
   }
 
   Ok(())
 }
 
-#[tauri::command]
-#[specta::specta]
-pub async fn save_project(
-  project: Project,
-  path: String,
-  allow_create: bool,
-) -> Result<(), String> {
-  validate_project(&project)?;
-  let project_toml = toml::to_string_pretty(&ProjectFileRef {
+/// Serializes a valid project in the current `.azp` schema.
+pub(crate) fn write_project_toml(project: &Project) -> Result<String, String> {
+  validate_project(project)?;
+  toml::to_string_pretty(&ProjectFileRef {
     schema_version: CURRENT_PROJECT_SCHEMA_VERSION,
+    metadata: &project.metadata,
     blocks: project
       .blocks
       .iter()
@@ -110,35 +206,27 @@ pub async fn save_project(
           None
         },
         preset_id: block.preset_id.as_deref(),
+        timing: block.timing.as_ref(),
+        comment: block.comment.as_deref(),
+        muted: block.muted,
+        pause_after_ms: block.pause_after_ms,
+        section_id: block.section_id.as_deref(),
       })
       .collect(),
     presets: &project.presets,
+    sections: &project.sections,
   })
-  .map_err(|e| e.to_string())?;
-  let path = if !path.ends_with(".azp") {
-    format!("{path}.azp")
-  } else {
-    path
-  };
-  if Path::new(&path).exists() || allow_create {
-    fs::write(&path, project_toml).map_err(|e| e.to_string())?;
-  } else {
-    return Err(format!("Project File {path} does not exist").to_string());
-  }
-  Ok(())
+  .map_err(|e| e.to_string())
 }
 
-#[tauri::command]
-#[specta::specta]
-pub async fn load_project(path: String) -> Result<Project, String> {
-  let project_toml = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
-  let project_file: ProjectFile = toml::from_str(&project_toml).map_err(|e| e.to_string())?;
-  if project_file.schema_version != CURRENT_PROJECT_SCHEMA_VERSION {
-    return Err(format!(
-      "Unsupported project schema version {}",
-      project_file.schema_version
-    ));
-  }
+/// Reads an `.azp` document of any supported schema version and validates it. Also returns the
+/// migrations that upgraded it, oldest first.
+pub(crate) fn read_project_toml(project_toml: &str) -> Result<(Project, Vec<String>), String> {
+  let mut document: toml::Table = toml::from_str(project_toml).map_err(|e| e.to_string())?;
+  let migrations = migrate_project(&mut document)?;
+  let project_file: ProjectFile = toml::Value::Table(document)
+    .try_into()
+    .map_err(|e: toml::de::Error| e.to_string())?;
   let project = Project {
     blocks: project_file
       .blocks
@@ -149,14 +237,382 @@ pub async fn load_project(path: String) -> Result<Project, String> {
         query_is_modified: block.query_override.is_some(),
         query: block.query_override,
         preset_id: block.preset_id,
+        timing: block.timing,
+        comment: block.comment,
+        muted: block.muted,
+        pause_after_ms: block.pause_after_ms,
+        section_id: block.section_id,
       })
       .collect(),
     presets: project_file.presets,
+    metadata: project_file.metadata,
+    sections: project_file.sections,
+  };
+  validate_project(&project)?;
+  Ok((project, migrations))
+}
+
+/// Saves a project by atomically replacing the file. When the contents change, up to
+/// `backup_count` previous versions are kept next to it as `<name>.<n>.azp.bak`, newest first.
+/// Returns the metadata as saved, with its creation and modification times filled in.
+#[tauri::command]
+#[specta::specta]
+pub async fn save_project(
+  mut project: Project,
+  path: String,
+  allow_create: bool,
+  backup_count: u32,
+) -> Result<ProjectMetadata, String> {
+  validate_project(&project)?;
+  let path = if !path.ends_with(".azp") {
+    format!("{path}.azp")
+  } else {
+    path
+  };
+  if !Path::new(&path).exists() && !allow_create {
+    return Err(format!("Project File {path} does not exist").to_string());
+  }
+
+  let previous_toml = fs::read_to_string(&path).ok();
+  let previous = previous_toml
+    .as_deref()
+    .and_then(|previous_toml| read_project_toml(previous_toml).ok())
+    .map(|(previous, _)| previous.metadata)
+    .unwrap_or_default();
+  let now_ms = SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map_or(0, |elapsed| elapsed.as_millis() as u64);
+  // a project that never had a creation time takes the one of the file it replaces
+  let created_ms = *project
+    .metadata
+    .created_ms
+    .get_or_insert(previous.created_ms.unwrap_or(now_ms));
+  // only real changes move the modification time, so saving unchanged work writes nothing
+  project.metadata.modified_ms = previous.modified_ms;
+  if let (Some(previous_toml), Ok(project_toml)) = (&previous_toml, write_project_toml(&project)) {
+    if *previous_toml == project_toml {
+      return Ok(project.metadata);
+    }
+  }
+  project.metadata.modified_ms = Some(now_ms.max(created_ms));
+  let project_toml = write_project_toml(&project)?;
+  write_project_file(Path::new(&path), project_toml.as_bytes(), backup_count)?;
+  Ok(project.metadata)
+}
+
+/// Which version of a project file a backup keeps, written as the `<tag>` of
+/// `<name>.<tag>.azp.bak`.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+enum BackupTag {
+  /// A version saved over, 1 being the most recent.
+  Generation(u32),
+  /// The original of a file upgraded from this schema version, tagged `v<version>`.
+  Migrated(u32),
+}
+
+fn backup_path(path: &Path, tag: BackupTag) -> PathBuf {
+  let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+  let tag = match tag {
+    BackupTag::Generation(generation) => generation.to_string(),
+    BackupTag::Migrated(version) => format!("v{version}"),
+  };
+  path.with_file_name(format!("{stem}.{tag}.azp.bak"))
+}
+
+/// The existing backups of a project file, in no particular order.
+fn project_backups(path: &Path) -> Result<Vec<(BackupTag, PathBuf)>, String> {
+  let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+  let prefix = format!("{stem}.");
+  let directory = match path.parent() {
+    Some(parent) if !parent.as_os_str().is_empty() => parent,
+    _ => Path::new("."),
+  };
+  let entries = match fs::read_dir(directory) {
+    Ok(entries) => entries,
+    Err(error) if error.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+    Err(error) => return Err(error.to_string()),
+  };
+  Ok(
+    entries
+      .filter_map(Result::ok)
+      .filter_map(|entry| {
+        let name = entry.file_name();
+        let tag = name
+          .to_str()?
+          .strip_prefix(&prefix)?
+          .strip_suffix(".azp.bak")?;
+        let tag = match tag.strip_prefix('v') {
+          Some(version) => BackupTag::Migrated(version.parse().ok()?),
+          None => BackupTag::Generation(tag.parse().ok().filter(|generation| *generation > 0)?),
+        };
+        Some((tag, entry.path()))
+      })
+      .collect(),
+  )
+}
+
+/// Shifts every backup one generation up, dropping those past `backup_count`, so generation 1 is
+/// free for the version about to be replaced.
+fn rotate_backups(path: &Path, backup_count: u32) -> Result<(), String> {
+  let mut generations = project_backups(path)?
+    .into_iter()
+    .filter_map(|(tag, backup)| match tag {
+      BackupTag::Generation(generation) => Some((generation, backup)),
+      BackupTag::Migrated(_) => None,
+    })
+    .collect::<Vec<_>>();
+  generations.sort_by_key(|(generation, _)| Reverse(*generation));
+  for (generation, backup) in generations {
+    if generation >= backup_count {
+      fs::remove_file(&backup).map_err(|e| format!("Failed to remove old backup: {e}"))?;
+    } else {
+      fs::rename(
+        &backup,
+        backup_path(path, BackupTag::Generation(generation + 1)),
+      )
+      .map_err(|e| format!("Failed to rotate backup: {e}"))?;
+    }
+  }
+  Ok(())
+}
+
+/// Atomically replaces a project file, keeping its previous contents as the newest backup. Saving
+/// unchanged contents leaves the file and its backups alone so auto-save does not churn them.
+fn write_project_file(path: &Path, contents: &[u8], backup_count: u32) -> Result<(), String> {
+  let previous = match fs::read(path) {
+    Ok(previous) => Some(previous),
+    Err(error) if error.kind() == ErrorKind::NotFound => None,
+    Err(error) => return Err(error.to_string()),
+  };
+  if previous.as_deref() == Some(contents) {
+    return Ok(());
+  }
+  if let Some(previous) = previous.filter(|_| backup_count > 0) {
+    rotate_backups(path, backup_count)?;
+    write_atomically(
+      &backup_path(path, BackupTag::Generation(1)),
+      &previous,
+      "project backup",
+    )?;
+  }
+  write_atomically(path, contents, "project file")
+}
+
+#[derive(Serialize, specta::Type)]
+pub struct ProjectBackup {
+  pub path: String,
+  /// 1 for the version saved over most recently, none for the original of an upgraded file.
+  pub generation: Option<u32>,
+  /// The schema version of a file kept before it was upgraded.
+  pub migrated_from: Option<u32>,
+  /// When the backup was made, in milliseconds since the Unix epoch.
+  pub modified_ms: Option<u64>,
+  pub size: u64,
+}
+
+/// Lists the backups of a project file: the rotating ones newest first, then the originals of
+/// upgrades from the newest schema version down.
+#[tauri::command]
+#[specta::specta]
+pub async fn list_project_backups(path: String) -> Result<Vec<ProjectBackup>, String> {
+  let mut backups = project_backups(Path::new(&path))?;
+  backups.sort_by_key(|(tag, _)| match tag {
+    BackupTag::Generation(_) => *tag,
+    BackupTag::Migrated(version) => BackupTag::Migrated(u32::MAX - version),
+  });
+  backups
+    .into_iter()
+    .map(|(tag, backup)| {
+      let metadata = fs::metadata(&backup).map_err(|e| e.to_string())?;
+      let (generation, migrated_from) = match tag {
+        BackupTag::Generation(generation) => (Some(generation), None),
+        BackupTag::Migrated(version) => (None, Some(version)),
+      };
+      Ok(ProjectBackup {
+        path: backup.to_string_lossy().into_owned(),
+        generation,
+        migrated_from,
+        modified_ms: metadata
+          .modified()
+          .ok()
+          .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+          .map(|elapsed| elapsed.as_millis() as u64),
+        size: metadata.len(),
+      })
+    })
+    .collect()
+}
+
+/// Replaces a project file with one of its backups and loads it. The version being replaced
+/// becomes the newest backup, so restoring can itself be undone.
+#[tauri::command]
+#[specta::specta]
+pub async fn restore_project_backup(
+  backup_path: String,
+  path: String,
+  backup_count: u32,
+) -> Result<LoadedProject, String> {
+  // refuse to restore a backup that would not load
+  load_project(backup_path.clone(), false).await?;
+  let contents = fs::read(&backup_path).map_err(|e| e.to_string())?;
+  write_project_file(Path::new(&path), &contents, backup_count)?;
+  load_project(path, false).await
+}
+
+#[derive(Serialize, specta::Type)]
+pub struct LoadedProject {
+  pub project: Project,
+  /// The migrations that upgraded an older project file, oldest first.
+  pub migrations: Vec<String>,
+  /// Where the original file was copied before being upgraded.
+  pub backup_path: Option<String>,
+}
+
+/// Loads a project, upgrading files from older schema versions in memory. With
+/// `backup_migrated`, the original of an upgraded file is kept next to it as
+/// `<name>.v<version>.azp.bak` before it can be saved over in the new format.
+#[tauri::command]
+#[specta::specta]
+pub async fn load_project(path: String, backup_migrated: bool) -> Result<LoadedProject, String> {
+  let project_toml = std::fs::read_to_string(&path).map_err(|e| e.to_string())?;
+  let (project, migrations) = read_project_toml(&project_toml)?;
+
+  let backup_path = if backup_migrated && !migrations.is_empty() {
+    // every migration upgrades by exactly one version
+    let original_version = CURRENT_PROJECT_SCHEMA_VERSION - migrations.len() as u32;
+    let backup = backup_path(Path::new(&path), BackupTag::Migrated(original_version));
+    write_atomically(&backup, project_toml.as_bytes(), "project backup")?;
+    Some(backup.to_string_lossy().into_owned())
+  } else {
+    None
   };
+  Ok(LoadedProject {
+    project,
+    migrations,
+    backup_path,
+  })
+}
+
+/// Keeps the current project for crash recovery until it is saved.
+#[tauri::command]
+#[specta::specta]
+pub async fn record_recovery_snapshot(
+  state: State<'_, AppState>,
+  project: Project,
+  project_path: Option<String>,
+) -> Result<(), String> {
+  state.recovery.record(project, project_path)
+}
+
+/// Drops this session's recovery snapshot once its work has been saved.
+#[tauri::command]
+#[specta::specta]
+pub async fn discard_recovery_snapshot(state: State<'_, AppState>) -> Result<(), String> {
+  state.recovery.discard()
+}
+
+/// Reads the project an earlier session left unsaved. The snapshot is kept until it is
+/// discarded, so a failed restore can be retried.
+#[tauri::command]
+#[specta::specta]
+pub async fn restore_recovery_session(
+  state: State<'_, AppState>,
+  session_id: String,
+) -> Result<Project, String> {
+  let project = state.recovery.restore(&session_id)?;
   validate_project(&project)?;
   Ok(project)
 }
 
+/// Forgets unsaved work left by an earlier session.
+#[tauri::command]
+#[specta::specta]
+pub async fn discard_recovery_session(
+  state: State<'_, AppState>,
+  session_id: String,
+) -> Result<(), String> {
+  state.recovery.discard_session(&session_id)
+}
+
+/// Reads an SRT, WebVTT or ASS/SSA file into new blocks that keep their cue timings.
+#[tauri::command]
+#[specta::specta]
+pub async fn import_subtitles(
+  path: String,
+  preset_id: Option<String>,
+) -> Result<Vec<TextBlockProps>, String> {
+  let document = fs::read_to_string(path).map_err(|e| e.to_string())?;
+  Ok(
+    parse_subtitles(&document)?
+      .into_iter()
+      .map(|cue| TextBlockProps {
+        id: uuid::Uuid::new_v4().to_string(),
+        text: cue.text,
+        query: None,
+        query_is_modified: false,
+        preset_id: preset_id.clone(),
+        timing: cue.timing,
+        comment: None,
+        muted: false,
+        pause_after_ms: None,
+        section_id: None,
+      })
+      .collect(),
+  )
+}
+
+/// Imports a project from the official VOICEVOX editor, rebuilding presets from each item's
+/// query and matching them against the installed styles.
+#[tauri::command]
+#[specta::specta]
+pub async fn import_vvproj(
+  state: State<'_, AppState>,
+  path: String,
+) -> Result<ImportedProject, String> {
+  let document = fs::read_to_string(path).map_err(|e| e.to_string())?;
+  // without a core every style is reported as not installed
+  let characters = installed_characters(&state).await.unwrap_or_default();
+  let imported = parse_vvproj(&document, &characters)?;
+  validate_project(&imported.project)?;
+  Ok(imported)
+}
+
+/// Drops the queries the user has not edited, so they are made again by the loaded core.
+fn without_unedited_queries(mut project: Project) -> Project {
+  for block in &mut project.blocks {
+    if !block.query_is_modified {
+      block.query = None;
+    }
+  }
+  project
+}
+
+/// Exports a project for the official VOICEVOX editor. Unedited queries are regenerated by the
+/// loaded core, and every item carries its preset applied to its query.
+#[tauri::command]
+#[specta::specta]
+pub async fn export_vvproj(
+  state: State<'_, AppState>,
+  project: Project,
+  path: String,
+) -> Result<String, String> {
+  let blocks = resolve_project_blocks(&state, &without_unedited_queries(project)).await?;
+  let characters = installed_characters(&state).await?;
+  let document = write_vvproj(
+    blocks
+      .iter()
+      .map(|block| (block.text.as_str(), &block.audio_query, block.speaker_id)),
+    &characters,
+  )?;
+  let path = if !path.ends_with(".vvproj") {
+    format!("{path}.vvproj")
+  } else {
+    path
+  };
+  fs::write(&path, document).map_err(|e| e.to_string())?;
+  Ok(path)
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
@@ -174,8 +630,15 @@ mod tests {
         query: Some(sample_query()),
         query_is_modified: true,
         preset_id: Some("preset-1".into()),
+        timing: None,
+        comment: None,
+        muted: false,
+        pause_after_ms: None,
+        section_id: None,
       }],
       presets: vec![preset],
+      metadata: Default::default(),
+      sections: Vec::new(),
     }
   }
 
@@ -185,7 +648,7 @@ mod tests {
       let directory = tempfile::tempdir().unwrap();
       let path = directory.path().join("project");
 
-      save_project(project(), path.to_string_lossy().into_owned(), true)
+      save_project(project(), path.to_string_lossy().into_owned(), true, 0)
         .await
         .unwrap();
       let saved = path.with_extension("azp");
@@ -204,25 +667,45 @@ mod tests {
       assert_eq!(saved_toml["presets"][0]["id"].as_str(), Some("preset-1"));
       assert!(saved_toml["blocks"][0].get("query").is_none());
       assert!(saved_toml["blocks"][0].get("query_override").is_some());
-      let loaded = load_project(saved.to_string_lossy().into_owned())
+      let loaded = load_project(saved.to_string_lossy().into_owned(), false)
         .await
         .unwrap();
 
-      assert_eq!(loaded.blocks.len(), 1);
-      assert_eq!(loaded.blocks[0].id, "block-1");
-      assert_eq!(loaded.blocks[0].text, "こんにちは、Azalea 🌺");
-      assert!(loaded.blocks[0].query.is_some());
-      assert!(loaded.blocks[0].query_is_modified);
-      assert_eq!(loaded.blocks[0].preset_id.as_deref(), Some("preset-1"));
-      assert_eq!(loaded.presets.len(), 1);
+      assert_eq!(loaded.project.blocks.len(), 1);
+      assert_eq!(loaded.project.blocks[0].id, "block-1");
+      assert_eq!(loaded.project.blocks[0].text, "こんにちは、Azalea 🌺");
+      assert!(loaded.project.blocks[0].query.is_some());
+      assert!(loaded.project.blocks[0].query_is_modified);
+      assert_eq!(
+        loaded.project.blocks[0].preset_id.as_deref(),
+        Some("preset-1")
+      );
+      assert_eq!(loaded.project.presets.len(), 1);
       assert_eq!(
-        loaded.presets[0].speaker_uuid.as_deref(),
+        loaded.project.presets[0].speaker_uuid.as_deref(),
         Some("speaker-uuid")
       );
-      assert_eq!(loaded.presets[0].style_name.as_deref(), Some("Normal"));
+      assert_eq!(
+        loaded.project.presets[0].style_name.as_deref(),
+        Some("Normal")
+      );
     });
   }
 
+  #[test]
+  fn vvproj_exports_regenerate_only_unedited_queries() {
+    let mut project = project();
+    let mut unedited = project.blocks[0].clone();
+    unedited.id = "block-2".into();
+    unedited.query_is_modified = false;
+    project.blocks.push(unedited);
+
+    let exported = without_unedited_queries(project);
+
+    assert!(exported.blocks[0].query.is_some());
+    assert!(exported.blocks[1].query.is_none());
+  }
+
   fn sample_query() -> AudioQuery {
     serde_json::from_value(serde_json::json!({
       "accent_phrases": [{
@@ -250,13 +733,99 @@ mod tests {
     .unwrap()
   }
 
+  fn renamed(name: &str) -> Project {
+    let mut project = project();
+    project.blocks[0].text = name.into();
+    project
+  }
+
+  #[test]
+  fn saves_rotate_a_bounded_number_of_backups() {
+    tauri::async_runtime::block_on(async {
+      let directory = tempfile::tempdir().unwrap();
+      let path = directory.path().join("rotating.azp");
+      let path_string = path.to_string_lossy().into_owned();
+      for name in ["first", "second", "third", "fourth"] {
+        save_project(renamed(name), path_string.clone(), true, 2)
+          .await
+          .unwrap();
+      }
+      // saving the same contents again must not push "third" out
+      save_project(renamed("fourth"), path_string.clone(), true, 2)
+        .await
+        .unwrap();
+
+      let backups = list_project_backups(path_string.clone()).await.unwrap();
+
+      assert_eq!(
+        backups
+          .iter()
+          .map(|backup| backup.generation)
+          .collect::<Vec<_>>(),
+        vec![Some(1), Some(2)]
+      );
+      for (backup, expected) in backups.iter().zip(["third", "second"]) {
+        let loaded = load_project(backup.path.clone(), false).await.unwrap();
+        assert_eq!(loaded.project.blocks[0].text, expected);
+        assert!(backup
+          .path
+          .ends_with(&format!("rotating.{}.azp.bak", backup.generation.unwrap())));
+      }
+      let entries = fs::read_dir(directory.path()).unwrap().count();
+      assert_eq!(entries, 3, "no temporary files are left behind");
+
+      save_project(renamed("fifth"), path_string.clone(), true, 0)
+        .await
+        .unwrap();
+      assert_eq!(list_project_backups(path_string).await.unwrap().len(), 2);
+    });
+  }
+
+  #[test]
+  fn restoring_a_backup_keeps_the_replaced_version() {
+    tauri::async_runtime::block_on(async {
+      let directory = tempfile::tempdir().unwrap();
+      let path = directory.path().join("restore.azp");
+      let path_string = path.to_string_lossy().into_owned();
+      for name in ["old", "new"] {
+        save_project(renamed(name), path_string.clone(), true, 3)
+          .await
+          .unwrap();
+      }
+      let backup = list_project_backups(path_string.clone()).await.unwrap()[0]
+        .path
+        .clone();
+
+      let restored = restore_project_backup(backup, path_string.clone(), 3)
+        .await
+        .unwrap();
+
+      assert_eq!(restored.project.blocks[0].text, "old");
+      let backups = list_project_backups(path_string.clone()).await.unwrap();
+      let newest = load_project(backups[0].path.clone(), false).await.unwrap();
+      assert_eq!(newest.project.blocks[0].text, "new");
+
+      let broken = directory.path().join("restore.3.azp.bak");
+      fs::write(&broken, "not a project").unwrap();
+      assert!(restore_project_backup(
+        broken.to_string_lossy().into_owned(),
+        path_string.clone(),
+        3
+      )
+      .await
+      .is_err());
+      let current = load_project(path_string, false).await.unwrap();
+      assert_eq!(current.project.blocks[0].text, "old");
+    });
+  }
+
   #[test]
   fn save_requires_creation_permission_for_new_files() {
     tauri::async_runtime::block_on(async {
       let directory = tempfile::tempdir().unwrap();
       let path = directory.path().join("new.azp");
 
-      let error = save_project(project(), path.to_string_lossy().into_owned(), false)
+      let error = save_project(project(), path.to_string_lossy().into_owned(), false, 0)
         .await
         .unwrap_err();
 
@@ -273,7 +842,7 @@ mod tests {
       let mut empty_id = project();
       empty_id.blocks[0].id = " ".into();
       let path = directory.path().join("empty-id.azp");
-      let error = save_project(empty_id, path.to_string_lossy().into_owned(), true)
+      let error = save_project(empty_id, path.to_string_lossy().into_owned(), true, 0)
         .await
         .unwrap_err();
       assert!(error.contains("empty ID"));
@@ -282,9 +851,14 @@ mod tests {
       let mut empty_preset_id = project();
       empty_preset_id.presets[0].id = " ".into();
       let path = directory.path().join("empty-preset-id.azp");
-      let error = save_project(empty_preset_id, path.to_string_lossy().into_owned(), true)
-        .await
-        .unwrap_err();
+      let error = save_project(
+        empty_preset_id,
+        path.to_string_lossy().into_owned(),
+        true,
+        0,
+      )
+      .await
+      .unwrap_err();
       assert!(error.contains("preset 0 has an empty ID"), "{error}");
       assert!(!path.exists());
 
@@ -297,6 +871,7 @@ mod tests {
         duplicate_preset_id,
         path.to_string_lossy().into_owned(),
         true,
+        0,
       )
       .await
       .unwrap_err();
@@ -306,7 +881,7 @@ mod tests {
       let mut missing_query = project();
       missing_query.blocks[0].query = None;
       let path = directory.path().join("missing-query.azp");
-      let error = save_project(missing_query, path.to_string_lossy().into_owned(), true)
+      let error = save_project(missing_query, path.to_string_lossy().into_owned(), true, 0)
         .await
         .unwrap_err();
       assert!(error.contains("missing query"));
@@ -319,6 +894,7 @@ mod tests {
         incomplete_fallback,
         path.to_string_lossy().into_owned(),
         true,
+        0,
       )
       .await
       .unwrap_err();
@@ -332,15 +908,17 @@ mod tests {
     tauri::async_runtime::block_on(async {
       let directory = tempfile::tempdir().unwrap();
       let missing = directory.path().join("missing.azp");
-      assert!(load_project(missing.to_string_lossy().into_owned())
+      assert!(load_project(missing.to_string_lossy().into_owned(), false)
         .await
         .is_err());
 
       let malformed = directory.path().join("malformed.azp");
       std::fs::write(&malformed, "{").unwrap();
-      assert!(load_project(malformed.to_string_lossy().into_owned())
-        .await
-        .is_err());
+      assert!(
+        load_project(malformed.to_string_lossy().into_owned(), false)
+          .await
+          .is_err()
+      );
 
       for (name, contents) in [
         ("unversioned.azp", "blocks = []\npresets = []\n"),
@@ -368,7 +946,7 @@ mod tests {
         let path = directory.path().join(name);
         std::fs::write(&path, contents).unwrap();
         assert!(
-          load_project(path.to_string_lossy().into_owned())
+          load_project(path.to_string_lossy().into_owned(), false)
             .await
             .is_err(),
           "{name} should be rejected"
@@ -385,20 +963,212 @@ mod tests {
       let mut derived_project = project();
       derived_project.blocks[0].query_is_modified = false;
 
-      save_project(derived_project, path.to_string_lossy().into_owned(), true)
-        .await
-        .unwrap();
+      save_project(
+        derived_project,
+        path.to_string_lossy().into_owned(),
+        true,
+        0,
+      )
+      .await
+      .unwrap();
       let saved_toml: toml::Value =
         toml::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
       assert!(saved_toml["blocks"][0].get("query_override").is_none());
-      let loaded = load_project(path.to_string_lossy().into_owned())
+      let loaded = load_project(path.to_string_lossy().into_owned(), false)
         .await
         .unwrap();
-      assert!(loaded.blocks[0].query.is_none());
-      assert!(!loaded.blocks[0].query_is_modified);
+      assert!(loaded.project.blocks[0].query.is_none());
+      assert!(!loaded.project.blocks[0].query_is_modified);
     });
   }
 
+  #[test]
+  fn metadata_and_comments_round_trip_with_save_times() {
+    tauri::async_runtime::block_on(async {
+      let directory = tempfile::tempdir().unwrap();
+      let path = directory.path().join("described.azp");
+      let path_string = path.to_string_lossy().into_owned();
+      let mut described = project();
+      described.metadata = ProjectMetadata {
+        title: "あいさつ".into(),
+        author: "Azalea".into(),
+        description: "First line\nsecond line".into(),
+        tags: vec!["sample".into(), "greeting".into()],
+        core_profile: Some("nightly".into()),
+        ..Default::default()
+      };
+      described.blocks[0].comment = Some("Say it slowly".into());
+
+      let saved = save_project(described.clone(), path_string.clone(), true, 0)
+        .await
+        .unwrap();
+      let created_ms = saved.created_ms.unwrap();
+      assert_eq!(saved.modified_ms, Some(created_ms));
+      let loaded = load_project(path_string.clone(), false).await.unwrap();
+      assert_eq!(loaded.project.metadata, saved);
+      assert_eq!(
+        loaded.project.blocks[0].comment.as_deref(),
+        Some("Say it slowly")
+      );
+
+      // a project without save times keeps those of the file and unchanged work is not rewritten
+      let written = fs::read_to_string(&path).unwrap();
+      let resaved = save_project(described.clone(), path_string.clone(), true, 0)
+        .await
+        .unwrap();
+      assert_eq!(resaved, saved);
+      assert_eq!(fs::read_to_string(&path).unwrap(), written);
+
+      described.metadata = saved;
+      described.metadata.title = "こんばんは".into();
+      let changed = save_project(described, path_string, true, 0).await.unwrap();
+      assert_eq!(changed.created_ms, Some(created_ms));
+      assert!(changed.modified_ms.unwrap() >= created_ms);
+    });
+  }
+
+  #[test]
+  fn validation_rejects_malformed_metadata() {
+    let invalid = [
+      ProjectMetadata {
+        title: "two\nlines".into(),
+        ..Default::default()
+      },
+      ProjectMetadata {
+        author: "carriage\rreturn".into(),
+        ..Default::default()
+      },
+      ProjectMetadata {
+        tags: vec![" padded".into()],
+        ..Default::default()
+      },
+      ProjectMetadata {
+        tags: vec!["".into()],
+        ..Default::default()
+      },
+      ProjectMetadata {
+        tags: vec!["twice".into(), "twice".into()],
+        ..Default::default()
+      },
+      ProjectMetadata {
+        created_ms: Some(2),
+        modified_ms: Some(1),
+        ..Default::default()
+      },
+    ];
+    for metadata in invalid {
+      let mut project = project();
+      project.metadata = metadata.clone();
+      assert!(validate_project(&project).is_err(), "{metadata:?}");
+    }
+
+    let mut described = project();
+    described.metadata.description = "Notes may span\nseveral lines".into();
+    assert!(validate_project(&described).is_ok());
+  }
+
+  #[test]
+  fn sections_mutes_and_pauses_round_trip() {
+    tauri::async_runtime::block_on(async {
+      let directory = tempfile::tempdir().unwrap();
+      let path = directory.path().join("sections.azp");
+      let mut grouped = project();
+      grouped.sections = vec![ProjectSection {
+        id: "section-1".into(),
+        name: "Chapter 1".into(),
+      }];
+      grouped.blocks[0].section_id = Some("section-1".into());
+      grouped.blocks[0].muted = true;
+      grouped.blocks[0].pause_after_ms = Some(750.0);
+
+      save_project(grouped, path.to_string_lossy().into_owned(), true, 0)
+        .await
+        .unwrap();
+      let loaded = load_project(path.to_string_lossy().into_owned(), false)
+        .await
+        .unwrap();
+
+      assert_eq!(loaded.project.sections[0].name, "Chapter 1");
+      let block = &loaded.project.blocks[0];
+      assert_eq!(block.section_id.as_deref(), Some("section-1"));
+      assert!(block.muted);
+      assert_eq!(block.pause_after_ms, Some(750.0));
+
+      // defaults are left out of the file
+      save_project(project(), path.to_string_lossy().into_owned(), true, 0)
+        .await
+        .unwrap();
+      let saved_toml: toml::Value =
+        toml::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+      for key in ["muted", "pause_after_ms", "section_id"] {
+        assert!(saved_toml["blocks"][0].get(key).is_none(), "{key}");
+      }
+    });
+  }
+
+  #[test]
+  fn validation_checks_sections_and_pauses() {
+    fn section(id: &str, name: &str) -> ProjectSection {
+      ProjectSection {
+        id: id.into(),
+        name: name.into(),
+      }
+    }
+    fn error(change: impl FnOnce(&mut Project)) -> String {
+      let mut invalid = project();
+      change(&mut invalid);
+      validate_project(&invalid).unwrap_err()
+    }
+
+    assert!(
+      error(|project| project.sections = vec![section(" ", "Intro")])
+        .contains("section 0 has an empty ID")
+    );
+    assert!(error(|project| {
+      project.sections = vec![section("intro", "Intro"), section("intro", "Again")];
+    })
+    .contains("section 1 has a duplicate ID"));
+    assert!(
+      error(|project| project.sections = vec![section("intro", "")])
+        .contains("section 0 has an empty name")
+    );
+    assert!(error(|project| {
+      project.sections = vec![section("intro", "Intro")];
+      project.blocks[0].section_id = Some("outro".into());
+    })
+    .contains("references missing section outro"));
+    assert!(
+      error(|project| project.blocks[0].pause_after_ms = Some(f32::NAN)).contains("invalid pause")
+    );
+    assert!(
+      error(|project| project.blocks[0].pause_after_ms = Some(-1.0)).contains("invalid pause")
+    );
+  }
+
+  #[test]
+  fn older_projects_load_with_empty_metadata() {
+    let (project, migrations) =
+      read_project_toml(include_str!("../../tests/fixtures/projects/v1.azp")).unwrap();
+
+    assert_eq!(migrations.len() as u32, CURRENT_PROJECT_SCHEMA_VERSION - 1);
+    assert_eq!(project.metadata, ProjectMetadata::default());
+    assert!(project.blocks.iter().all(|block| block.comment.is_none()));
+
+    let (project, migrations) =
+      read_project_toml(include_str!("../../tests/fixtures/projects/v2.azp")).unwrap();
+    assert_eq!(migrations.len() as u32, CURRENT_PROJECT_SCHEMA_VERSION - 2);
+    assert_eq!(project.metadata.tags, ["sample", "greeting"]);
+    assert_eq!(
+      project.blocks[1].comment.as_deref(),
+      Some("Slow down if it sounds rushed.")
+    );
+    assert!(project.sections.is_empty());
+    assert!(project
+      .blocks
+      .iter()
+      .all(|block| block.section_id.is_none()));
+  }
+
   #[test]
   fn load_rejects_unsupported_schemas_and_invalid_current_projects() {
     tauri::async_runtime::block_on(async {
@@ -412,7 +1182,7 @@ mod tests {
         ),
       )
       .unwrap();
-      let error = load_project(newer.to_string_lossy().into_owned())
+      let error = load_project(newer.to_string_lossy().into_owned(), false)
         .await
         .err()
         .expect("unsupported project schemas should be rejected");
@@ -424,7 +1194,7 @@ mod tests {
         "schema_version = 1\npresets = []\n[[blocks]]\nid = \"same\"\ntext = \"a\"\n[[blocks]]\nid = \"same\"\ntext = \"b\"\n",
       )
       .unwrap();
-      let error = load_project(duplicate_ids.to_string_lossy().into_owned())
+      let error = load_project(duplicate_ids.to_string_lossy().into_owned(), false)
         .await
         .err()
         .expect("duplicate block IDs should be rejected");
@@ -435,6 +1205,7 @@ mod tests {
         project(),
         missing_preset_id.to_string_lossy().into_owned(),
         true,
+        0,
       )
       .await
       .unwrap();
@@ -442,7 +1213,7 @@ mod tests {
         .unwrap()
         .replace("\nid = \"preset-1\"\n", "\n");
       std::fs::write(&missing_preset_id, contents).unwrap();
-      let error = load_project(missing_preset_id.to_string_lossy().into_owned())
+      let error = load_project(missing_preset_id.to_string_lossy().into_owned(), false)
         .await
         .err()
         .expect("missing preset IDs should be rejected");
@@ -454,11 +1225,154 @@ mod tests {
         "schema_version = 1\npresets = []\n[[blocks]]\nid = \"block\"\ntext = \"a\"\npreset_id = \"missing\"\n",
       )
       .unwrap();
-      let error = load_project(missing_preset.to_string_lossy().into_owned())
+      let error = load_project(missing_preset.to_string_lossy().into_owned(), false)
         .await
         .err()
         .expect("missing preset references should be rejected");
       assert!(error.contains("references missing preset"));
     });
   }
+
+  #[test]
+  fn block_timings_round_trip_and_are_validated() {
+    tauri::async_runtime::block_on(async {
+      let directory = tempfile::tempdir().unwrap();
+      let path = directory.path().join("timed.azp");
+      let mut timed_project = project();
+      timed_project.blocks[0].timing = Some(BlockTiming {
+        start: 1.5,
+        end: 2.25,
+      });
+
+      save_project(timed_project, path.to_string_lossy().into_owned(), true, 0)
+        .await
+        .unwrap();
+      let loaded = load_project(path.to_string_lossy().into_owned(), false)
+        .await
+        .unwrap();
+      assert_eq!(
+        loaded.project.blocks[0].timing,
+        Some(BlockTiming {
+          start: 1.5,
+          end: 2.25
+        })
+      );
+
+      let mut backwards = project();
+      backwards.blocks[0].timing = Some(BlockTiming {
+        start: 2.0,
+        end: 1.0,
+      });
+      let error = save_project(backwards, path.to_string_lossy().into_owned(), true, 0)
+        .await
+        .unwrap_err();
+      assert!(error.contains("invalid timing"));
+    });
+  }
+
+  #[test]
+  fn imported_subtitles_become_timed_blocks_with_fresh_ids() {
+    tauri::async_runtime::block_on(async {
+      let directory = tempfile::tempdir().unwrap();
+      let path = directory.path().join("dub.srt");
+      std::fs::write(
+        &path,
+        "1\n00:00:01,000 --> 00:00:02,500\nこんにちは\n\n2\n00:00:03,000 --> 00:00:04,000\nまたね\n",
+      )
+      .unwrap();
+
+      let blocks = import_subtitles(path.to_string_lossy().into_owned(), Some("preset-1".into()))
+        .await
+        .unwrap();
+
+      assert_eq!(blocks.len(), 2);
+      assert_ne!(blocks[0].id, blocks[1].id);
+      assert_eq!(blocks[1].text, "またね");
+      assert_eq!(blocks[0].preset_id.as_deref(), Some("preset-1"));
+      assert!(blocks[0].query.is_none());
+      assert_eq!(
+        blocks[0].timing,
+        Some(BlockTiming {
+          start: 1.0,
+          end: 2.5
+        })
+      );
+      assert!(
+        import_subtitles("/definitely/not/a/real/path.srt".into(), None)
+          .await
+          .is_err()
+      );
+    });
+  }
+
+  #[test]
+  fn originals_of_upgraded_files_are_listed_with_the_rotating_backups() {
+    tauri::async_runtime::block_on(async {
+      let directory = tempfile::tempdir().unwrap();
+      let path = directory.path().join("upgraded.azp");
+      let path_string = path.to_string_lossy().into_owned();
+      fs::copy(
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/projects/v1.azp"),
+        &path,
+      )
+      .unwrap();
+
+      let loaded = load_project(path_string.clone(), true).await.unwrap();
+      let original = directory.path().join("upgraded.v1.azp.bak");
+      assert_eq!(
+        loaded.backup_path.as_deref(),
+        Some(original.to_string_lossy().as_ref())
+      );
+      save_project(loaded.project, path_string.clone(), true, 3)
+        .await
+        .unwrap();
+      // the upgraded file is saved over twice, which rotates only the numbered backups
+      save_project(renamed("edited"), path_string.clone(), true, 3)
+        .await
+        .unwrap();
+
+      let backups = list_project_backups(path_string).await.unwrap();
+      assert_eq!(
+        backups
+          .iter()
+          .map(|backup| (backup.generation, backup.migrated_from))
+          .collect::<Vec<_>>(),
+        vec![(Some(1), None), (Some(2), None), (None, Some(1))]
+      );
+      assert_eq!(backups[2].path, original.to_string_lossy());
+    });
+  }
+
+  #[test]
+  fn the_oldest_fixture_loads_with_its_edited_queries() {
+    tauri::async_runtime::block_on(async {
+      let fixture = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/projects/v1.azp");
+
+      let loaded = load_project(fixture.to_string_lossy().into_owned(), false)
+        .await
+        .unwrap();
+
+      assert_eq!(
+        loaded.migrations.len() as u32,
+        CURRENT_PROJECT_SCHEMA_VERSION - 1
+      );
+      assert!(loaded.backup_path.is_none());
+      let blocks = &loaded.project.blocks;
+      assert_eq!(blocks.len(), 3);
+      assert_eq!(blocks[0].text, "こんにちは");
+      assert!(blocks[0].query_is_modified);
+      assert_eq!(
+        blocks[0].query.as_ref().unwrap().accent_phrases[0]
+          .moras
+          .len(),
+        2
+      );
+      assert!(blocks[1].query.is_none());
+      assert_eq!(blocks[1].preset_id, blocks[0].preset_id);
+      assert_eq!(
+        loaded.project.presets[0].style_name.as_deref(),
+        Some("ノーマル")
+      );
+    });
+  }
 }
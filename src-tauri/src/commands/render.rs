@@ -0,0 +1,328 @@
+use super::core::{
+  cached_audio_query, cached_waveform_key, installed_characters, synthesize_cached,
+};
+use super::project::validate_project;
+use crate::config::types::{BlockTiming, Project};
+use crate::render::{apply_preset, concat_wavs, preset_style_id};
+use crate::statistics::{BlockStatistics, ProjectStatistics};
+use crate::subtitle::{write_subtitles, SubtitleCue, SubtitleFormat};
+use crate::synthesis::SynthesisBackend;
+use crate::timing::{block_offsets, query_timing, QueryTiming};
+use crate::AppState;
+
+use futures_util::stream::{self, StreamExt};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, State};
+use tauri_specta::Event;
+use voicevox_core::{AudioQuery, StyleId};
+
+#[derive(Clone, Debug, Deserialize, Serialize, specta::Type, tauri_specta::Event)]
+#[serde(rename_all = "camelCase")]
+pub struct RenderProgressEvent {
+  pub path: String,
+  pub block_id: String,
+  pub completed: usize,
+  pub total: usize,
+}
+
+/// A project block with its preset applied, ready for synthesis.
+pub(crate) struct ResolvedBlock {
+  pub block_id: String,
+  pub text: String,
+  pub audio_query: AudioQuery,
+  pub speaker_id: StyleId,
+  pub timing: Option<BlockTiming>,
+}
+
+/// Resolves every non-empty, unmuted block against its preset. Blocks without a stored query get
+/// one from `query_lru`, the same way the editor requests them.
+pub(crate) async fn resolve_project_blocks(
+  state: &AppState,
+  project: &Project,
+) -> Result<Vec<ResolvedBlock>, String> {
+  validate_project(project)?;
+  let characters = installed_characters(state).await?;
+
+  let mut resolved = Vec::with_capacity(project.blocks.len());
+  for (index, block) in project.blocks.iter().enumerate() {
+    if block.muted || block.text.trim().is_empty() {
+      continue;
+    }
+    let preset = block
+      .preset_id
+      .as_ref()
+      .and_then(|preset_id| {
+        project
+          .presets
+          .iter()
+          .find(|preset| &preset.id == preset_id)
+      })
+      .ok_or_else(|| format!("Project block {index} has no preset"))?;
+    let speaker_id = preset_style_id(preset, &characters)
+      .ok_or_else(|| format!("Project block {index} uses a style that is not installed"))?;
+    let query = match &block.query {
+      Some(query) => query.clone(),
+      None => cached_audio_query(state, block.text.clone(), speaker_id).await?,
+    };
+    resolved.push(ResolvedBlock {
+      block_id: block.id.clone(),
+      text: block.text.clone(),
+      audio_query: apply_preset(&query, preset, block.pause_after_ms),
+      speaker_id,
+      timing: block.timing,
+    });
+  }
+  Ok(resolved)
+}
+
+fn emit_render_progress(app: &AppHandle, event: RenderProgressEvent) {
+  if let Err(error) = event.emit(app) {
+    eprintln!("Failed to emit render progress: {error}");
+  }
+}
+
+/// Computes each block's timing and where it starts in the render, in seconds.
+fn block_layout(blocks: &[ResolvedBlock]) -> Result<Vec<(QueryTiming, f64)>, String> {
+  let timings = blocks
+    .iter()
+    .map(|block| {
+      let timing = query_timing(&block.audio_query)?;
+      Ok((timing, block.timing.map(|timing| timing.start)))
+    })
+    .collect::<Result<Vec<_>, String>>()?;
+  let offsets = block_offsets(&timings);
+  Ok(
+    timings
+      .into_iter()
+      .map(|(timing, _)| timing)
+      .zip(offsets)
+      .collect(),
+  )
+}
+
+/// Renders every block of a project into one WAV file through the waveform cache.
+///
+/// Preset silences are part of each block's query, so untimed blocks are joined back to back.
+/// Blocks imported from subtitles start speaking at their cue, padded with silence.
+#[tauri::command]
+#[specta::specta]
+pub async fn render_project(
+  app: AppHandle,
+  state: State<'_, AppState>,
+  project: Project,
+  path: String,
+) -> Result<String, String> {
+  let mut blocks = resolve_project_blocks(&state, &project).await?;
+  // every block has to share the output format of the first one to be joined
+  if let Some((first, rest)) = blocks.split_first_mut() {
+    for block in rest {
+      block.audio_query.output_sampling_rate = first.audio_query.output_sampling_rate;
+      block.audio_query.output_stereo = first.audio_query.output_stereo;
+    }
+  }
+
+  let layout = block_layout(&blocks)?;
+
+  let total = blocks.len();
+  // as many blocks are synthesized at once as the core has workers, and joined in order
+  let workers = state
+    .core
+    .read()
+    .await
+    .as_ref()
+    .map_or(1, |core| core.workers);
+  let (app, state) = (&app, &*state);
+  let mut syntheses = stream::iter(blocks.into_iter().zip(layout))
+    .map(|(block, (_, offset))| async move {
+      let wav = synthesize_cached(
+        app,
+        state,
+        block.audio_query,
+        block.speaker_id,
+        None,
+        SynthesisBackend::Blocking,
+      )
+      .await?;
+      Ok::<_, String>((block.block_id, offset, wav))
+    })
+    .buffered(workers);
+  let mut wavs = Vec::with_capacity(total);
+  while let Some(synthesis) = syntheses.next().await {
+    let (block_id, offset, wav) = synthesis?;
+    wavs.push((offset, wav));
+    emit_render_progress(
+      app,
+      RenderProgressEvent {
+        path: path.clone(),
+        block_id,
+        completed: wavs.len(),
+        total,
+      },
+    );
+  }
+
+  let wav = tauri::async_runtime::spawn_blocking(move || concat_wavs(&wavs))
+    .await
+    .map_err(|e| format!("Render task failed: {e}"))??;
+  std::fs::write(&path, wav).map_err(|e| e.to_string())?;
+  Ok(path)
+}
+
+/// Places one cue per block over its speech, leaving out the preset silences around it.
+fn subtitle_cues(blocks: &[ResolvedBlock]) -> Result<Vec<SubtitleCue>, String> {
+  Ok(
+    blocks
+      .iter()
+      .zip(block_layout(blocks)?)
+      .map(|(block, (timing, offset))| SubtitleCue {
+        start: offset + timing.speech_start,
+        end: offset + timing.speech_end,
+        text: block.text.clone(),
+      })
+      .collect(),
+  )
+}
+
+/// Writes SRT or WebVTT subtitles timed to the audio `render_project` produces.
+#[tauri::command]
+#[specta::specta]
+pub async fn export_subtitles(
+  state: State<'_, AppState>,
+  project: Project,
+  path: String,
+  format: SubtitleFormat,
+) -> Result<String, String> {
+  let blocks = resolve_project_blocks(&state, &project).await?;
+  let cues = subtitle_cues(&blocks)?;
+  std::fs::write(&path, write_subtitles(&cues, format)).map_err(|e| e.to_string())?;
+  Ok(path)
+}
+
+/// Reports the length, character and mora counts of a project per block, style and preset, and
+/// which blocks still have to be synthesized. Muted and empty blocks are left out, as in a render.
+#[tauri::command]
+#[specta::specta]
+pub async fn project_statistics(
+  state: State<'_, AppState>,
+  project: Project,
+) -> Result<ProjectStatistics, String> {
+  let blocks = resolve_project_blocks(&state, &project).await?;
+  let layout = block_layout(&blocks)?;
+  let render_seconds = layout
+    .last()
+    .map_or(0.0, |(timing, offset)| offset + timing.duration);
+
+  let mut keys = Vec::with_capacity(blocks.len());
+  for block in &blocks {
+    keys.push(cached_waveform_key(&state, &block.audio_query, block.speaker_id).await?);
+  }
+  let mut statistics = Vec::with_capacity(blocks.len());
+  {
+    let cache_guard = state.wav_lru.read().await;
+    for ((block, (timing, _)), key) in blocks.into_iter().zip(layout).zip(keys) {
+      let synthesized = cache_guard
+        .as_ref()
+        .and_then(|cache| cache.peek(&key))
+        .is_some_and(|entry| entry.cell.get().is_some());
+      // resolving the blocks made sure every one of them has a preset
+      let preset_id = project
+        .blocks
+        .iter()
+        .find(|candidate| candidate.id == block.block_id)
+        .and_then(|candidate| candidate.preset_id.clone())
+        .unwrap_or_default();
+      statistics.push(BlockStatistics::new(
+        block.block_id,
+        &block.text,
+        &block.audio_query,
+        timing,
+        block.speaker_id,
+        preset_id,
+        synthesized,
+      ));
+    }
+  }
+  Ok(ProjectStatistics::new(statistics, render_seconds))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use serde_json::json;
+
+  fn block(
+    id: &str,
+    pre: f32,
+    vowel_length: f32,
+    post: f32,
+    timing: Option<BlockTiming>,
+  ) -> ResolvedBlock {
+    ResolvedBlock {
+      block_id: id.into(),
+      text: format!("text of {id}"),
+      audio_query: serde_json::from_value(json!({
+        "accent_phrases": [{
+          "moras": [{
+            "text": "ア",
+            "consonant": null,
+            "consonant_length": null,
+            "vowel": "a",
+            "vowel_length": vowel_length,
+            "pitch": 5.0
+          }],
+          "accent": 1,
+          "pause_mora": null,
+          "is_interrogative": false
+        }],
+        "speedScale": 1.0,
+        "pitchScale": 0.0,
+        "intonationScale": 1.0,
+        "volumeScale": 1.0,
+        "prePhonemeLength": pre,
+        "postPhonemeLength": post,
+        "outputSamplingRate": 24000,
+        "outputStereo": false
+      }))
+      .unwrap(),
+      speaker_id: StyleId::new(1),
+      timing,
+    }
+  }
+
+  #[test]
+  fn subtitle_cues_follow_the_rendered_block_order_without_silences() {
+    // 0.512 seconds is exactly 48 decoder frames, so no rounding is involved
+    let cues = subtitle_cues(&[
+      block("first", 0.0, 0.512, 0.512, None),
+      block("second", 0.512, 1.024, 0.0, None),
+    ])
+    .unwrap();
+
+    assert_eq!(cues.len(), 2);
+    assert_eq!(cues[0].text, "text of first");
+    assert_eq!((cues[0].start, cues[0].end), (0.0, 0.512));
+    assert_eq!(cues[1].text, "text of second");
+    assert_eq!((cues[1].start, cues[1].end), (1.536, 2.56));
+  }
+
+  #[test]
+  fn subtitle_cues_of_timed_blocks_start_on_their_original_cue() {
+    let cue = |start| {
+      Some(BlockTiming {
+        start,
+        end: start + 1.0,
+      })
+    };
+    let cues = subtitle_cues(&[
+      block("first", 0.512, 0.512, 0.0, cue(2.0)),
+      // overlaps the first block, so it waits for it to finish
+      block("second", 0.0, 0.512, 0.0, cue(2.048)),
+      block("third", 0.0, 0.512, 0.0, None),
+    ])
+    .unwrap();
+
+    assert_eq!((cues[0].start, cues[0].end), (2.0, 2.512));
+    assert_eq!((cues[1].start, cues[1].end), (2.512, 3.024));
+    assert_eq!((cues[2].start, cues[2].end), (3.024, 3.536));
+  }
+}
@@ -0,0 +1,108 @@
+use super::core::{cached_audio_query, installed_characters};
+use super::project::validate_project;
+use crate::config::types::Project;
+use crate::render::preset_style_id;
+use crate::search::{
+  compile_query, find_matches, reading, replace_in_blocks, SearchMatch, SearchQuery, SearchTarget,
+};
+use crate::AppState;
+
+use serde::Serialize;
+use tauri::State;
+
+#[derive(Serialize, specta::Type)]
+pub struct ProjectSearch {
+  /// Matches in block order, then in order within each block.
+  pub matches: Vec<SearchMatch>,
+  /// Blocks whose reading could not be made because their preset has no installed style.
+  pub unreadable_block_ids: Vec<String>,
+}
+
+/// Searches the text or the readings of every block. Readings come from each block's query,
+/// or from the core for blocks that have none yet.
+#[tauri::command]
+#[specta::specta]
+pub async fn search_project(
+  state: State<'_, AppState>,
+  project: Project,
+  query: SearchQuery,
+) -> Result<ProjectSearch, String> {
+  validate_project(&project)?;
+  let pattern = compile_query(&query)?;
+  let mut search = ProjectSearch {
+    matches: Vec::new(),
+    unreadable_block_ids: Vec::new(),
+  };
+  if query.target == SearchTarget::Text {
+    for block in &project.blocks {
+      search
+        .matches
+        .extend(find_matches(&block.id, &block.text, &pattern));
+    }
+    return Ok(search);
+  }
+
+  let characters = installed_characters(&state).await?;
+  for block in &project.blocks {
+    if block.text.trim().is_empty() {
+      continue;
+    }
+    let accent_phrases = match &block.query {
+      Some(query) => query.accent_phrases.clone(),
+      None => {
+        let speaker_id = block
+          .preset_id
+          .as_ref()
+          .and_then(|preset_id| {
+            project
+              .presets
+              .iter()
+              .find(|preset| &preset.id == preset_id)
+          })
+          .and_then(|preset| preset_style_id(preset, &characters));
+        let Some(speaker_id) = speaker_id else {
+          search.unreadable_block_ids.push(block.id.clone());
+          continue;
+        };
+        cached_audio_query(&state, block.text.clone(), speaker_id)
+          .await?
+          .accent_phrases
+      }
+    };
+    search
+      .matches
+      .extend(find_matches(&block.id, &reading(&accent_phrases), &pattern));
+  }
+  Ok(search)
+}
+
+#[derive(Serialize, specta::Type)]
+pub struct ProjectReplacement {
+  pub project: Project,
+  /// Blocks whose text changed. Those without a hand-edited query have lost it and need a new one.
+  pub changed_block_ids: Vec<String>,
+  pub replacements: u32,
+}
+
+/// Replaces every match in the text of the project's blocks. With a regex query, `$1` and `$name`
+/// in `replacement` insert capture groups.
+#[tauri::command]
+#[specta::specta]
+pub async fn replace_in_project(
+  mut project: Project,
+  query: SearchQuery,
+  replacement: String,
+) -> Result<ProjectReplacement, String> {
+  if query.target != SearchTarget::Text {
+    return Err("Only block text can be replaced".into());
+  }
+  validate_project(&project)?;
+  let pattern = compile_query(&query)?;
+  let (changed_block_ids, replacements) =
+    replace_in_blocks(&mut project.blocks, &pattern, &replacement, query.regex);
+  Ok(ProjectReplacement {
+    project,
+    changed_block_ids,
+    replacements,
+  })
+}
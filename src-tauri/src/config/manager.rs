@@ -29,6 +29,19 @@ pub(crate) fn assets_dir() -> PathBuf {
   CONFIG_DIR.join("assets")
 }
 
+/// Kept apart from `assets_dir`, so the size and clearing of the assets leave the waveforms out.
+pub(crate) fn waveform_store_dir() -> PathBuf {
+  CONFIG_DIR.join("waveforms")
+}
+
+pub(crate) fn recovery_dir() -> PathBuf {
+  CONFIG_DIR.join("recovery")
+}
+
+pub(crate) fn vvm_index_path() -> PathBuf {
+  CONFIG_DIR.join("vvm_index.json")
+}
+
 /// This struct serves the purpose of serializing/deserializing it to/from a file.
 /// It also saves a in-memory copy of the config.
 pub struct ConfigManager {
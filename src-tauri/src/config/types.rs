@@ -6,16 +6,83 @@ use voicevox_core::{AudioQuery, StyleId};
 
 #[derive(Default, Clone, Deserialize, Serialize, Type)]
 pub struct AzaleaConfig {
+  /// The configuration the core runs with. While a profile is active, this is its working copy.
   pub core: Option<CoreConfig>,
+  /// Named core installations to switch between, such as a stable and a nightly VOICEVOX.
+  #[serde(default)]
+  pub core_profiles: Vec<CoreProfile>,
+  /// The name of the profile `core` belongs to.
+  #[serde(default)]
+  pub active_core_profile: Option<String>,
   pub ui: UIConfig,
   #[serde(default = "presets_default")]
   pub system_presets: Vec<Preset>,
 }
 
+/// The name a `core` without a profile is kept under when switching to a profile.
+const STANDALONE_CORE_PROFILE: &str = "Default";
+
 fn presets_default() -> Vec<Preset> {
   vec![Preset::default()]
 }
 
+impl AzaleaConfig {
+  pub fn core_profile(&self, name: &str) -> Result<&CoreProfile, String> {
+    self
+      .core_profiles
+      .iter()
+      .find(|profile| profile.name == name)
+      .ok_or_else(|| format!("Unknown core profile {name:?}"))
+  }
+
+  /// Makes `name` the active profile. Changes made to `core` while the previous profile was active
+  /// are kept in that profile. A `core` that belongs to no profile, such as the installation set up
+  /// before any profile existed, is kept as a new profile so it can be switched back to.
+  pub fn activate_core_profile(&mut self, name: &str) -> Result<(), String> {
+    let config = self.core_profile(name)?.config.clone();
+    if let Some(core) = self.core.take() {
+      let active = self.active_core_profile.as_ref().and_then(|active| {
+        self
+          .core_profiles
+          .iter_mut()
+          .find(|profile| &profile.name == active)
+      });
+      match active {
+        Some(profile) => profile.config = core,
+        None => {
+          let name = (1..)
+            .map(|n| match n {
+              1 => STANDALONE_CORE_PROFILE.to_string(),
+              n => format!("{STANDALONE_CORE_PROFILE} {n}"),
+            })
+            .find(|name| self.core_profile(name).is_err())
+            .unwrap_or_default();
+          self.core_profiles.push(CoreProfile { name, config: core });
+        }
+      }
+    }
+    self.core = Some(config);
+    self.active_core_profile = Some(name.to_string());
+    Ok(())
+  }
+
+  pub fn validate_core_profiles(&self) -> Result<(), String> {
+    let mut names = std::collections::HashSet::new();
+    for profile in &self.core_profiles {
+      if profile.name.trim().is_empty() {
+        return Err("Core profile names cannot be empty".into());
+      }
+      if !names.insert(profile.name.as_str()) {
+        return Err(format!("Duplicate core profile {:?}", profile.name));
+      }
+    }
+    if let Some(active) = &self.active_core_profile {
+      self.core_profile(active)?;
+    }
+    Ok(())
+  }
+}
+
 #[derive(Clone, Deserialize, Serialize, Type)]
 pub struct CoreConfig {
   /// The Path to the core directory, it should be the directory containing the dynamic library.
@@ -24,21 +91,90 @@ pub struct CoreConfig {
   pub ort_path: PathBuf,
   pub ojt_dir: PathBuf,
   pub vvm_dir: PathBuf,
+  /// Most audio queries kept in memory. 0 disables both the query and the waveform cache.
   #[serde(default = "cache_size_default")]
   pub cache_size: usize,
+  /// Memory budget for synthesized waveforms in megabytes, weighed by the size of their WAV
+  /// files. Past it the least recently used waveforms are dropped. Must not be 0, `cache_size`
+  /// turns the cache off.
+  #[serde(default = "waveform_cache_mb_default")]
+  pub waveform_cache_mb: u64,
   #[serde(default = "cpu_num_threads_default")]
   pub cpu_num_threads: u16,
+  /// Most voice models kept loaded at once. Past it the least recently used model is unloaded.
+  /// 0 means no limit.
+  #[serde(default)]
+  pub max_loaded_models: usize,
+  /// Memory budget for loaded voice models in megabytes, estimated from their file sizes. 0 means
+  /// no limit.
+  #[serde(default)]
+  pub model_memory_budget_mb: u64,
+  /// Synthesis tasks the core runs at once. With `cpu_num_threads` left at 0, the CPU cores are
+  /// shared evenly between them.
+  #[serde(default = "synthesis_workers_default")]
+  pub synthesis_workers: usize,
+  /// Size of the waveform cache on disk in megabytes. Past it the least recently used waveforms
+  /// are removed. 0 disables the cache.
+  #[serde(default = "waveform_store_mb_default")]
+  pub waveform_store_mb: u64,
+}
+
+impl CoreConfig {
+  /// The config of the core installed at these paths, with every other setting at its default.
+  pub fn new(ort_path: PathBuf, ojt_dir: PathBuf, vvm_dir: PathBuf) -> Self {
+    Self {
+      ort_path,
+      ojt_dir,
+      vvm_dir,
+      cache_size: cache_size_default(),
+      waveform_cache_mb: waveform_cache_mb_default(),
+      cpu_num_threads: cpu_num_threads_default(),
+      max_loaded_models: 0,
+      model_memory_budget_mb: 0,
+      synthesis_workers: synthesis_workers_default(),
+      waveform_store_mb: waveform_store_mb_default(),
+    }
+  }
+
+  /// Rejects cache sizes the caches cannot be built with, before the core is started with them.
+  pub fn validate_cache_sizes(&self) -> Result<(), String> {
+    if self.cache_size != 0 && self.waveform_cache_mb == 0 {
+      return Err(
+        "waveform_cache_mb must be non-zero, set cache_size to 0 to turn the caches off".into(),
+      );
+    }
+    Ok(())
+  }
+}
+
+/// A named core installation.
+#[derive(Clone, Deserialize, Serialize, Type)]
+pub struct CoreProfile {
+  pub name: String,
+  pub config: CoreConfig,
 }
 
 pub fn cache_size_default() -> usize {
   128
 }
 
+pub fn waveform_cache_mb_default() -> u64 {
+  256
+}
+
 /// Number of CPU threads VOICEVOX Core may use. 0 lets the runtime choose automatically.
 pub fn cpu_num_threads_default() -> u16 {
   0
 }
 
+pub fn synthesis_workers_default() -> usize {
+  1
+}
+
+pub fn waveform_store_mb_default() -> u64 {
+  1024
+}
+
 #[derive(Clone, Deserialize, Serialize, Type)]
 pub enum Locale {
   Ja,
@@ -74,6 +210,9 @@ pub struct UIConfig {
   pub bottom_scale: usize,
   #[serde(default)]
   pub auto_save: bool,
+  /// How many previous versions of a project are kept as `.azp.bak` files when saving over it.
+  #[serde(default = "project_backup_count_default")]
+  pub project_backup_count: u32,
   #[serde(default = "bottom_ratio_default")]
   pub bottom_ratio: f32,
   #[serde(default = "side_width_default")]
@@ -109,6 +248,7 @@ impl Default for UIConfig {
       primary_color: primary_color_default(),
       bottom_scale: bottom_scale_default(),
       auto_save: Default::default(),
+      project_backup_count: project_backup_count_default(),
       bottom_ratio: bottom_ratio_default(),
       side_width: side_width_default(),
       buffer_render: buffer_render_default(),
@@ -137,6 +277,10 @@ fn bottom_scale_default() -> usize {
   360
 }
 
+fn project_backup_count_default() -> u32 {
+  3
+}
+
 fn bottom_ratio_default() -> f32 {
   0.3
 }
@@ -270,6 +414,13 @@ impl Default for Preset {
   }
 }
 
+/// Where a block sat on the subtitle track it was imported from, in seconds.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize, Type)]
+pub struct BlockTiming {
+  pub start: f64,
+  pub end: f64,
+}
+
 #[derive(Clone, Deserialize, Serialize, Type)]
 pub struct TextBlockProps {
   pub id: String,
@@ -277,20 +428,74 @@ pub struct TextBlockProps {
   pub query: Option<AudioQuery>,
   pub query_is_modified: bool,
   pub preset_id: Option<String>,
+  /// Blocks with a timing are rendered at their cue start instead of right after the previous block.
+  #[serde(default)]
+  pub timing: Option<BlockTiming>,
+  /// A note about the block that is not spoken.
+  #[serde(default)]
+  pub comment: Option<String>,
+  /// Muted blocks stay in the project but are left out of playback and export.
+  #[serde(default)]
+  pub muted: bool,
+  /// Silence after the block in milliseconds, replacing the end silence of its preset.
+  #[serde(default)]
+  pub pause_after_ms: Option<f32>,
+  /// The section the block belongs to, if any.
+  #[serde(default)]
+  pub section_id: Option<String>,
+}
+
+/// A named group of blocks, such as a chapter.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize, Type)]
+pub struct ProjectSection {
+  pub id: String,
+  pub name: String,
+}
+
+/// Describes a project for people and tools that index project files.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize, Type)]
+pub struct ProjectMetadata {
+  #[serde(default)]
+  pub title: String,
+  #[serde(default)]
+  pub author: String,
+  #[serde(default)]
+  pub description: String,
+  /// When the project was first saved, in milliseconds since the Unix epoch.
+  #[serde(default)]
+  pub created_ms: Option<u64>,
+  /// When the project was last saved with changes, in milliseconds since the Unix epoch.
+  #[serde(default)]
+  pub modified_ms: Option<u64>,
+  #[serde(default)]
+  pub tags: Vec<String>,
+  /// The core profile the project was last saved with.
+  #[serde(default)]
+  pub core_profile: Option<String>,
 }
 
 #[derive(Clone, Deserialize, Serialize, Type, Default)]
 pub struct Project {
   pub blocks: Vec<TextBlockProps>,
   pub presets: Vec<Preset>,
+  #[serde(default)]
+  pub metadata: ProjectMetadata,
+  #[serde(default)]
+  pub sections: Vec<ProjectSection>,
 }
 
 #[cfg(test)]
 mod tests {
   use super::{
-    cache_size_default, cpu_num_threads_default, AzaleaConfig, KeyboardShortcut, UIConfig,
+    cache_size_default, cpu_num_threads_default, synthesis_workers_default,
+    waveform_cache_mb_default, waveform_store_mb_default, AzaleaConfig, CoreConfig, CoreProfile,
+    KeyboardShortcut, UIConfig, STANDALONE_CORE_PROFILE,
   };
 
+  fn core_config(vvm_dir: &str) -> CoreConfig {
+    CoreConfig::new("/runtime".into(), "/dictionary".into(), vvm_dir.into())
+  }
+
   #[test]
   fn missing_settings_use_defaults() {
     let config: UIConfig = toml::from_str("").unwrap();
@@ -372,7 +577,103 @@ mod tests {
 
     let core = config.core.unwrap();
     assert_eq!(core.cache_size, cache_size_default());
+    assert_eq!(core.waveform_cache_mb, waveform_cache_mb_default());
     assert_eq!(core.cpu_num_threads, cpu_num_threads_default());
+    assert_eq!(
+      (core.max_loaded_models, core.model_memory_budget_mb),
+      (0, 0)
+    );
+    assert_eq!(core.synthesis_workers, synthesis_workers_default());
+    assert_eq!(core.waveform_store_mb, waveform_store_mb_default());
+  }
+
+  #[test]
+  fn a_zero_waveform_cache_budget_is_rejected_unless_the_caches_are_off() {
+    core_config("/models").validate_cache_sizes().unwrap();
+    let zero = CoreConfig {
+      waveform_cache_mb: 0,
+      ..core_config("/models")
+    };
+    assert!(zero.validate_cache_sizes().is_err());
+    CoreConfig {
+      cache_size: 0,
+      ..zero
+    }
+    .validate_cache_sizes()
+    .unwrap();
+  }
+
+  #[test]
+  fn switching_core_profiles_keeps_changes_to_the_previous_one() {
+    let profile = |name: &str, vvm_dir: &str| CoreProfile {
+      name: name.into(),
+      config: core_config(vvm_dir),
+    };
+    let mut config = AzaleaConfig {
+      core_profiles: vec![profile("stable", "/stable"), profile("nightly", "/nightly")],
+      ..Default::default()
+    };
+    config.validate_core_profiles().unwrap();
+
+    config.activate_core_profile("stable").unwrap();
+    config.core.as_mut().unwrap().cpu_num_threads = 4;
+    config.activate_core_profile("nightly").unwrap();
+
+    assert_eq!(config.active_core_profile.as_deref(), Some("nightly"));
+    assert_eq!(
+      config.core.as_ref().unwrap().vvm_dir.to_str(),
+      Some("/nightly")
+    );
+    assert_eq!(
+      config
+        .core_profile("stable")
+        .unwrap()
+        .config
+        .cpu_num_threads,
+      4
+    );
+    assert!(config.activate_core_profile("beta").is_err());
+    assert_eq!(config.active_core_profile.as_deref(), Some("nightly"));
+
+    config.core_profiles.push(profile("nightly", "/other"));
+    assert!(config.validate_core_profiles().is_err());
+    config.core_profiles.pop();
+    config.active_core_profile = Some("beta".into());
+    assert!(config.validate_core_profiles().is_err());
+  }
+
+  #[test]
+  fn switching_away_from_a_core_without_profile_keeps_it_as_one() {
+    let core = core_config;
+    let mut config = AzaleaConfig {
+      core: Some(core("/installed")),
+      core_profiles: vec![
+        CoreProfile {
+          name: "nightly".into(),
+          config: core("/nightly"),
+        },
+        CoreProfile {
+          name: STANDALONE_CORE_PROFILE.into(),
+          config: core("/taken"),
+        },
+      ],
+      ..Default::default()
+    };
+
+    config.activate_core_profile("nightly").unwrap();
+
+    let kept = format!("{STANDALONE_CORE_PROFILE} 2");
+    assert_eq!(
+      config.core_profile(&kept).unwrap().config.vvm_dir.to_str(),
+      Some("/installed")
+    );
+    config.validate_core_profiles().unwrap();
+    config.activate_core_profile(&kept).unwrap();
+    assert_eq!(
+      config.core.as_ref().unwrap().vvm_dir.to_str(),
+      Some("/installed")
+    );
+    assert_eq!(config.core_profiles.len(), 3);
   }
 
   #[test]
@@ -1,18 +1,22 @@
+use futures_executor::block_on;
 use voicevox_core::{
-  blocking::{Onnxruntime, OpenJtalk, Synthesizer, VoiceModelFile},
-  nonblocking::{
-    Onnxruntime as NonblockingOnnxruntime, Synthesizer as NonblockingSynthesizer,
-    VoiceModelFile as NonblockingVoiceModelFile,
-  },
-  AccentPhrase, AudioQuery, StyleId, StyleType, VoiceModelId, VoiceModelMeta,
+  blocking::{Onnxruntime as BlockingOnnxruntime, VoiceModelFile as BlockingVoiceModelFile},
+  nonblocking::{Onnxruntime, OpenJtalk, Synthesizer, VoiceModelFile},
+  AccentPhrase, AudioQuery, StyleId, VoiceModelId,
 };
 
-use crate::config::types::{cache_size_default, cpu_num_threads_default};
+use crate::config::manager::vvm_index_path;
 use crate::config::CoreConfig;
+use crate::model_residency::{ModelResidency, ModelResidencyReport};
+use crate::vvm_index::{IndexedVoiceModel, VoiceModels, VvmIndex};
 use std::{
-  collections::{HashMap, HashSet},
+  collections::HashSet,
+  fs,
   path::{Path, PathBuf},
+  sync::{Arc, Mutex, MutexGuard, PoisonError, RwLock, RwLockReadGuard},
+  thread::available_parallelism,
 };
+use tokio::sync::{Mutex as AsyncMutex, Semaphore};
 use walkdir::WalkDir;
 
 use anyhow::{Context, Result};
@@ -51,45 +55,98 @@ pub fn search_dir(dirname: &str, dir: impl AsRef<Path>, partial: bool) -> Option
   None
 }
 
+/// CPU threads of each synthesis worker. An explicit `cpu_num_threads` is used as is, otherwise
+/// the available cores are shared evenly between the workers. 0 lets the runtime choose.
+fn worker_cpu_threads(cfg: &CoreConfig, available_cores: usize) -> u16 {
+  if cfg.cpu_num_threads != 0 || cfg.synthesis_workers <= 1 {
+    return cfg.cpu_num_threads;
+  }
+  (available_cores / cfg.synthesis_workers).clamp(1, u16::MAX as usize) as u16
+}
+
+/// One synthesizer serves both the blocking and the nonblocking methods, so each voice model is
+/// loaded once. The blocking methods wait on it on the calling thread, and are meant to be called
+/// from blocking tasks.
 pub struct Core {
-  pub synthesizer: Synthesizer<OpenJtalk>,
-  nonblocking_synthesizer: NonblockingSynthesizer<()>,
-  pub metas: HashMap<String, VoiceModelMeta>,
-  pub speaker_to_vvm: HashMap<StyleId, VoiceModelId>,
+  synthesizer: Synthesizer<OpenJtalk>,
+  models: RwLock<VoiceModels>,
+  /// The directory `models` were gathered from.
+  pub vvm_dir: PathBuf,
+  /// The OpenJTalk dictionary queries are made with.
+  ojt_dir: PathBuf,
+  residency: Mutex<ModelResidency>,
+  /// Held while a model loads, so tasks needing the same model load it once.
+  loading: AsyncMutex<()>,
+  /// Tasks run on the core at once.
+  pub workers: usize,
+  task_gate: Arc<Semaphore>,
+  /// The core profile this core was started from, if any.
+  pub profile: Option<String>,
+}
+
+/// Keeps a voice model loaded while a task uses it.
+struct ModelUse<'a> {
+  core: &'a Core,
+  vvm_id: VoiceModelId,
+}
+
+impl Drop for ModelUse<'_> {
+  fn drop(&mut self) {
+    self.core.residency().release(self.vvm_id);
+  }
 }
 
 impl Core {
   /// Walk the dir where *.vvm is located
-  /// Read meta from each vvm and return a (file path, metadata) list
-  pub fn gather_meta(
+  /// Read the ID and meta of each vvm
+  ///
+  /// With an `index_path`, only the files that changed since they were indexed are opened.
+  pub(crate) fn gather_meta(
     dir: impl AsRef<Path>,
-  ) -> Result<(
-    HashMap<StyleId, VoiceModelId>,
-    HashMap<String, VoiceModelMeta>,
-  )> {
-    // TODO: organize the styles with character-styles mapping
-    let mut metas = HashMap::new();
-    let mut speaker_to_vvm = HashMap::new();
-    for entry in WalkDir::new(dir).into_iter().flatten() {
-      if entry.path().is_file() && entry.path().extension() == Some("vvm".as_ref()) {
-        let vvm = VoiceModelFile::open(entry.path())?;
-        let meta = vvm.metas().clone();
-        let mut filtered_meta = HashMap::new();
-        for m in meta.iter() {
-          for style in &m.styles {
-            if style.r#type == StyleType::Talk {
-              speaker_to_vvm.insert(style.id, vvm.id());
-              filtered_meta.insert(m.speaker_uuid.clone(), m.clone());
-            }
-          }
-        }
-        metas.insert(
-          entry.path().to_string_lossy().to_string(),
-          filtered_meta.into_values().collect(),
-        );
+    index_path: Option<&Path>,
+  ) -> Result<Vec<IndexedVoiceModel>> {
+    let paths = WalkDir::new(dir)
+      .into_iter()
+      .flatten()
+      .filter(|entry| entry.path().is_file() && entry.path().extension() == Some("vvm".as_ref()))
+      .map(|entry| entry.into_path());
+    let mut index = index_path.map(VvmIndex::load).unwrap_or_default();
+    let (models, changed) = index.refresh(paths, |path| {
+      let vvm = BlockingVoiceModelFile::open(path)?;
+      Ok::<_, anyhow::Error>((vvm.id(), vvm.metas().clone()))
+    })?;
+    if let (Some(index_path), true) = (index_path, changed) {
+      if let Err(error) = index.save(index_path) {
+        eprintln!("Failed to save the voice model index: {error}");
       }
     }
-    Ok((speaker_to_vvm, metas))
+    Ok(models)
+  }
+
+  /// The voice models found in `vvm_dir`, kept up to date by `reload_models`.
+  pub fn models(&self) -> RwLockReadGuard<'_, VoiceModels> {
+    self.models.read().unwrap_or_else(PoisonError::into_inner)
+  }
+
+  /// Limits the tasks run on the core at once to `workers`. Work that unloads models others may
+  /// be using takes every permit.
+  pub fn task_gate(&self) -> Arc<Semaphore> {
+    self.task_gate.clone()
+  }
+
+  /// Picks up models added to, removed from or changed in `vvm_dir` since they were gathered.
+  /// Removed and changed models are unloaded, so a changed one is loaded afresh when it is next
+  /// used. Returns whether any model changed.
+  pub fn reload_models(&self) -> Result<bool> {
+    let gathered = Self::gather_meta(&self.vvm_dir, Some(&vvm_index_path()))?;
+    let next = VoiceModels::new(&gathered);
+    let stale = self.models().stale_ids(&gathered);
+    let changed = !stale.is_empty() || next.ids != self.models().ids;
+    for vvm_id in stale {
+      self.unload_model(vvm_id)?;
+    }
+    *self.models.write().unwrap_or_else(PoisonError::into_inner) = next;
+    Ok(changed)
   }
 
   pub fn find_path(root: &Path) -> Option<CoreConfig> {
@@ -104,72 +161,201 @@ impl Core {
     let ojt_dir = search_dir(OJT_DIR_NAME, root, true)?;
     let ort_path = search_file(ORT_NAME, root, true)?;
     let vvm_dir = search_file(VVM_EXT, root, true)?.parent()?.to_path_buf();
-    Some(CoreConfig {
-      vvm_dir,
-      ojt_dir,
-      ort_path,
-      cache_size: cache_size_default(),
-      cpu_num_threads: cpu_num_threads_default(),
-    })
+    Some(CoreConfig::new(ort_path, ojt_dir, vvm_dir))
   }
 
   pub fn init(cfg: &CoreConfig) -> Result<Self> {
-    let ort = Onnxruntime::load_once().filename(&cfg.ort_path).perform()?;
-    let ojt = OpenJtalk::new(cfg.ojt_dir.to_string_lossy().to_string())?;
+    // ONNX Runtime is loaded once per process, whichever API loads it
+    BlockingOnnxruntime::load_once()
+      .filename(&cfg.ort_path)
+      .perform()?;
+    let ort = Onnxruntime::get().context("ONNX Runtime is unavailable after loading it")?;
+    let ojt = block_on(OpenJtalk::new(cfg.ojt_dir.to_string_lossy().to_string()))?;
+    let available_cores = available_parallelism().map_or(1, |cores| cores.get());
     let synthesizer = Synthesizer::builder(ort)
       .text_analyzer(ojt)
-      .cpu_num_threads(cfg.cpu_num_threads)
-      .build()?;
-    let nonblocking_ort = NonblockingOnnxruntime::get()
-      .context("nonblocking ONNX Runtime is unavailable after initialization")?;
-    let nonblocking_synthesizer = NonblockingSynthesizer::builder(nonblocking_ort)
-      .cpu_num_threads(cfg.cpu_num_threads)
+      .cpu_num_threads(worker_cpu_threads(cfg, available_cores))
       .build()?;
-    let (speaker_to_vvm, metas) = Self::gather_meta(&cfg.vvm_dir)?;
+    let workers = cfg.synthesis_workers.max(1);
+    let models = Self::gather_meta(&cfg.vvm_dir, Some(&vvm_index_path()))?;
     Ok(Self {
       synthesizer,
-      nonblocking_synthesizer,
-      metas,
-      speaker_to_vvm,
+      models: RwLock::new(VoiceModels::new(&models)),
+      vvm_dir: cfg.vvm_dir.clone(),
+      ojt_dir: cfg.ojt_dir.clone(),
+      residency: Mutex::new(ModelResidency::new(
+        cfg.max_loaded_models,
+        cfg.model_memory_budget_mb.saturating_mul(1024 * 1024),
+      )),
+      loading: AsyncMutex::new(()),
+      workers,
+      task_gate: Arc::new(Semaphore::new(workers)),
+      profile: None,
     })
   }
 
   pub fn load_speaker(&self, speaker_id: StyleId) -> Result<()> {
+    block_on(self.load_speaker_nonblocking(speaker_id))
+  }
+
+  async fn load_speaker_nonblocking(&self, speaker_id: StyleId) -> Result<()> {
+    let _model = self.use_model(speaker_id).await?;
+    Ok(())
+  }
+
+  /// Loads the model of `speaker_id` if needed, marks it as the most recently used and keeps it
+  /// loaded until the returned use is dropped.
+  async fn use_model(&self, speaker_id: StyleId) -> Result<ModelUse<'_>> {
+    let vvm_id = self
+      .models()
+      .speaker_to_vvm
+      .get(&speaker_id)
+      .copied()
+      .context("Speaker ID not found in any loaded VVM")?;
+    // acquired before checking whether the model is loaded, so it cannot be evicted in between
+    self.residency().acquire(vvm_id);
+    let model = ModelUse { core: self, vvm_id };
+    if !self.synthesizer.is_loaded_voice_model(vvm_id) {
+      let _loading = self.loading.lock().await;
+      if !self.synthesizer.is_loaded_voice_model(vvm_id) {
+        self.open_and_load(speaker_id).await?;
+      }
+    }
+    self.mark_used(speaker_id)?;
+    Ok(model)
+  }
+
+  async fn open_and_load(&self, speaker_id: StyleId) -> Result<()> {
     let vvm_name = self
+      .models()
       .metas
       .iter()
-      .find_map(|(k, v)| {
-        v.iter()
-          .flat_map(|cm| &cm.styles)
-          .find(|style| style.id == speaker_id)
-          .map(|_| k.clone())
+      .find_map(|(path, characters)| {
+        characters
+          .iter()
+          .flat_map(|character| &character.styles)
+          .any(|style| style.id == speaker_id)
+          .then(|| path.clone())
       })
       .context("Speaker ID not found in any loaded VVM")?;
-    let vvm = VoiceModelFile::open(&vvm_name)?;
-    self.synthesizer.load_voice_model(&vvm).perform()?;
+    let vvm = VoiceModelFile::open(vvm_name).await?;
+    self.synthesizer.load_voice_model(&vvm).perform().await?;
+    Ok(())
+  }
+
+  /// Loads the model of `speaker_id` if needed, and marks it as the most recently used.
+  pub async fn prepare_synthesis(&self, speaker_id: StyleId) -> Result<()> {
+    self.load_speaker_nonblocking(speaker_id).await
+  }
+
+  /// The model file and ID providing `speaker_id`.
+  fn model_of(&self, speaker_id: StyleId) -> Option<(String, VoiceModelId)> {
+    let models = self.models();
+    let vvm_id = *models.speaker_to_vvm.get(&speaker_id)?;
+    let (path, _) = models.ids.iter().find(|(_, id)| **id == vvm_id)?;
+    Some((path.clone(), vvm_id))
+  }
+
+  /// Tells the model of `speaker_id` apart from other models and other versions of it, for
+  /// caches that outlive the running core. A model file changed in place counts as another
+  /// version once the models are reloaded.
+  pub fn model_identity(&self, speaker_id: StyleId) -> Option<String> {
+    let models = self.models();
+    let vvm_id = models.speaker_to_vvm.get(&speaker_id)?;
+    let (path, _) = models.ids.iter().find(|(_, id)| *id == vvm_id)?;
+    let mut versions = models
+      .metas
+      .get(path)?
+      .iter()
+      .map(|character| serde_json::to_string(&character.version))
+      .collect::<Result<Vec<_>, _>>()
+      .ok()?;
+    versions.sort();
+    serde_json::to_string(&(vvm_id, versions, models.stamps.get(path))).ok()
+  }
+
+  /// Tells the dictionary queries are made with apart from others, for the query cache.
+  pub fn dictionary_identity(&self) -> String {
+    self.ojt_dir.to_string_lossy().into_owned()
+  }
+
+  fn residency(&self) -> MutexGuard<'_, ModelResidency> {
+    self
+      .residency
+      .lock()
+      .unwrap_or_else(PoisonError::into_inner)
+  }
+
+  /// Records that the model of `speaker_id` was used, unloading the least recently used models
+  /// beyond the limits of the config.
+  fn mark_used(&self, speaker_id: StyleId) -> Result<()> {
+    let Some((path, vvm_id)) = self.model_of(speaker_id) else {
+      return Ok(());
+    };
+    let bytes = fs::metadata(&path)
+      .map(|metadata| metadata.len())
+      .unwrap_or(0);
+    // unloaded under the lock, so no task starts using an evicted model in between
+    let mut residency = self.residency();
+    for model in residency.touch(vvm_id, &path, bytes) {
+      self.unload_resident(&mut residency, model.id)?;
+    }
+    Ok(())
+  }
+
+  fn unload_model(&self, vvm_id: VoiceModelId) -> Result<()> {
+    self.unload_resident(&mut self.residency(), vvm_id)
+  }
+
+  fn unload_resident(&self, residency: &mut ModelResidency, vvm_id: VoiceModelId) -> Result<()> {
+    if self.synthesizer.is_loaded_voice_model(vvm_id) {
+      self.synthesizer.unload_voice_model(vvm_id)?;
+    }
+    residency.forget(vvm_id);
     Ok(())
   }
 
+  /// How many of `speaker_ids`, loaded in order, keep their models loaded together within the
+  /// limits of the config.
+  pub fn speakers_within_limits(&self, speaker_ids: &[StyleId]) -> usize {
+    let models = speaker_ids
+      .iter()
+      .map(|speaker_id| {
+        let (path, vvm_id) = self.model_of(*speaker_id)?;
+        let bytes = fs::metadata(&path)
+          .map(|metadata| metadata.len())
+          .unwrap_or(0);
+        Some((vvm_id, bytes))
+      })
+      .collect::<Vec<_>>();
+    self.residency().fitting(&models)
+  }
+
+  /// The loaded models, most recently used first, and the limits they are kept within.
+  pub fn model_residency(&self) -> ModelResidencyReport {
+    self.residency().report()
+  }
+
   pub fn is_speaker_loaded(&self, speaker_id: StyleId) -> bool {
-    let vvm_id = match self.speaker_to_vvm.get(&speaker_id) {
-      Some(id) => id,
+    let vvm_id = match self.models().speaker_to_vvm.get(&speaker_id) {
+      Some(id) => *id,
       None => return false,
     };
-    self.synthesizer.is_loaded_voice_model(*vvm_id)
+    self.synthesizer.is_loaded_voice_model(vvm_id)
   }
 
   pub fn audio_query(&self, text: &str, speaker_id: StyleId) -> Result<AudioQuery> {
-    if !self.is_speaker_loaded(speaker_id) {
-      self.load_speaker(speaker_id)?;
-    }
-    Ok(self.synthesizer.create_audio_query(text, speaker_id)?)
+    let _model = block_on(self.use_model(speaker_id))?;
+    Ok(block_on(
+      self.synthesizer.create_audio_query(text, speaker_id),
+    )?)
   }
 
   pub fn accent_phrases(&self, text: &str, speaker_id: StyleId) -> Result<Vec<AccentPhrase>> {
-    if !self.is_speaker_loaded(speaker_id) {
-      self.load_speaker(speaker_id)?;
-    }
-    Ok(self.synthesizer.create_accent_phrases(text, speaker_id)?)
+    let _model = block_on(self.use_model(speaker_id))?;
+    Ok(block_on(
+      self.synthesizer.create_accent_phrases(text, speaker_id),
+    )?)
   }
 
   /// Useful for accent phrase manipulation
@@ -181,7 +367,8 @@ impl Core {
     ap: Vec<AccentPhrase>,
     style_id: StyleId,
   ) -> Result<Vec<AccentPhrase>> {
-    Ok(self.synthesizer.replace_mora_data(&ap, style_id)?)
+    let _model = block_on(self.use_model(style_id))?;
+    Ok(block_on(self.synthesizer.replace_mora_data(&ap, style_id))?)
   }
 
   /// same as `replace_mora` but only replaces pitch
@@ -190,7 +377,10 @@ impl Core {
     ap: Vec<AccentPhrase>,
     style_id: StyleId,
   ) -> Result<Vec<AccentPhrase>> {
-    Ok(self.synthesizer.replace_mora_pitch(&ap, style_id)?)
+    let _model = block_on(self.use_model(style_id))?;
+    Ok(block_on(
+      self.synthesizer.replace_mora_pitch(&ap, style_id),
+    )?)
   }
 
   /// same as `replace_mora` but only replaces length
@@ -199,57 +389,30 @@ impl Core {
     ap: Vec<AccentPhrase>,
     style_id: StyleId,
   ) -> Result<Vec<AccentPhrase>> {
-    Ok(self.synthesizer.replace_phoneme_length(&ap, style_id)?)
+    let _model = block_on(self.use_model(style_id))?;
+    Ok(block_on(
+      self.synthesizer.replace_phoneme_length(&ap, style_id),
+    )?)
   }
 
   pub fn synthesis(&self, query: &AudioQuery, speaker_id: StyleId) -> Result<Vec<u8>> {
-    if !self.is_speaker_loaded(speaker_id) {
-      self.load_speaker(speaker_id)?;
-    }
-    Ok(self.synthesizer.synthesis(query, speaker_id).perform()?)
-  }
-
-  async fn load_speaker_nonblocking(&self, speaker_id: StyleId) -> Result<()> {
-    let vvm_name = self
-      .metas
-      .iter()
-      .find_map(|(path, characters)| {
-        characters
-          .iter()
-          .flat_map(|character| &character.styles)
-          .any(|style| style.id == speaker_id)
-          .then(|| path.clone())
-      })
-      .context("Speaker ID not found in any loaded VVM")?;
-    let vvm = NonblockingVoiceModelFile::open(vvm_name).await?;
-    self
-      .nonblocking_synthesizer
-      .load_voice_model(&vvm)
-      .perform()
-      .await?;
-    Ok(())
-  }
-
-  pub async fn prepare_nonblocking_synthesis(&self, speaker_id: StyleId) -> Result<()> {
-    let vvm_id = self
-      .speaker_to_vvm
-      .get(&speaker_id)
-      .copied()
-      .context("Speaker ID not found in any loaded VVM")?;
-    if !self.nonblocking_synthesizer.is_loaded_voice_model(vvm_id) {
-      self.load_speaker_nonblocking(speaker_id).await?;
-    }
-    Ok(())
+    let _model = block_on(self.use_model(speaker_id))?;
+    Ok(block_on(
+      self.synthesizer.synthesis(query, speaker_id).perform(),
+    )?)
   }
 
+  /// Synthesizes with a model loaded by `prepare_synthesis`. Should another task have
+  /// unloaded the model since, it is loaded again.
   pub async fn synthesis_nonblocking_prepared(
     &self,
     query: &AudioQuery,
     speaker_id: StyleId,
   ) -> Result<Vec<u8>> {
+    let _model = self.use_model(speaker_id).await?;
     Ok(
       self
-        .nonblocking_synthesizer
+        .synthesizer
         .synthesis(query, speaker_id)
         .cancellable(true)
         .perform()
@@ -262,39 +425,36 @@ impl Core {
     query: &AudioQuery,
     speaker_id: StyleId,
   ) -> Result<Vec<u8>> {
-    self.prepare_nonblocking_synthesis(speaker_id).await?;
+    self.prepare_synthesis(speaker_id).await?;
     self.synthesis_nonblocking_prepared(query, speaker_id).await
   }
 
   pub fn unload_all_speakers(&self) -> Result<()> {
-    for vvm_id in self
+    let vvm_ids = self
+      .models()
       .speaker_to_vvm
       .values()
       .copied()
-      .collect::<HashSet<_>>()
-    {
-      if self.synthesizer.is_loaded_voice_model(vvm_id) {
-        self.synthesizer.unload_voice_model(vvm_id)?;
-      }
+      .collect::<HashSet<_>>();
+    for vvm_id in vvm_ids {
+      self.unload_model(vvm_id)?;
     }
     Ok(())
   }
 
   pub fn unload_speaker(&self, speaker_id: StyleId) -> Result<()> {
-    let vvm_id = match self.speaker_to_vvm.get(&speaker_id) {
+    let vvm_id = match self.models().speaker_to_vvm.get(&speaker_id) {
       Some(id) => *id,
       None => return Ok(()),
     };
-    if self.synthesizer.is_loaded_voice_model(vvm_id) {
-      self.synthesizer.unload_voice_model(vvm_id)?;
-    }
-    Ok(())
+    self.unload_model(vvm_id)
   }
 }
 
 #[cfg(test)]
 mod tests {
   use super::*;
+  use crate::config::types::{cache_size_default, cpu_num_threads_default};
 
   fn ort_name() -> &'static str {
     #[cfg(target_os = "linux")]
@@ -361,6 +521,19 @@ mod tests {
     assert_eq!(config.cpu_num_threads, cpu_num_threads_default());
   }
 
+  #[test]
+  fn workers_share_the_cores_unless_threads_are_configured() {
+    let mut config = CoreConfig::new("/runtime".into(), "/dictionary".into(), "/models".into());
+    assert_eq!(worker_cpu_threads(&config, 16), 0);
+
+    config.synthesis_workers = 4;
+    assert_eq!(worker_cpu_threads(&config, 16), 4);
+    assert_eq!(worker_cpu_threads(&config, 2), 1);
+
+    config.cpu_num_threads = 2;
+    assert_eq!(worker_cpu_threads(&config, 16), 2);
+  }
+
   #[test]
   fn find_path_rejects_incomplete_installations() {
     let root = tempfile::tempdir().unwrap();
@@ -1,11 +1,26 @@
 //! TODO: there's no reason we pass the audio to frontend, we can keep it in the buffer and avoid the IPC overhead
 mod async_job;
+mod atomic_write;
 pub mod audio;
+mod bundle;
+mod cache_key;
+mod cache_report;
 pub mod commands;
 pub mod config;
 pub mod core;
+mod migration;
+mod model_residency;
+mod recovery;
+mod render;
+mod search;
 mod spectrogram;
+mod statistics;
+mod subtitle;
 mod synthesis;
+mod timing;
+mod vvm_index;
+mod vvproj;
+mod waveform_store;
 use core::Core;
 
 use commands::*;
@@ -14,50 +29,65 @@ use specta_typescript::Typescript;
 use std::sync::{Arc, Mutex, RwLock};
 use tauri::async_runtime::RwLock as TokioRwLock;
 use tauri::Manager;
-use tokio::sync::Semaphore;
 
 use tauri_specta::{collect_commands, collect_events, Builder, Event};
 
-use voicevox_core::{AudioQuery, StyleId};
+use voicevox_core::AudioQuery;
 
 use spectrogram::{SpectrogramJobEvent, SpectrogramQueue};
-use synthesis::{SynthesisJobEvent, SynthesisQueue, WaveformCacheEntry};
+use synthesis::{SynthesisJobEvent, SynthesisQueue, WaveformCache};
 
-pub(crate) type WavLruType = lru::LruCache<(String, StyleId), WaveformCacheEntry>;
+pub(crate) type WavLruType = WaveformCache;
+pub(crate) type QueryLruType = lru::LruCache<cache_key::CacheKey, AudioQuery>;
 
 type LockedState<T> = RwLock<Option<T>>;
 pub struct AppState {
   pub(crate) core: TokioRwLock<Option<Arc<Core>>>,
-  pub(crate) core_task_gate: Arc<Semaphore>,
-  pub(crate) query_lru: LockedState<lru::LruCache<(String, StyleId), AudioQuery>>,
+  pub(crate) query_lru: LockedState<QueryLruType>,
   pub(crate) wav_lru: TokioRwLock<Option<WavLruType>>,
+  /// How lookups in `wav_lru` and `query_lru` went, see `inspect_caches`.
+  pub(crate) waveform_cache_statistics: cache_report::CacheStatistics,
+  pub(crate) query_cache_statistics: cache_report::CacheStatistics,
+  /// Waveforms kept on disk behind `wav_lru`.
+  pub(crate) waveform_store: LockedState<Arc<waveform_store::WaveformStore>>,
   pub(crate) synthesis_queue: SynthesisQueue,
   pub(crate) spectrogram_queue: SpectrogramQueue,
   pub(crate) config_manager: LockedState<config::ConfigManager>,
   pub(crate) audio_player: LockedState<audio::AudioPlayer>,
+  pub(crate) recovery: recovery::RecoveryJournal,
+  /// Watches the running core's model directory, see `watch_voice_models`.
+  pub(crate) model_watcher: Mutex<Option<notify::RecommendedWatcher>>,
+  pub(crate) model_preload: async_job::LatestCancellation,
 }
 
 fn specta_builder() -> Builder<tauri::Wry> {
   Builder::<tauri::Wry>::new()
     .commands(collect_commands![
       clear_caches,
+      inspect_caches,
+      evict_cache_entry,
       get_cached_speaker_icons,
       download_speaker_icons,
       get_assets_size,
       clear_assets,
+      get_waveform_cache_size,
+      clear_waveform_cache,
       pick_core,
       init_config,
       get_config,
       set_config,
       init_core,
       reinit_core,
+      switch_core_profile,
       get_metas,
+      model_residency,
       get_range,
       audio_query,
       accent_phrases,
       replace_mora,
       replace_mora_pitch,
       replace_mora_duration,
+      fit_query_duration,
       synthesize,
       synthesize_nonblocking,
       cancel_synthesis,
@@ -76,12 +106,33 @@ fn specta_builder() -> Builder<tauri::Wry> {
       quit,
       save_project,
       load_project,
+      preload_project_models,
+      cancel_model_preload,
+      list_project_backups,
+      restore_project_backup,
+      record_recovery_snapshot,
+      discard_recovery_snapshot,
+      restore_recovery_session,
+      discard_recovery_session,
+      import_subtitles,
+      import_vvproj,
+      export_vvproj,
+      export_bundle,
+      load_bundle,
+      render_project,
+      export_subtitles,
+      search_project,
+      replace_in_project,
+      project_statistics,
     ])
     .events(collect_events![
       InitializationEvent,
       FrontendReadyEvent,
       SynthesisJobEvent,
-      SpectrogramJobEvent
+      SpectrogramJobEvent,
+      RenderProgressEvent,
+      ModelPreloadEvent,
+      VoiceModelsChangedEvent
     ])
 }
 
@@ -115,13 +166,18 @@ pub fn run() {
   app
     .manage(AppState {
       core: TokioRwLock::new(None),
-      core_task_gate: Arc::new(Semaphore::new(1)),
       query_lru: RwLock::new(None),
       wav_lru: TokioRwLock::new(None),
+      waveform_cache_statistics: Default::default(),
+      query_cache_statistics: Default::default(),
+      waveform_store: RwLock::new(None),
       synthesis_queue: SynthesisQueue::default(),
       spectrogram_queue: SpectrogramQueue::default(),
       config_manager: RwLock::new(None),
       audio_player: RwLock::new(None),
+      recovery: recovery::RecoveryJournal::new(config::manager::recovery_dir()),
+      model_watcher: Mutex::new(None),
+      model_preload: async_job::LatestCancellation::default(),
     })
     .invoke_handler(builder.invoke_handler())
     .setup(move |app| {
@@ -165,25 +221,30 @@ pub fn run() {
 #[cfg(test)]
 mod tests {
   use super::*;
+  use crate::cache_key::waveform_cache_key;
+  use crate::synthesis::WaveformCacheEntry;
   use serde_json::json;
   use std::num::NonZeroUsize;
   use tauri::test::{get_ipc_response, mock_builder, mock_context, noop_assets, INVOKE_KEY};
   use tauri::webview::InvokeRequest;
   use tokio::sync::OnceCell;
+  use voicevox_core::StyleId;
 
-  fn empty_app_state(
-    wav_lru: Option<WavLruType>,
-    query_lru: Option<lru::LruCache<(String, StyleId), AudioQuery>>,
-  ) -> AppState {
+  fn empty_app_state(wav_lru: Option<WavLruType>, query_lru: Option<QueryLruType>) -> AppState {
     AppState {
       core: TokioRwLock::new(None),
-      core_task_gate: Arc::new(Semaphore::new(1)),
       query_lru: RwLock::new(query_lru),
       wav_lru: TokioRwLock::new(wav_lru),
+      waveform_cache_statistics: Default::default(),
+      query_cache_statistics: Default::default(),
+      waveform_store: RwLock::new(None),
       synthesis_queue: SynthesisQueue::default(),
       spectrogram_queue: SpectrogramQueue::default(),
       config_manager: RwLock::new(None),
       audio_player: RwLock::new(None),
+      recovery: recovery::RecoveryJournal::new(std::env::temp_dir().join("azalea-test-recovery")),
+      model_watcher: Mutex::new(None),
+      model_preload: async_job::LatestCancellation::default(),
     }
   }
 
@@ -277,7 +338,7 @@ mod tests {
 
   #[test]
   fn mock_runtime_serializes_all_waveform_cache_states() {
-    let wav_lru = lru::LruCache::new(NonZeroUsize::new(4).unwrap());
+    let wav_lru = WaveformCache::new(1024);
     let query_lru = lru::LruCache::new(NonZeroUsize::new(4).unwrap());
     let app = mock_builder()
       .manage(empty_app_state(Some(wav_lru), Some(query_lru)))
@@ -315,7 +376,8 @@ mod tests {
     assert_eq!(invoke_state(), json!("UnInitialized"));
 
     let cell = Arc::new(OnceCell::new());
-    let query_key = serde_json::to_string(&query).unwrap();
+    // without a core no style has a voice model
+    let key = waveform_cache_key(&query, StyleId(1), "").unwrap();
     tauri::async_runtime::block_on(async {
       app
         .state::<AppState>()
@@ -324,14 +386,60 @@ mod tests {
         .await
         .as_mut()
         .unwrap()
-        .put(
-          (query_key, StyleId(1)),
-          WaveformCacheEntry::new(cell.clone()),
-        );
+        .push(key, WaveformCacheEntry::new(cell.clone()));
     });
     assert_eq!(invoke_state(), json!("Pending"));
 
     cell.set(vec![1, 2, 3]).unwrap();
     assert_eq!(invoke_state(), json!("Done"));
   }
+
+  #[test]
+  fn mock_runtime_inspects_and_evicts_cache_entries() {
+    let mut wav_lru = WaveformCache::new(1024);
+    wav_lru.push(
+      ("waveform".into(), StyleId(1)),
+      WaveformCacheEntry::new(Arc::new(OnceCell::new_with(Some(vec![0; 44])))),
+    );
+    let query_lru = lru::LruCache::new(NonZeroUsize::new(4).unwrap());
+    let app = mock_builder()
+      .manage(empty_app_state(Some(wav_lru), Some(query_lru)))
+      .invoke_handler(tauri::generate_handler![inspect_caches, evict_cache_entry])
+      .build(mock_context(noop_assets()))
+      .unwrap();
+    let webview = tauri::WebviewWindowBuilder::new(&app, "main", Default::default())
+      .build()
+      .unwrap();
+    let inspect = || {
+      get_ipc_response(&webview, invoke_request("inspect_caches", json!({})))
+        .unwrap()
+        .deserialize::<serde_json::Value>()
+        .unwrap()
+    };
+    let evict = || {
+      get_ipc_response(
+        &webview,
+        invoke_request(
+          "evict_cache_entry",
+          json!({ "cache": "Waveform", "key": "waveform", "styleId": 1 }),
+        ),
+      )
+      .unwrap()
+      .deserialize::<bool>()
+      .unwrap()
+    };
+
+    let report = inspect();
+    assert_eq!(report["waveforms"][0]["key"], "waveform");
+    assert_eq!(report["waveforms"][0]["bytes"], 44);
+    assert_eq!(report["waveforms"][0]["initialized"], true);
+    assert_eq!(report["query_capacity"], 4);
+
+    assert!(evict());
+    assert!(!evict());
+    let report = inspect();
+    assert_eq!(report["waveforms"], json!([]));
+    assert_eq!(report["waveform_bytes"], 0);
+    assert_eq!(report["waveform_counts"]["evictions"], 1);
+  }
 }
@@ -0,0 +1,215 @@
+//! Upgrades `.azp` project documents written by older versions of Azalea before they are read.
+//!
+//! Migrations work on the raw TOML so each one only has to know the layout of the version it
+//! upgrades from, not the structs the current version deserializes into.
+use toml::{Table, Value};
+
+pub(crate) const CURRENT_PROJECT_SCHEMA_VERSION: u32 = 3;
+
+/// Upgrades a document from schema version `from` to `from + 1`.
+pub(crate) struct Migration {
+  pub from: u32,
+  pub description: &'static str,
+  pub migrate: fn(&mut Table) -> Result<(), String>,
+}
+
+/// Version 2 describes the project in a `[metadata]` table. Older projects get an empty one.
+fn add_metadata(document: &mut Table) -> Result<(), String> {
+  document
+    .entry("metadata")
+    .or_insert_with(|| Value::Table(Table::new()));
+  Ok(())
+}
+
+/// Version 3 groups blocks into named sections. Older projects have none.
+fn add_sections(document: &mut Table) -> Result<(), String> {
+  document
+    .entry("sections")
+    .or_insert_with(|| Value::Array(Vec::new()));
+  Ok(())
+}
+
+/// Every schema change so far, oldest first.
+const MIGRATIONS: &[Migration] = &[
+  Migration {
+    from: 1,
+    description: "add project metadata",
+    migrate: add_metadata,
+  },
+  Migration {
+    from: 2,
+    description: "add block sections",
+    migrate: add_sections,
+  },
+];
+
+pub(crate) fn schema_version(document: &Table) -> Result<u32, String> {
+  let version = document
+    .get("schema_version")
+    .ok_or("Project file has no schema version")?;
+  version
+    .as_integer()
+    .and_then(|version| u32::try_from(version).ok())
+    .filter(|version| *version > 0)
+    .ok_or_else(|| format!("Unsupported project schema version {version}"))
+}
+
+fn run_migrations(
+  document: &mut Table,
+  migrations: &[Migration],
+  current: u32,
+) -> Result<Vec<String>, String> {
+  let mut version = schema_version(document)?;
+  if version > current {
+    return Err(format!(
+      "Unsupported project schema version {version}, it is newer than this version of Azalea"
+    ));
+  }
+  let mut applied = Vec::new();
+  while version < current {
+    let migration = migrations
+      .iter()
+      .find(|migration| migration.from == version)
+      .ok_or_else(|| format!("Unsupported project schema version {version}"))?;
+    (migration.migrate)(document)
+      .map_err(|e| format!("Failed to upgrade project from schema version {version}: {e}"))?;
+    version += 1;
+    document.insert("schema_version".into(), Value::Integer(version.into()));
+    applied.push(format!(
+      "v{} → v{version}: {}",
+      version - 1,
+      migration.description
+    ));
+  }
+  Ok(applied)
+}
+
+/// Upgrades a project document in place to the current schema version and describes the
+/// migrations that ran, oldest first.
+pub(crate) fn migrate_project(document: &mut Table) -> Result<Vec<String>, String> {
+  run_migrations(document, MIGRATIONS, CURRENT_PROJECT_SCHEMA_VERSION)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// One document per schema version Azalea has ever written, oldest first.
+  const FIXTURES: &[(u32, &str)] = &[
+    (1, include_str!("../tests/fixtures/projects/v1.azp")),
+    (2, include_str!("../tests/fixtures/projects/v2.azp")),
+    (3, include_str!("../tests/fixtures/projects/v3.azp")),
+  ];
+
+  fn document(toml: &str) -> Table {
+    toml::from_str(toml).unwrap()
+  }
+
+  #[test]
+  fn every_historical_fixture_upgrades_to_the_current_version() {
+    assert_eq!(
+      FIXTURES.last().map(|(version, _)| *version),
+      Some(CURRENT_PROJECT_SCHEMA_VERSION),
+      "add a fixture for every new schema version"
+    );
+    for (version, fixture) in FIXTURES {
+      let mut document = document(fixture);
+      assert_eq!(schema_version(&document), Ok(*version));
+
+      let applied = migrate_project(&mut document).unwrap();
+
+      assert_eq!(
+        applied.len() as u32,
+        CURRENT_PROJECT_SCHEMA_VERSION - version
+      );
+      assert_eq!(
+        schema_version(&document),
+        Ok(CURRENT_PROJECT_SCHEMA_VERSION)
+      );
+    }
+  }
+
+  fn rename_text(document: &mut Table) -> Result<(), String> {
+    let blocks = document
+      .get_mut("blocks")
+      .and_then(Value::as_array_mut)
+      .ok_or("missing blocks")?;
+    for block in blocks.iter_mut().filter_map(Value::as_table_mut) {
+      if let Some(text) = block.remove("content") {
+        block.insert("text".into(), text);
+      }
+    }
+    Ok(())
+  }
+
+  fn add_presets(document: &mut Table) -> Result<(), String> {
+    document
+      .entry("presets")
+      .or_insert_with(|| Value::Array(Vec::new()));
+    Ok(())
+  }
+
+  fn fail(_: &mut Table) -> Result<(), String> {
+    Err("broken".into())
+  }
+
+  const CHAIN: &[Migration] = &[
+    Migration {
+      from: 2,
+      description: "add presets",
+      migrate: add_presets,
+    },
+    Migration {
+      from: 1,
+      description: "rename content to text",
+      migrate: rename_text,
+    },
+  ];
+
+  #[test]
+  fn migrations_run_in_version_order_and_are_reported() {
+    let mut old = document("schema_version = 1\n[[blocks]]\ncontent = \"hello\"\n");
+
+    let applied = run_migrations(&mut old, CHAIN, 3).unwrap();
+
+    assert_eq!(
+      applied,
+      vec!["v1 → v2: rename content to text", "v2 → v3: add presets"]
+    );
+    assert_eq!(schema_version(&old), Ok(3));
+    assert_eq!(old["blocks"][0]["text"].as_str(), Some("hello"));
+    assert!(old["presets"].as_array().unwrap().is_empty());
+
+    let mut current = document("schema_version = 3\n");
+    assert!(run_migrations(&mut current, CHAIN, 3).unwrap().is_empty());
+  }
+
+  #[test]
+  fn unknown_newer_and_failing_versions_are_rejected() {
+    let error = run_migrations(&mut document("schema_version = 4\n"), CHAIN, 3).unwrap_err();
+    assert!(error.contains("newer than this version"), "{error}");
+
+    let error = run_migrations(&mut document("schema_version = 1\n"), &CHAIN[..1], 3).unwrap_err();
+    assert!(
+      error.contains("Unsupported project schema version 1"),
+      "{error}"
+    );
+
+    let failing = [Migration {
+      from: 1,
+      description: "fails",
+      migrate: fail,
+    }];
+    let error = run_migrations(&mut document("schema_version = 1\n"), &failing, 2).unwrap_err();
+    assert!(error.contains("from schema version 1: broken"), "{error}");
+
+    for invalid in [
+      "",
+      "schema_version = 0\n",
+      "schema_version = -1\n",
+      "schema_version = \"1\"\n",
+    ] {
+      assert!(schema_version(&document(invalid)).is_err(), "{invalid}");
+    }
+  }
+}
@@ -0,0 +1,218 @@
+//! Keeps loaded voice models within the limits of `CoreConfig`, unloading the least recently used
+//! ones first.
+//!
+//! A loaded model takes roughly the size of its file in memory. Models in use by a running task
+//! are never unloaded, even if that leaves the loaded models over the limits for a while.
+use serde::Serialize;
+use std::collections::HashMap;
+use voicevox_core::VoiceModelId;
+
+#[derive(Clone, Debug, PartialEq, Serialize, specta::Type)]
+pub struct ResidentModel {
+  #[serde(skip)]
+  pub(crate) id: VoiceModelId,
+  pub path: String,
+  /// Size of the model file, the estimated memory of the loaded model.
+  pub bytes: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, specta::Type)]
+pub struct ModelResidencyReport {
+  /// Loaded models, most recently used first.
+  pub models: Vec<ResidentModel>,
+  /// Estimated memory of every loaded model.
+  pub loaded_bytes: u64,
+  /// 0 when the number of models is unlimited.
+  pub max_loaded_models: usize,
+  /// 0 when the memory of models is unlimited.
+  pub memory_budget_bytes: u64,
+}
+
+pub(crate) struct ModelResidency {
+  max_models: usize,
+  budget_bytes: u64,
+  /// Least recently used first.
+  models: Vec<ResidentModel>,
+  /// Running tasks using each model.
+  in_use: HashMap<VoiceModelId, usize>,
+}
+
+impl ModelResidency {
+  /// A limit of 0 disables it.
+  pub fn new(max_models: usize, budget_bytes: u64) -> Self {
+    Self {
+      max_models,
+      budget_bytes,
+      models: Vec::new(),
+      in_use: HashMap::new(),
+    }
+  }
+
+  /// Records that the model loaded from the file at `path` of `bytes` bytes was just used.
+  /// Returns the models to unload to get back within the limits. The model just used and models
+  /// in use are never among them, even when they alone are over the budget.
+  pub fn touch(&mut self, id: VoiceModelId, path: &str, bytes: u64) -> Vec<ResidentModel> {
+    let model = match self.models.iter().position(|model| model.id == id) {
+      Some(index) => self.models.remove(index),
+      None => ResidentModel {
+        id,
+        path: path.into(),
+        bytes,
+      },
+    };
+    self.models.push(model);
+
+    let mut evicted = Vec::new();
+    while self.over_limits() {
+      let Some(index) = self
+        .models
+        .iter()
+        .position(|model| model.id != id && !self.in_use.contains_key(&model.id))
+      else {
+        break;
+      };
+      evicted.push(self.models.remove(index));
+    }
+    evicted
+  }
+
+  /// Keeps the model from being evicted until a matching `release`.
+  pub fn acquire(&mut self, id: VoiceModelId) {
+    *self.in_use.entry(id).or_default() += 1;
+  }
+
+  pub fn release(&mut self, id: VoiceModelId) {
+    if let Some(count) = self.in_use.get_mut(&id) {
+      *count -= 1;
+      if *count == 0 {
+        self.in_use.remove(&id);
+      }
+    }
+  }
+
+  /// Records that the model was unloaded.
+  pub fn forget(&mut self, id: VoiceModelId) {
+    self.models.retain(|model| model.id != id);
+  }
+
+  pub fn loaded_bytes(&self) -> u64 {
+    self.models.iter().map(|model| model.bytes).sum()
+  }
+
+  /// How many of `models`, in the order they are wanted, can be loaded together within the
+  /// limits. A model wanted again counts once, `None` stands for a style without a model, and the
+  /// first model always fits, as the model just used is never unloaded.
+  pub fn fitting(&self, models: &[Option<(VoiceModelId, u64)>]) -> usize {
+    let mut wanted: Vec<VoiceModelId> = Vec::new();
+    let mut bytes = 0;
+    for (index, model) in models.iter().enumerate() {
+      let Some((id, size)) = model else {
+        continue;
+      };
+      if wanted.contains(id) {
+        continue;
+      }
+      let over = (self.max_models != 0 && wanted.len() >= self.max_models)
+        || (self.budget_bytes != 0 && bytes + size > self.budget_bytes);
+      if over && !wanted.is_empty() {
+        return index;
+      }
+      wanted.push(*id);
+      bytes += size;
+    }
+    models.len()
+  }
+
+  fn over_limits(&self) -> bool {
+    (self.max_models != 0 && self.models.len() > self.max_models)
+      || (self.budget_bytes != 0 && self.loaded_bytes() > self.budget_bytes)
+  }
+
+  pub fn report(&self) -> ModelResidencyReport {
+    ModelResidencyReport {
+      models: self.models.iter().rev().cloned().collect(),
+      loaded_bytes: self.loaded_bytes(),
+      max_loaded_models: self.max_models,
+      memory_budget_bytes: self.budget_bytes,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use serde_json::json;
+
+  fn model_id(n: u8) -> VoiceModelId {
+    serde_json::from_value(json!(format!("00000000-0000-0000-0000-0000000000{n:02x}"))).unwrap()
+  }
+
+  fn ids(models: &[ResidentModel]) -> Vec<VoiceModelId> {
+    models.iter().map(|model| model.id).collect()
+  }
+
+  #[test]
+  fn the_least_recently_used_model_is_evicted_past_the_model_limit() {
+    let mut residency = ModelResidency::new(2, 0);
+    assert!(residency.touch(model_id(1), "1.vvm", 10).is_empty());
+    assert!(residency.touch(model_id(2), "2.vvm", 10).is_empty());
+    // using the first model again makes the second the least recently used
+    residency.touch(model_id(1), "1.vvm", 10);
+
+    let evicted = residency.touch(model_id(3), "3.vvm", 10);
+
+    assert_eq!(ids(&evicted), [model_id(2)]);
+    let report = residency.report();
+    assert_eq!(ids(&report.models), [model_id(3), model_id(1)]);
+    assert_eq!(report.loaded_bytes, 20);
+  }
+
+  #[test]
+  fn the_byte_budget_keeps_the_model_just_used() {
+    let mut residency = ModelResidency::new(0, 25);
+    residency.touch(model_id(1), "1.vvm", 10);
+    assert!(residency.touch(model_id(2), "2.vvm", 10).is_empty());
+
+    let evicted = residency.touch(model_id(3), "3.vvm", 10);
+    assert_eq!(ids(&evicted), [model_id(1)]);
+
+    // a model over the budget on its own stays loaded
+    let evicted = residency.touch(model_id(4), "4.vvm", 40);
+    assert_eq!(ids(&evicted), [model_id(2), model_id(3)]);
+    assert_eq!(residency.loaded_bytes(), 40);
+
+    residency.forget(model_id(4));
+    assert!(residency.report().models.is_empty());
+  }
+
+  #[test]
+  fn models_in_use_are_not_evicted() {
+    let mut residency = ModelResidency::new(1, 0);
+    residency.touch(model_id(1), "1.vvm", 10);
+    residency.acquire(model_id(1));
+
+    // another task goes over the limit while the first model is in use
+    assert!(residency.touch(model_id(2), "2.vvm", 10).is_empty());
+    assert_eq!(residency.report().models.len(), 2);
+
+    residency.release(model_id(1));
+    let evicted = residency.touch(model_id(2), "2.vvm", 10);
+    assert_eq!(ids(&evicted), [model_id(1)]);
+  }
+
+  #[test]
+  fn models_past_the_limits_do_not_fit_after_the_earlier_ones() {
+    let wanted = [
+      Some((model_id(1), 10)),
+      None,
+      Some((model_id(2), 10)),
+      Some((model_id(1), 10)),
+      Some((model_id(3), 10)),
+    ];
+    assert_eq!(ModelResidency::new(2, 0).fitting(&wanted), 4);
+    assert_eq!(ModelResidency::new(0, 15).fitting(&wanted), 2);
+    assert_eq!(ModelResidency::new(0, 0).fitting(&wanted), 5);
+    // the first model fits even when it is over the budget on its own
+    assert_eq!(ModelResidency::new(0, 5).fitting(&wanted), 2);
+  }
+}
@@ -0,0 +1,203 @@
+//! Crash recovery for unsaved work.
+//!
+//! Every run of Azalea is a session that keeps the latest snapshot of its project in
+//! `recovery/<session id>.json` next to `config.toml`. A snapshot is removed once the project is
+//! saved, so whatever is left behind by an earlier session is work that was never saved.
+use crate::atomic_write::write_atomically;
+use crate::config::types::Project;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const SNAPSHOT_EXTENSION: &str = "json";
+
+#[derive(Deserialize, Serialize)]
+struct RecoverySnapshot {
+  /// The file the project was last loaded from or saved to.
+  project_path: Option<String>,
+  saved_ms: u64,
+  project: Project,
+}
+
+/// Unsaved work left behind by an earlier session.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize, specta::Type)]
+pub struct RecoverableSession {
+  pub id: String,
+  /// The file the project was last loaded from or saved to, if any.
+  pub project_path: Option<String>,
+  /// When the snapshot was taken, in milliseconds since the Unix epoch.
+  pub saved_ms: u64,
+  pub block_count: u32,
+}
+
+pub(crate) struct RecoveryJournal {
+  directory: PathBuf,
+  session_id: String,
+}
+
+impl RecoveryJournal {
+  /// Starts a new session whose snapshots are kept in `directory`.
+  pub fn new(directory: PathBuf) -> Self {
+    Self {
+      directory,
+      session_id: uuid::Uuid::new_v4().as_hyphenated().to_string(),
+    }
+  }
+
+  fn snapshot_path(&self, session_id: &str) -> Result<PathBuf, String> {
+    let valid = !session_id.is_empty()
+      && session_id
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-');
+    if !valid {
+      return Err(format!("Invalid recovery session id {session_id:?}"));
+    }
+    Ok(
+      self
+        .directory
+        .join(session_id)
+        .with_extension(SNAPSHOT_EXTENSION),
+    )
+  }
+
+  /// Replaces this session's snapshot with `project`.
+  pub fn record(&self, project: Project, project_path: Option<String>) -> Result<(), String> {
+    fs::create_dir_all(&self.directory)
+      .map_err(|e| format!("Failed to create the recovery directory: {e}"))?;
+    let snapshot = RecoverySnapshot {
+      project_path,
+      saved_ms: SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis() as u64),
+      project,
+    };
+    let contents = serde_json::to_vec(&snapshot).map_err(|e| e.to_string())?;
+    write_atomically(
+      &self.snapshot_path(&self.session_id)?,
+      &contents,
+      "recovery snapshot",
+    )
+  }
+
+  /// Forgets this session's snapshot, e.g. after the project was saved.
+  pub fn discard(&self) -> Result<(), String> {
+    self.discard_session(&self.session_id)
+  }
+
+  pub fn discard_session(&self, session_id: &str) -> Result<(), String> {
+    match fs::remove_file(self.snapshot_path(session_id)?) {
+      Err(error) if error.kind() != ErrorKind::NotFound => Err(error.to_string()),
+      _ => Ok(()),
+    }
+  }
+
+  /// Lists the snapshots left behind by other sessions, newest first. Unreadable snapshots are
+  /// skipped rather than failing startup.
+  pub fn recoverable_sessions(&self) -> Vec<RecoverableSession> {
+    let Ok(entries) = fs::read_dir(&self.directory) else {
+      return Vec::new();
+    };
+    let mut sessions = entries
+      .filter_map(Result::ok)
+      .filter_map(|entry| {
+        let path = entry.path();
+        if path.extension()? != SNAPSHOT_EXTENSION {
+          return None;
+        }
+        let id = path.file_stem()?.to_str()?.to_string();
+        if id == self.session_id {
+          return None;
+        }
+        let snapshot = read_snapshot(&path).ok()?;
+        Some(RecoverableSession {
+          id,
+          project_path: snapshot.project_path,
+          saved_ms: snapshot.saved_ms,
+          block_count: snapshot.project.blocks.len() as u32,
+        })
+      })
+      .collect::<Vec<_>>();
+    sessions.sort_by(|a, b| b.saved_ms.cmp(&a.saved_ms).then_with(|| a.id.cmp(&b.id)));
+    sessions
+  }
+
+  /// Reads the project left behind by another session.
+  pub fn restore(&self, session_id: &str) -> Result<Project, String> {
+    let path = self.snapshot_path(session_id)?;
+    Ok(read_snapshot(&path)?.project)
+  }
+}
+
+fn read_snapshot(path: &Path) -> Result<RecoverySnapshot, String> {
+  let contents = fs::read(path).map_err(|e| e.to_string())?;
+  serde_json::from_slice(&contents).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::config::types::TextBlockProps;
+
+  fn project(text: &str) -> Project {
+    Project {
+      blocks: vec![TextBlockProps {
+        id: "block-1".into(),
+        text: text.into(),
+        query: None,
+        query_is_modified: false,
+        preset_id: None,
+        timing: None,
+        comment: None,
+        muted: false,
+        pause_after_ms: None,
+        section_id: None,
+      }],
+      presets: Vec::new(),
+      metadata: Default::default(),
+      sections: Vec::new(),
+    }
+  }
+
+  #[test]
+  fn snapshots_of_earlier_sessions_are_recoverable() {
+    let directory = tempfile::tempdir().unwrap();
+    let crashed = RecoveryJournal::new(directory.path().to_path_buf());
+    crashed
+      .record(project("draft"), Some("/tmp/draft.azp".into()))
+      .unwrap();
+    crashed.record(project("latest draft"), None).unwrap();
+
+    let current = RecoveryJournal::new(directory.path().to_path_buf());
+    current.record(project("current"), None).unwrap();
+    let sessions = current.recoverable_sessions();
+
+    assert_eq!(sessions.len(), 1);
+    assert_eq!(sessions[0].id, crashed.session_id);
+    assert_eq!(sessions[0].project_path, None);
+    assert_eq!(sessions[0].block_count, 1);
+    let restored = current.restore(&sessions[0].id).unwrap();
+    assert_eq!(restored.blocks[0].text, "latest draft");
+
+    current.discard_session(&sessions[0].id).unwrap();
+    assert!(current.recoverable_sessions().is_empty());
+  }
+
+  #[test]
+  fn saved_and_unreadable_sessions_are_not_offered() {
+    let directory = tempfile::tempdir().unwrap();
+    let saved = RecoveryJournal::new(directory.path().to_path_buf());
+    saved.record(project("saved"), None).unwrap();
+    saved.discard().unwrap();
+    saved.discard().unwrap();
+    fs::write(directory.path().join("broken.json"), "{").unwrap();
+
+    let current = RecoveryJournal::new(directory.path().to_path_buf());
+
+    assert!(current.recoverable_sessions().is_empty());
+    assert!(current.restore("broken").is_err());
+    assert!(current.restore("../config").is_err());
+    assert!(current.discard_session("").is_err());
+  }
+}
@@ -0,0 +1,386 @@
+//! Project rendering helpers: resolving blocks against their presets and joining the
+//! synthesized waveforms into one file.
+use std::io::Cursor;
+
+use voicevox_core::{AudioQuery, CharacterMeta, StyleId};
+
+use crate::config::types::{Preset, Project};
+use crate::timing::fit_duration;
+
+/// Applies a preset to a query the same way `getModifiedQuery` does on the frontend.
+///
+/// Preset silences are stored in milliseconds and become the query's pre/post phoneme lengths.
+/// A block's own pause after it replaces the end silence of the preset.
+pub(crate) fn apply_preset(
+  query: &AudioQuery,
+  preset: &Preset,
+  pause_after_ms: Option<f32>,
+) -> AudioQuery {
+  let mut query = query.clone();
+  query.pitch_scale = preset.pitch;
+  query.speed_scale = preset.speed as f32 / 100.0;
+  query.intonation_scale = preset.intonation;
+  query.volume_scale = preset.volume;
+  query.pre_phoneme_length = preset.start_slience / 1000.0;
+  query.post_phoneme_length = pause_after_ms.unwrap_or(preset.end_slience) / 1000.0;
+  query
+}
+
+/// Fits a block to last `target` seconds once its preset is applied. Only the phoneme lengths,
+/// which the preset leaves alone, are changed, so the block can keep the returned query as an
+/// edited one.
+pub(crate) fn fit_block_duration(
+  query: &AudioQuery,
+  preset: &Preset,
+  pause_after_ms: Option<f32>,
+  target: f64,
+) -> Result<AudioQuery, String> {
+  let fitted = fit_duration(&apply_preset(query, preset, pause_after_ms), target)?;
+  let mut query = query.clone();
+  query.accent_phrases = fitted.accent_phrases;
+  Ok(query)
+}
+
+/// Resolves the style a preset speaks with, preferring its speaker UUID and style name
+/// fallback over the raw style ID, like `findPresetStyle` on the frontend.
+pub(crate) fn preset_style_id<'a>(
+  preset: &Preset,
+  characters: impl IntoIterator<Item = &'a CharacterMeta>,
+) -> Option<StyleId> {
+  let mut characters = characters.into_iter();
+  if let (Some(speaker_uuid), Some(style_name)) = (&preset.speaker_uuid, &preset.style_name) {
+    return characters
+      .find(|character| &character.speaker_uuid == speaker_uuid)?
+      .styles
+      .iter()
+      .find(|style| &style.name == style_name)
+      .map(|style| style.id);
+  }
+  characters
+    .flat_map(|character| &character.styles)
+    .find(|style| style.id == preset.style_id)
+    .map(|style| style.id)
+}
+
+/// The distinct styles the project's blocks speak with, in the order they are first heard. Muted
+/// and empty blocks are left out, as are presets without an installed style.
+pub(crate) fn project_style_ids<'a>(
+  project: &Project,
+  characters: impl IntoIterator<Item = &'a CharacterMeta> + Clone,
+) -> Vec<StyleId> {
+  let mut style_ids = Vec::new();
+  for block in &project.blocks {
+    if block.muted || block.text.trim().is_empty() {
+      continue;
+    }
+    let style_id = block
+      .preset_id
+      .as_ref()
+      .and_then(|preset_id| {
+        project
+          .presets
+          .iter()
+          .find(|preset| &preset.id == preset_id)
+      })
+      .and_then(|preset| preset_style_id(preset, characters.clone()));
+    if let Some(style_id) = style_id.filter(|style_id| !style_ids.contains(style_id)) {
+      style_ids.push(style_id);
+    }
+  }
+  style_ids
+}
+
+/// Decodes a 16-bit PCM waveform as produced by VOICEVOX Core.
+pub(crate) fn decode_pcm16(wav: &[u8]) -> Result<(hound::WavSpec, Vec<i16>), String> {
+  let reader =
+    hound::WavReader::new(Cursor::new(wav)).map_err(|e| format!("Failed to decode WAV: {e}"))?;
+  let spec = reader.spec();
+  if spec.sample_format != hound::SampleFormat::Int || spec.bits_per_sample != 16 {
+    return Err("Only 16-bit PCM WAV audio can be rendered".into());
+  }
+  let samples = reader
+    .into_samples::<i16>()
+    .collect::<Result<Vec<_>, _>>()
+    .map_err(|e| format!("Failed to decode WAV samples: {e}"))?;
+  Ok((spec, samples))
+}
+
+/// Joins waveforms that share one format into a single WAV file. Each waveform starts at its
+/// offset in seconds, with silence filling any gap before it.
+pub(crate) fn concat_wavs(wavs: &[(f64, Vec<u8>)]) -> Result<Vec<u8>, String> {
+  let mut spec = None;
+  let mut samples = Vec::new();
+  for (index, (offset, wav)) in wavs.iter().enumerate() {
+    let (block_spec, block_samples) = decode_pcm16(wav)?;
+    match spec {
+      None => spec = Some(block_spec),
+      Some(spec) if spec != block_spec => {
+        return Err(format!("Rendered block {index} has a different WAV format"));
+      }
+      Some(_) => {}
+    }
+    let start = (offset * block_spec.sample_rate as f64).round().max(0.0) as usize
+      * block_spec.channels as usize;
+    if samples.len() < start {
+      samples.resize(start, 0);
+    }
+    samples.extend(block_samples);
+  }
+  let spec = spec.ok_or("There is no audio to render")?;
+  encode_pcm16(spec, &samples)
+}
+
+fn encode_pcm16(spec: hound::WavSpec, samples: &[i16]) -> Result<Vec<u8>, String> {
+  let mut cursor = Cursor::new(Vec::new());
+  let mut writer =
+    hound::WavWriter::new(&mut cursor, spec).map_err(|e| format!("Failed to encode WAV: {e}"))?;
+  for sample in samples {
+    writer
+      .write_sample(*sample)
+      .map_err(|e| format!("Failed to encode WAV: {e}"))?;
+  }
+  writer
+    .finalize()
+    .map_err(|e| format!("Failed to encode WAV: {e}"))?;
+  Ok(cursor.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::config::types::TextBlockProps;
+  use crate::timing::query_timing;
+  use serde_json::json;
+
+  fn query() -> AudioQuery {
+    serde_json::from_value(json!({
+      "accent_phrases": [],
+      "speedScale": 1.0,
+      "pitchScale": 0.0,
+      "intonationScale": 1.0,
+      "volumeScale": 1.0,
+      "prePhonemeLength": 0.1,
+      "postPhonemeLength": 0.1,
+      "outputSamplingRate": 24000,
+      "outputStereo": false
+    }))
+    .unwrap()
+  }
+
+  fn characters() -> Vec<CharacterMeta> {
+    serde_json::from_value(json!([
+      {
+        "name": "First",
+        "speaker_uuid": "first-uuid",
+        "version": "0.1.0",
+        "order": null,
+        "styles": [{ "id": 1, "name": "Normal", "type": "talk", "order": null }]
+      },
+      {
+        "name": "Second",
+        "speaker_uuid": "second-uuid",
+        "version": "0.1.0",
+        "order": null,
+        "styles": [
+          { "id": 2, "name": "Normal", "type": "talk", "order": null },
+          { "id": 3, "name": "Whisper", "type": "talk", "order": null }
+        ]
+      }
+    ]))
+    .unwrap()
+  }
+
+  fn wav(channels: u16, sample_rate: u32, samples: &[i16]) -> Vec<u8> {
+    encode_pcm16(
+      hound::WavSpec {
+        channels,
+        sample_rate,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+      },
+      samples,
+    )
+    .unwrap()
+  }
+
+  #[test]
+  fn presets_replace_global_parameters_and_convert_silences_to_seconds() {
+    let preset = Preset {
+      speed: 150,
+      pitch: 0.05,
+      intonation: 1.2,
+      volume: 0.8,
+      start_slience: 250.0,
+      end_slience: 1500.0,
+      ..Preset::default()
+    };
+
+    let modified = apply_preset(&query(), &preset, None);
+
+    assert_eq!(modified.speed_scale, 1.5);
+    assert_eq!(modified.pitch_scale, 0.05);
+    assert_eq!(modified.intonation_scale, 1.2);
+    assert_eq!(modified.volume_scale, 0.8);
+    assert_eq!(modified.pre_phoneme_length, 0.25);
+    assert_eq!(modified.post_phoneme_length, 1.5);
+    assert_eq!(modified.output_sampling_rate, 24000);
+
+    let paused = apply_preset(&query(), &preset, Some(0.0));
+    assert_eq!(paused.pre_phoneme_length, 0.25);
+    assert_eq!(paused.post_phoneme_length, 0.0);
+  }
+
+  #[test]
+  fn fitted_blocks_last_the_target_once_their_preset_is_applied() {
+    let query: AudioQuery = serde_json::from_value(json!({
+      "accent_phrases": [{
+        "moras": [
+          {
+            "text": "コ",
+            "consonant": "k",
+            "consonant_length": 0.08,
+            "vowel": "o",
+            "vowel_length": 0.12,
+            "pitch": 5.4
+          },
+          {
+            "text": "ン",
+            "consonant": null,
+            "consonant_length": null,
+            "vowel": "N",
+            "vowel_length": 0.1,
+            "pitch": 5.5
+          }
+        ],
+        "accent": 1,
+        "pause_mora": null,
+        "is_interrogative": false
+      }],
+      "speedScale": 1.0,
+      "pitchScale": 0.0,
+      "intonationScale": 1.0,
+      "volumeScale": 1.0,
+      "prePhonemeLength": 0.1,
+      "postPhonemeLength": 0.1,
+      "outputSamplingRate": 24000,
+      "outputStereo": false
+    }))
+    .unwrap();
+    let preset = Preset {
+      speed: 150,
+      start_slience: 250.0,
+      end_slience: 500.0,
+      ..Preset::default()
+    };
+
+    for (pause_after_ms, target) in [(None, 0.8), (Some(100.0), 0.5)] {
+      let fitted = fit_block_duration(&query, &preset, pause_after_ms, target).unwrap();
+      let rendered = apply_preset(&fitted, &preset, pause_after_ms);
+      let duration = query_timing(&rendered).unwrap().duration;
+      assert!((duration - target).abs() <= 256.0 / 24_000.0, "{duration}");
+      assert_eq!(fitted.speed_scale, query.speed_scale);
+    }
+  }
+
+  #[test]
+  fn preset_styles_prefer_the_speaker_fallback_over_the_style_id() {
+    let characters = characters();
+    let mut preset = Preset {
+      style_id: StyleId::new(1),
+      ..Preset::default()
+    };
+    assert_eq!(preset_style_id(&preset, &characters), Some(StyleId::new(1)));
+
+    preset.speaker_uuid = Some("second-uuid".into());
+    preset.style_name = Some("Whisper".into());
+    assert_eq!(preset_style_id(&preset, &characters), Some(StyleId::new(3)));
+
+    preset.style_name = Some("Missing".into());
+    assert_eq!(preset_style_id(&preset, &characters), None);
+
+    let unknown = Preset {
+      style_id: StyleId::new(99),
+      ..Preset::default()
+    };
+    assert_eq!(preset_style_id(&unknown, &characters), None);
+  }
+
+  #[test]
+  fn project_styles_follow_the_blocks_that_speak() {
+    let preset = |id: &str, style_id: u32| Preset {
+      id: id.into(),
+      style_id: StyleId::new(style_id),
+      ..Preset::default()
+    };
+    let block = |text: &str, preset_id: &str, muted: bool| -> TextBlockProps {
+      serde_json::from_value(json!({
+        "id": text,
+        "text": text,
+        "query": null,
+        "query_is_modified": false,
+        "preset_id": preset_id,
+        "muted": muted
+      }))
+      .unwrap()
+    };
+    let project = Project {
+      blocks: vec![
+        block("second", "second", false),
+        block("", "first", false),
+        block("first", "first", false),
+        block("again", "second-copy", false),
+        block("muted", "whisper", true),
+        block("unknown", "unknown", false),
+      ],
+      presets: vec![
+        preset("first", 1),
+        preset("second", 2),
+        preset("second-copy", 2),
+        preset("whisper", 3),
+        preset("unknown", 99),
+      ],
+      ..Project::default()
+    };
+
+    assert_eq!(
+      project_style_ids(&project, &characters()),
+      [StyleId::new(2), StyleId::new(1)]
+    );
+  }
+
+  #[test]
+  fn waveforms_are_joined_in_order() {
+    let joined =
+      concat_wavs(&[(0.0, wav(1, 24_000, &[1, 2])), (0.0, wav(1, 24_000, &[3]))]).unwrap();
+
+    let (spec, samples) = decode_pcm16(&joined).unwrap();
+    assert_eq!(spec.sample_rate, 24_000);
+    assert_eq!(spec.channels, 1);
+    assert_eq!(samples, vec![1, 2, 3]);
+  }
+
+  #[test]
+  fn waveforms_are_padded_with_silence_up_to_their_offsets() {
+    let joined = concat_wavs(&[
+      (0.5, wav(2, 4, &[1, 1])),
+      (1.0, wav(2, 4, &[2, 2])),
+      (0.0, wav(2, 4, &[3, 3])),
+    ])
+    .unwrap();
+
+    let (_, samples) = decode_pcm16(&joined).unwrap();
+    assert_eq!(samples, vec![0, 0, 0, 0, 1, 1, 0, 0, 2, 2, 3, 3]);
+  }
+
+  #[test]
+  fn joining_rejects_mismatched_formats_and_empty_renders() {
+    let error =
+      concat_wavs(&[(0.0, wav(1, 24_000, &[1])), (0.0, wav(2, 24_000, &[1, 1]))]).unwrap_err();
+    assert!(error.contains("block 1 has a different WAV format"));
+
+    assert!(concat_wavs(&[]).is_err());
+    assert!(concat_wavs(&[(0.0, b"not a wave file".to_vec())])
+      .unwrap_err()
+      .contains("Failed to decode WAV"));
+  }
+}
@@ -0,0 +1,269 @@
+//! Project-wide search and replace over block text and the readings VOICEVOX gives it.
+use regex::{NoExpand, Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use voicevox_core::AccentPhrase;
+
+use crate::config::types::TextBlockProps;
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize, specta::Type)]
+pub enum SearchTarget {
+  /// The text of each block.
+  #[default]
+  Text,
+  /// The katakana reading of each block, as spoken by the core.
+  Reading,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, specta::Type)]
+pub struct SearchQuery {
+  pub pattern: String,
+  /// Treats `pattern` as a regular expression instead of literal text.
+  #[serde(default)]
+  pub regex: bool,
+  #[serde(default)]
+  pub case_sensitive: bool,
+  #[serde(default)]
+  pub target: SearchTarget,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize, specta::Type)]
+pub struct SearchMatch {
+  pub block_id: String,
+  /// Where the match starts in the searched text or reading, in UTF-16 code units like
+  /// JavaScript string indices.
+  pub start: u32,
+  pub end: u32,
+  pub matched: String,
+}
+
+/// Readings are katakana, so hiragana matches the same sounds.
+pub(crate) fn to_katakana(text: &str) -> String {
+  text
+    .chars()
+    .map(|c| match c {
+      'ぁ'..='ゖ' | 'ゝ' | 'ゞ' => char::from_u32(c as u32 + 0x60).unwrap_or(c),
+      _ => c,
+    })
+    .collect()
+}
+
+/// Compiles a search query. Patterns that match empty text are rejected, as they would match
+/// between every character.
+pub(crate) fn compile_query(query: &SearchQuery) -> Result<Regex, String> {
+  let pattern = match query.target {
+    SearchTarget::Text => query.pattern.clone(),
+    SearchTarget::Reading => to_katakana(&query.pattern),
+  };
+  let pattern = if query.regex {
+    pattern
+  } else {
+    regex::escape(&pattern)
+  };
+  let compiled = RegexBuilder::new(&pattern)
+    .case_insensitive(!query.case_sensitive)
+    .build()
+    .map_err(|e| format!("Invalid search pattern: {e}"))?;
+  if compiled.is_match("") {
+    return Err("Search pattern matches empty text".into());
+  }
+  Ok(compiled)
+}
+
+/// What the core reads accent phrases as, with pauses read as their pause mora, usually `、`.
+pub(crate) fn reading(accent_phrases: &[AccentPhrase]) -> String {
+  let mut reading = String::new();
+  for phrase in accent_phrases {
+    for mora in &phrase.moras {
+      reading.push_str(&mora.text);
+    }
+    if let Some(pause) = &phrase.pause_mora {
+      reading.push_str(&pause.text);
+    }
+  }
+  reading
+}
+
+pub(crate) fn find_matches(block_id: &str, haystack: &str, pattern: &Regex) -> Vec<SearchMatch> {
+  let mut searched = 0;
+  let mut offset = 0;
+  pattern
+    .find_iter(haystack)
+    .map(|found| {
+      offset += haystack[searched..found.start()].encode_utf16().count();
+      let start = offset;
+      offset += found.as_str().encode_utf16().count();
+      searched = found.end();
+      SearchMatch {
+        block_id: block_id.into(),
+        start: start as u32,
+        end: offset as u32,
+        matched: found.as_str().into(),
+      }
+    })
+    .collect()
+}
+
+/// Replaces every match in the text of `blocks`, expanding `$1`-style groups when `expand` is set.
+/// A changed block loses its query unless it was edited by hand, so a fresh one is made for the
+/// new text. Returns the IDs of the changed blocks and how many matches were replaced.
+pub(crate) fn replace_in_blocks(
+  blocks: &mut [TextBlockProps],
+  pattern: &Regex,
+  replacement: &str,
+  expand: bool,
+) -> (Vec<String>, u32) {
+  let mut changed = Vec::new();
+  let mut replacements = 0;
+  for block in blocks {
+    let matches = pattern.find_iter(&block.text).count();
+    if matches == 0 {
+      continue;
+    }
+    let text = if expand {
+      pattern.replace_all(&block.text, replacement)
+    } else {
+      pattern.replace_all(&block.text, NoExpand(replacement))
+    };
+    if text == block.text {
+      continue;
+    }
+    block.text = text.into_owned();
+    if !block.query_is_modified {
+      block.query = None;
+    }
+    changed.push(block.id.clone());
+    replacements += matches as u32;
+  }
+  (changed, replacements)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use serde_json::json;
+
+  fn query(pattern: &str, regex: bool, target: SearchTarget) -> SearchQuery {
+    SearchQuery {
+      pattern: pattern.into(),
+      regex,
+      case_sensitive: false,
+      target,
+    }
+  }
+
+  fn block(id: &str, text: &str, query_is_modified: bool) -> TextBlockProps {
+    serde_json::from_value(json!({
+      "id": id,
+      "text": text,
+      "query": {
+        "accent_phrases": [],
+        "speedScale": 1.0,
+        "pitchScale": 0.0,
+        "intonationScale": 1.0,
+        "volumeScale": 1.0,
+        "prePhonemeLength": 0.1,
+        "postPhonemeLength": 0.1,
+        "outputSamplingRate": 24000,
+        "outputStereo": false
+      },
+      "query_is_modified": query_is_modified,
+      "preset_id": null
+    }))
+    .unwrap()
+  }
+
+  #[test]
+  fn matches_report_utf16_offsets() {
+    let pattern = compile_query(&query("azalea", false, SearchTarget::Text)).unwrap();
+
+    let matches = find_matches("block-1", "🌺 Azalea と azalea", &pattern);
+
+    assert_eq!(
+      matches
+        .iter()
+        .map(|found| (found.start, found.end, found.matched.as_str()))
+        .collect::<Vec<_>>(),
+      vec![(3, 9, "Azalea"), (12, 18, "azalea")]
+    );
+  }
+
+  #[test]
+  fn literal_patterns_are_escaped_and_regex_patterns_are_checked() {
+    let literal = compile_query(&query("1.5", false, SearchTarget::Text)).unwrap();
+    assert!(!literal.is_match("125"));
+    assert!(literal.is_match("x1.5"));
+
+    let regex = compile_query(&query(r"\d+\.\d", true, SearchTarget::Text)).unwrap();
+    assert!(regex.is_match("v2.0"));
+
+    let mut exact = query("Azalea", false, SearchTarget::Text);
+    exact.case_sensitive = true;
+    assert!(!compile_query(&exact).unwrap().is_match("azalea"));
+
+    for (pattern, regex) in [("", false), ("(", true), ("a*", true)] {
+      assert!(
+        compile_query(&query(pattern, regex, SearchTarget::Text)).is_err(),
+        "{pattern}"
+      );
+    }
+  }
+
+  #[test]
+  fn readings_are_searched_in_katakana() {
+    let accent_phrases: Vec<AccentPhrase> = serde_json::from_value(json!([
+      {
+        "moras": [
+          { "text": "コ", "consonant": "k", "consonant_length": 0.1, "vowel": "o", "vowel_length": 0.1, "pitch": 5.0 },
+          { "text": "ン", "consonant": null, "consonant_length": null, "vowel": "N", "vowel_length": 0.1, "pitch": 5.0 }
+        ],
+        "accent": 1,
+        "pause_mora": { "text": "、", "consonant": null, "consonant_length": null, "vowel": "pau", "vowel_length": 0.3, "pitch": 0.0 }
+      },
+      {
+        "moras": [
+          { "text": "ワ", "consonant": "w", "consonant_length": 0.1, "vowel": "a", "vowel_length": 0.1, "pitch": 5.0 }
+        ],
+        "accent": 1,
+        "pause_mora": null
+      }
+    ]))
+    .unwrap();
+    let reading = reading(&accent_phrases);
+    assert_eq!(reading, "コン、ワ");
+
+    let pattern = compile_query(&query("こん", false, SearchTarget::Reading)).unwrap();
+    let matches = find_matches("block-1", &reading, &pattern);
+    assert_eq!(matches.len(), 1);
+    assert_eq!((matches[0].start, matches[0].end), (0, 2));
+  }
+
+  #[test]
+  fn replacements_drop_only_unedited_queries() {
+    let mut blocks = vec![
+      block("plain", "colour and colour", false),
+      block("edited", "colour", true),
+      block("untouched", "color", false),
+    ];
+    let pattern = compile_query(&query("colou?r", true, SearchTarget::Text)).unwrap();
+
+    let (changed, replacements) = replace_in_blocks(&mut blocks, &pattern, "color", false);
+
+    assert_eq!(changed, ["plain", "edited"]);
+    assert_eq!(replacements, 3);
+    assert_eq!(blocks[0].text, "color and color");
+    assert!(blocks[0].query.is_none());
+    assert!(blocks[1].query.is_some());
+    assert!(blocks[2].query.is_some());
+
+    let pattern = compile_query(&query(r"(\w+) and (\w+)", true, SearchTarget::Text)).unwrap();
+    replace_in_blocks(&mut blocks, &pattern, "$2 & $1", true);
+    assert_eq!(blocks[0].text, "color & color");
+    replace_in_blocks(
+      &mut blocks,
+      &compile_query(&query("&", false, SearchTarget::Text)).unwrap(),
+      "$1",
+      false,
+    );
+    assert_eq!(blocks[0].text, "color $1 color");
+  }
+}
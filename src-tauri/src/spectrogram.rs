@@ -134,6 +134,10 @@ impl SpectrogramQueue {
     self.0.next().await
   }
 
+  pub fn set_max_running(&self, max_running: usize) {
+    self.0.set_max_running(max_running)
+  }
+
   #[cfg(test)]
   fn pop_next(&self) -> Option<QueuedJob<SpectrogramJob>> {
     self.0.pop_next()
@@ -0,0 +1,256 @@
+//! Project statistics for quoting voice work and checking runtimes before rendering.
+use serde::{Deserialize, Serialize};
+use voicevox_core::{AudioQuery, StyleId};
+
+use crate::timing::QueryTiming;
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize, specta::Type)]
+pub struct StatisticsTotals {
+  pub blocks: u32,
+  /// Characters of text, not counting whitespace.
+  pub characters: u32,
+  /// Spoken moras, not counting pauses.
+  pub moras: u32,
+  /// Synthesized length in seconds, including the silences around each block.
+  pub seconds: f64,
+  /// Length of the speech alone in seconds.
+  pub speech_seconds: f64,
+}
+
+impl StatisticsTotals {
+  fn add(&mut self, block: &BlockStatistics) {
+    self.blocks += 1;
+    self.characters += block.characters;
+    self.moras += block.moras;
+    self.seconds += block.seconds;
+    self.speech_seconds += block.speech_seconds;
+  }
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize, specta::Type)]
+pub struct BlockStatistics {
+  pub block_id: String,
+  pub style_id: StyleId,
+  pub preset_id: String,
+  pub characters: u32,
+  pub moras: u32,
+  pub seconds: f64,
+  pub speech_seconds: f64,
+  /// Whether the waveform cache holds this block's current take.
+  pub synthesized: bool,
+}
+
+impl BlockStatistics {
+  pub(crate) fn new(
+    block_id: String,
+    text: &str,
+    query: &AudioQuery,
+    timing: QueryTiming,
+    style_id: StyleId,
+    preset_id: String,
+    synthesized: bool,
+  ) -> Self {
+    Self {
+      block_id,
+      style_id,
+      preset_id,
+      characters: text.chars().filter(|c| !c.is_whitespace()).count() as u32,
+      moras: query
+        .accent_phrases
+        .iter()
+        .map(|phrase| phrase.moras.len() as u32)
+        .sum(),
+      seconds: timing.duration,
+      speech_seconds: timing.speech_end - timing.speech_start,
+      synthesized,
+    }
+  }
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize, specta::Type)]
+pub struct StyleStatistics {
+  pub style_id: StyleId,
+  pub totals: StatisticsTotals,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize, specta::Type)]
+pub struct PresetStatistics {
+  pub preset_id: String,
+  pub totals: StatisticsTotals,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize, specta::Type)]
+pub struct ProjectStatistics {
+  pub totals: StatisticsTotals,
+  /// Length of the rendered project in seconds. Blocks timed to subtitle cues can leave gaps, so
+  /// this can be longer than `totals.seconds`.
+  pub render_seconds: f64,
+  pub blocks: Vec<BlockStatistics>,
+  /// Time per style, in the order the styles first speak.
+  pub styles: Vec<StyleStatistics>,
+  /// Time per preset, in the order the presets are first used.
+  pub presets: Vec<PresetStatistics>,
+  /// Blocks whose current take has not been synthesized yet.
+  pub unsynthesized_block_ids: Vec<String>,
+}
+
+impl ProjectStatistics {
+  pub(crate) fn new(blocks: Vec<BlockStatistics>, render_seconds: f64) -> Self {
+    let mut totals = StatisticsTotals::default();
+    let mut styles: Vec<StyleStatistics> = Vec::new();
+    let mut presets: Vec<PresetStatistics> = Vec::new();
+    for block in &blocks {
+      totals.add(block);
+      match styles
+        .iter_mut()
+        .find(|style| style.style_id == block.style_id)
+      {
+        Some(style) => style.totals.add(block),
+        None => {
+          let mut style = StyleStatistics {
+            style_id: block.style_id,
+            totals: StatisticsTotals::default(),
+          };
+          style.totals.add(block);
+          styles.push(style);
+        }
+      }
+      match presets
+        .iter_mut()
+        .find(|preset| preset.preset_id == block.preset_id)
+      {
+        Some(preset) => preset.totals.add(block),
+        None => {
+          let mut preset = PresetStatistics {
+            preset_id: block.preset_id.clone(),
+            totals: StatisticsTotals::default(),
+          };
+          preset.totals.add(block);
+          presets.push(preset);
+        }
+      }
+    }
+    let unsynthesized_block_ids = blocks
+      .iter()
+      .filter(|block| !block.synthesized)
+      .map(|block| block.block_id.clone())
+      .collect();
+    Self {
+      totals,
+      render_seconds,
+      blocks,
+      styles,
+      presets,
+      unsynthesized_block_ids,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use serde_json::json;
+
+  fn query(moras: usize) -> AudioQuery {
+    let mora = json!({
+      "text": "ア",
+      "consonant": null,
+      "consonant_length": null,
+      "vowel": "a",
+      "vowel_length": 0.1,
+      "pitch": 5.0
+    });
+    serde_json::from_value(json!({
+      "accent_phrases": [{
+        "moras": vec![mora.clone(); moras],
+        "accent": 1,
+        "pause_mora": mora,
+        "is_interrogative": false
+      }],
+      "speedScale": 1.0,
+      "pitchScale": 0.0,
+      "intonationScale": 1.0,
+      "volumeScale": 1.0,
+      "prePhonemeLength": 0.1,
+      "postPhonemeLength": 0.1,
+      "outputSamplingRate": 24000,
+      "outputStereo": false
+    }))
+    .unwrap()
+  }
+
+  fn block(id: &str, text: &str, style_id: u32, preset_id: &str, seconds: f64) -> BlockStatistics {
+    let timing = QueryTiming {
+      duration: seconds,
+      speech_start: 0.25,
+      speech_end: seconds - 0.25,
+    };
+    BlockStatistics::new(
+      id.into(),
+      text,
+      &query(text.chars().count()),
+      timing,
+      StyleId(style_id),
+      preset_id.into(),
+      id != "draft",
+    )
+  }
+
+  #[test]
+  fn blocks_count_characters_without_whitespace_and_moras_without_pauses() {
+    let timing = QueryTiming {
+      duration: 2.0,
+      speech_start: 0.25,
+      speech_end: 1.75,
+    };
+    // the query has a pause mora after its two moras
+    let block = BlockStatistics::new(
+      "block-1".into(),
+      "あい う\n",
+      &query(2),
+      timing,
+      StyleId(1),
+      "preset".into(),
+      true,
+    );
+
+    assert_eq!(block.characters, 3);
+    assert_eq!(block.moras, 2);
+    assert_eq!((block.seconds, block.speech_seconds), (2.0, 1.5));
+  }
+
+  #[test]
+  fn totals_are_grouped_by_style_and_preset_in_order_of_use() {
+    let statistics = ProjectStatistics::new(
+      vec![
+        block("intro", "あ", 3, "narrator", 1.0),
+        block("line", "いい", 1, "guest", 2.0),
+        block("draft", "ううう", 3, "narrator-fast", 4.0),
+      ],
+      10.0,
+    );
+
+    assert_eq!(statistics.totals.blocks, 3);
+    assert_eq!(statistics.totals.characters, 6);
+    assert_eq!(statistics.totals.seconds, 7.0);
+    assert_eq!(statistics.totals.speech_seconds, 5.5);
+    assert_eq!(statistics.render_seconds, 10.0);
+    assert_eq!(
+      statistics
+        .styles
+        .iter()
+        .map(|style| (style.style_id.0, style.totals.blocks, style.totals.seconds))
+        .collect::<Vec<_>>(),
+      vec![(3, 2, 5.0), (1, 1, 2.0)]
+    );
+    assert_eq!(
+      statistics
+        .presets
+        .iter()
+        .map(|preset| (preset.preset_id.as_str(), preset.totals.characters))
+        .collect::<Vec<_>>(),
+      vec![("narrator", 1), ("guest", 2), ("narrator-fast", 3)]
+    );
+    assert_eq!(statistics.unsynthesized_block_ids, ["draft"]);
+  }
+}
@@ -0,0 +1,411 @@
+//! Subtitle documents: writing them for rendered projects and reading existing tracks back
+//! in as blocks.
+use serde::{Deserialize, Serialize};
+
+use crate::config::types::BlockTiming;
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize, specta::Type)]
+pub enum SubtitleFormat {
+  Srt,
+  WebVtt,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct SubtitleCue {
+  /// in seconds from the start of the render
+  pub start: f64,
+  /// in seconds from the start of the render
+  pub end: f64,
+  pub text: String,
+}
+
+fn format_timestamp(seconds: f64, millisecond_separator: char) -> String {
+  let total_millis = (seconds.max(0.0) * 1000.0).round() as u64;
+  let (hours, rest) = (total_millis / 3_600_000, total_millis % 3_600_000);
+  let (minutes, rest) = (rest / 60_000, rest % 60_000);
+  let (seconds, millis) = (rest / 1000, rest % 1000);
+  format!("{hours:02}:{minutes:02}:{seconds:02}{millisecond_separator}{millis:03}")
+}
+
+/// Cue payloads may not contain blank lines in either format.
+fn cue_lines(text: &str) -> impl Iterator<Item = &str> {
+  text.lines().map(str::trim).filter(|line| !line.is_empty())
+}
+
+fn escape_webvtt(line: &str) -> String {
+  line
+    .replace('&', "&amp;")
+    .replace('<', "&lt;")
+    .replace('>', "&gt;")
+}
+
+pub(crate) fn write_subtitles(cues: &[SubtitleCue], format: SubtitleFormat) -> String {
+  let mut document = String::new();
+  if format == SubtitleFormat::WebVtt {
+    document.push_str("WEBVTT\n\n");
+  }
+  for (index, cue) in cues.iter().enumerate() {
+    let (start, end) = match format {
+      SubtitleFormat::Srt => {
+        document.push_str(&format!("{}\n", index + 1));
+        (
+          format_timestamp(cue.start, ','),
+          format_timestamp(cue.end, ','),
+        )
+      }
+      SubtitleFormat::WebVtt => (
+        format_timestamp(cue.start, '.'),
+        format_timestamp(cue.end, '.'),
+      ),
+    };
+    document.push_str(&format!("{start} --> {end}\n"));
+    for line in cue_lines(&cue.text) {
+      match format {
+        SubtitleFormat::Srt => document.push_str(line),
+        SubtitleFormat::WebVtt => document.push_str(&escape_webvtt(line)),
+      }
+      document.push('\n');
+    }
+    document.push('\n');
+  }
+  document
+}
+
+/// A cue read from an existing subtitle track. Cues without a timestamp line keep no timing.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct ImportedCue {
+  pub text: String,
+  pub timing: Option<BlockTiming>,
+}
+
+/// Parses `H:MM:SS.fff`, `MM:SS,fff` and ASS `H:MM:SS.cc` timestamps into seconds.
+fn parse_timestamp(timestamp: &str) -> Option<f64> {
+  let (clock, fraction) = match timestamp.trim().rsplit_once([',', '.']) {
+    Some((clock, fraction)) => (clock, fraction),
+    None => (timestamp.trim(), ""),
+  };
+  let mut seconds = 0.0;
+  let parts = clock.split(':').collect::<Vec<_>>();
+  if !(2..=3).contains(&parts.len()) {
+    return None;
+  }
+  for part in parts {
+    if part.is_empty() || !part.bytes().all(|byte| byte.is_ascii_digit()) {
+      return None;
+    }
+    seconds = seconds * 60.0 + part.parse::<f64>().ok()?;
+  }
+  if !fraction.is_empty() {
+    if !fraction.bytes().all(|byte| byte.is_ascii_digit()) {
+      return None;
+    }
+    seconds += fraction.parse::<f64>().ok()? / 10f64.powi(fraction.len() as i32);
+  }
+  Some(seconds)
+}
+
+fn cue_timing(start: &str, end: &str) -> Option<BlockTiming> {
+  let (start, end) = (parse_timestamp(start)?, parse_timestamp(end)?);
+  (end >= start).then_some(BlockTiming { start, end })
+}
+
+/// Joins cue lines with spaces and collapses whitespace. Markup between `open` and `close`
+/// becomes `replacement`: SRT tags break words like in `parseSrt` on the frontend, while ASS
+/// override blocks may sit inside a word.
+fn plain_text<'a>(
+  lines: impl IntoIterator<Item = &'a str>,
+  (open, close): (char, char),
+  replacement: &str,
+) -> String {
+  let mut text = String::new();
+  let mut in_tag = false;
+  for line in lines {
+    text.push(' ');
+    for character in line.chars() {
+      match character {
+        _ if character == open => in_tag = true,
+        _ if character == close && in_tag => {
+          in_tag = false;
+          text.push_str(replacement);
+        }
+        _ if !in_tag => text.push(character),
+        _ => {}
+      }
+    }
+  }
+  text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn parse_srt(document: &str) -> Result<Vec<ImportedCue>, String> {
+  let mut cues = Vec::new();
+  let mut lines = Vec::new();
+  // the WebVTT header block carries no cue
+  let mut skip_block = document.starts_with("WEBVTT");
+  // a trailing empty line flushes the last cue
+  for line in document.lines().chain([""]) {
+    let line = line.trim();
+    if !line.is_empty() {
+      lines.push(line);
+      continue;
+    }
+    if lines.is_empty() {
+      continue;
+    }
+    if std::mem::take(&mut skip_block) {
+      lines.clear();
+      continue;
+    }
+    let timing_index = lines.iter().position(|line| line.contains("-->"));
+    let (timing, text_lines) = match timing_index {
+      Some(index) => {
+        let (start, rest) = lines[index].split_once("-->").unwrap_or_default();
+        // WebVTT cue settings may follow the end timestamp
+        let end = rest.split_whitespace().next().unwrap_or_default();
+        let timing = cue_timing(start, end).ok_or_else(|| {
+          format!(
+            "Subtitle cue {} has an invalid timestamp: {}",
+            cues.len() + 1,
+            lines[index]
+          )
+        })?;
+        (Some(timing), &lines[index + 1..])
+      }
+      None => (None, &lines[..]),
+    };
+    let text = plain_text(text_lines.iter().copied(), ('<', '>'), " ");
+    if !text.is_empty() {
+      cues.push(ImportedCue { text, timing });
+    }
+    lines.clear();
+  }
+  Ok(cues)
+}
+
+/// The event format both ASS and SSA fall back to when a file has no `Format:` line.
+const DEFAULT_ASS_EVENT_FORMAT: &str =
+  "Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text";
+
+fn parse_ass(document: &str) -> Result<Vec<ImportedCue>, String> {
+  let mut in_events = false;
+  let mut format = DEFAULT_ASS_EVENT_FORMAT
+    .split(',')
+    .map(|field| field.trim().to_ascii_lowercase())
+    .collect::<Vec<_>>();
+  let mut cues = Vec::new();
+  for (line_index, line) in document.lines().enumerate() {
+    let line = line.trim();
+    if line.starts_with('[') {
+      in_events = line.eq_ignore_ascii_case("[events]");
+      continue;
+    }
+    if !in_events {
+      continue;
+    }
+    let Some((kind, value)) = line.split_once(':') else {
+      continue;
+    };
+    match kind.trim() {
+      "Format" => {
+        format = value
+          .split(',')
+          .map(|field| field.trim().to_ascii_lowercase())
+          .collect();
+      }
+      "Dialogue" => {
+        // the text is always the last field and may contain commas itself
+        let fields = value.splitn(format.len(), ',').collect::<Vec<_>>();
+        let field = |name: &str| {
+          format
+            .iter()
+            .position(|field| field == name)
+            .and_then(|index| fields.get(index))
+            .copied()
+        };
+        let (Some(start), Some(end), Some(text)) = (field("start"), field("end"), field("text"))
+        else {
+          return Err(format!(
+            "Subtitle dialogue on line {} is missing fields",
+            line_index + 1
+          ));
+        };
+        let timing = cue_timing(start, end).ok_or_else(|| {
+          format!(
+            "Subtitle dialogue on line {} has an invalid timestamp",
+            line_index + 1
+          )
+        })?;
+        let text = text
+          .replace("\\N", " ")
+          .replace("\\n", " ")
+          .replace("\\h", " ");
+        let text = plain_text([text.as_str()], ('{', '}'), "");
+        if !text.is_empty() {
+          cues.push(ImportedCue {
+            text,
+            timing: Some(timing),
+          });
+        }
+      }
+      _ => {}
+    }
+  }
+  // ASS events are not required to be in playback order
+  cues.sort_by(|a, b| {
+    let start = |cue: &ImportedCue| cue.timing.map_or(0.0, |timing| timing.start);
+    start(a).total_cmp(&start(b))
+  });
+  Ok(cues)
+}
+
+/// Reads an SRT (or WebVTT) or ASS/SSA document into cues in playback order.
+pub(crate) fn parse_subtitles(document: &str) -> Result<Vec<ImportedCue>, String> {
+  let document = document.trim_start_matches('\u{feff}');
+  let is_ass = document.lines().any(|line| {
+    let line = line.trim();
+    line.eq_ignore_ascii_case("[script info]") || line.eq_ignore_ascii_case("[events]")
+  });
+  if is_ass {
+    parse_ass(document)
+  } else {
+    parse_srt(document)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn cues() -> Vec<SubtitleCue> {
+    vec![
+      SubtitleCue {
+        start: 0.1,
+        end: 1.2346,
+        text: "こんにちは".into(),
+      },
+      SubtitleCue {
+        start: 3_723.5,
+        end: 3_725.0,
+        text: "first line\n\n  a <b> & c  \n".into(),
+      },
+    ]
+  }
+
+  #[test]
+  fn timestamps_cover_hours_and_round_to_milliseconds() {
+    assert_eq!(format_timestamp(0.0, ','), "00:00:00,000");
+    assert_eq!(format_timestamp(1.2346, '.'), "00:00:01.235");
+    assert_eq!(format_timestamp(3_723.5, ','), "01:02:03,500");
+    assert_eq!(format_timestamp(-1.0, ','), "00:00:00,000");
+  }
+
+  #[test]
+  fn srt_documents_number_cues_and_drop_blank_lines() {
+    assert_eq!(
+      write_subtitles(&cues(), SubtitleFormat::Srt),
+      "1\n00:00:00,100 --> 00:00:01,235\nこんにちは\n\n\
+       2\n01:02:03,500 --> 01:02:05,000\nfirst line\na <b> & c\n\n"
+    );
+  }
+
+  #[test]
+  fn webvtt_documents_have_a_header_and_escape_markup() {
+    assert_eq!(
+      write_subtitles(&cues(), SubtitleFormat::WebVtt),
+      "WEBVTT\n\n\
+       00:00:00.100 --> 00:00:01.235\nこんにちは\n\n\
+       01:02:03.500 --> 01:02:05.000\nfirst line\na &lt;b&gt; &amp; c\n\n"
+    );
+  }
+
+  fn timed(text: &str, start: f64, end: f64) -> ImportedCue {
+    ImportedCue {
+      text: text.into(),
+      timing: Some(BlockTiming { start, end }),
+    }
+  }
+
+  #[test]
+  fn srt_cues_keep_their_timings_and_plain_text() {
+    let document = "\u{feff}1\r\n00:00:00,000 --> 00:00:02,000\r\nHello there\r\n\r\n\
+                    2\n00:00:02,500 --> 00:00:05,000\nLine one\nLine two\n\n\n\
+                    3\n01:00:05,500 --> 01:00:07,250\n<i>Goodbye</i> now\n\n\
+                    4\n00:00:08,000 --> 00:00:09,000\n<b></b>\n";
+
+    assert_eq!(
+      parse_subtitles(document).unwrap(),
+      vec![
+        timed("Hello there", 0.0, 2.0),
+        timed("Line one Line two", 2.5, 5.0),
+        timed("Goodbye now", 3_605.5, 3_607.25),
+      ]
+    );
+  }
+
+  #[test]
+  fn untimed_text_and_webvtt_documents_are_accepted() {
+    assert_eq!(
+      parse_subtitles("Just some\nlines").unwrap(),
+      vec![ImportedCue {
+        text: "Just some lines".into(),
+        timing: None,
+      }]
+    );
+    assert_eq!(
+      parse_subtitles(
+        "WEBVTT - dub\nKind: captions\n\n00:01.500 --> 00:02.000 align:start\nこんにちは\n"
+      )
+      .unwrap(),
+      vec![timed("こんにちは", 1.5, 2.0)]
+    );
+    assert!(parse_subtitles("").unwrap().is_empty());
+  }
+
+  #[test]
+  fn malformed_srt_timestamps_are_reported() {
+    for document in [
+      "1\n00:00:xx,000 --> 00:00:01,000\nText\n",
+      "1\n00:00:02,000 --> 00:00:01,000\nBackwards\n",
+      "1\n00:00:01,000 -->\nNo end\n",
+    ] {
+      assert!(
+        parse_subtitles(document)
+          .unwrap_err()
+          .contains("cue 1 has an invalid timestamp"),
+        "{document}"
+      );
+    }
+  }
+
+  #[test]
+  fn ass_dialogue_follows_the_event_format_and_playback_order() {
+    let document = "[Script Info]\nTitle: Dub\n\n\
+                    [V4+ Styles]\nFormat: Name, Fontname\nStyle: Default,Arial\n\n\
+                    [Events]\n\
+                    Format: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\n\
+                    Dialogue: 0,0:00:03.50,0:00:04.00,Default,,0,0,0,,{\\i1}Second{\\i0}, with a comma\n\
+                    Comment: 0,0:00:00.00,0:00:01.00,Default,,0,0,0,,Not spoken\n\
+                    Dialogue: 0,0:00:01.00,0:00:02.25,Default,,0,0,0,,First\\Nline\n";
+
+    assert_eq!(
+      parse_subtitles(document).unwrap(),
+      vec![
+        timed("First line", 1.0, 2.25),
+        timed("Second, with a comma", 3.5, 4.0),
+      ]
+    );
+  }
+
+  #[test]
+  fn ssa_dialogue_without_a_format_line_uses_the_default_fields() {
+    let document = "[Events]\nDialogue: Marked=0,0:00:01.00,0:00:02.00,*Default,,0,0,0,,Hello\n";
+    assert_eq!(
+      parse_subtitles(document).unwrap(),
+      vec![timed("Hello", 1.0, 2.0)]
+    );
+
+    let broken = "[Events]\nDialogue: 0,0:00:01.00\n";
+    assert!(parse_subtitles(broken)
+      .unwrap_err()
+      .contains("line 2 is missing fields"));
+  }
+}
@@ -7,6 +7,7 @@ use voicevox_core::{AudioQuery, StyleId};
 #[cfg(test)]
 use crate::async_job::DEFAULT_QUEUE_CAPACITY;
 use crate::async_job::{LatestJob, LatestJobQueue, QueueEvent, QueueEventState, QueuedJob};
+use crate::cache_key::CacheKey;
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub(crate) enum SynthesisBackend {
@@ -142,6 +143,10 @@ impl SynthesisQueue {
     self.0.next().await
   }
 
+  pub fn set_max_running(&self, max_running: usize) {
+    self.0.set_max_running(max_running)
+  }
+
   #[cfg(test)]
   fn pop_next(&self) -> Option<QueuedJob<SynthesisJob>> {
     self.0.pop_next()
@@ -169,16 +174,24 @@ pub(crate) struct WaveformCacheOwner {
 pub(crate) struct WaveformCacheEntry {
   pub cell: Arc<OnceCell<Vec<u8>>>,
   pub owners: Vec<WaveformCacheOwner>,
+  /// Size of the WAV file in the cell as last weighed by the cache, 0 while it is synthesized.
+  bytes: u64,
 }
 
 impl WaveformCacheEntry {
   pub fn new(cell: Arc<OnceCell<Vec<u8>>>) -> Self {
+    let bytes = wav_bytes(&cell);
     Self {
       cell,
       owners: Vec::new(),
+      bytes,
     }
   }
 
+  pub fn bytes(&self) -> u64 {
+    self.bytes
+  }
+
   pub fn add_owner(&mut self, owner: WaveformCacheOwner) {
     self
       .owners
@@ -187,6 +200,120 @@ impl WaveformCacheEntry {
   }
 }
 
+fn wav_bytes(cell: &OnceCell<Vec<u8>>) -> u64 {
+  cell.get().map_or(0, |wav| wav.len() as u64)
+}
+
+pub(crate) type WaveformCacheKey = CacheKey;
+
+/// Synthesized waveforms kept in memory, weighed by the size of their WAV files. Past the budget
+/// the least recently used waveforms are dropped, but never the one used last, so a waveform
+/// larger than the whole budget is still kept until the next one comes in.
+pub(crate) struct WaveformCache {
+  entries: lru::LruCache<WaveformCacheKey, WaveformCacheEntry>,
+  budget_bytes: u64,
+  bytes: u64,
+}
+
+impl WaveformCache {
+  pub fn new(budget_bytes: u64) -> Self {
+    Self {
+      entries: lru::LruCache::unbounded(),
+      budget_bytes,
+      bytes: 0,
+    }
+  }
+
+  pub fn get(&mut self, key: &WaveformCacheKey) -> Option<&WaveformCacheEntry> {
+    self.entries.get(key)
+  }
+
+  pub fn get_mut(&mut self, key: &WaveformCacheKey) -> Option<&mut WaveformCacheEntry> {
+    self.entries.get_mut(key)
+  }
+
+  /// Looks up an entry without making it the most recently used.
+  pub fn peek(&self, key: &WaveformCacheKey) -> Option<&WaveformCacheEntry> {
+    self.entries.peek(key)
+  }
+
+  pub fn contains(&self, key: &WaveformCacheKey) -> bool {
+    self.entries.contains(key)
+  }
+
+  /// Entries from the most to the least recently used.
+  pub fn iter(&self) -> impl Iterator<Item = (&WaveformCacheKey, &WaveformCacheEntry)> {
+    self.entries.iter()
+  }
+
+  /// Drops the entry of `key`, returning it.
+  pub fn pop(&mut self, key: &WaveformCacheKey) -> Option<WaveformCacheEntry> {
+    let entry = self.entries.pop(key)?;
+    self.bytes -= entry.bytes;
+    Some(entry)
+  }
+
+  /// Drops every entry, returning them.
+  pub fn clear(&mut self) -> Vec<(WaveformCacheKey, WaveformCacheEntry)> {
+    let mut dropped = Vec::with_capacity(self.entries.len());
+    while let Some(entry) = self.entries.pop_lru() {
+      dropped.push(entry);
+    }
+    self.bytes = 0;
+    dropped
+  }
+
+  /// Total size of the cached waveforms in bytes.
+  pub fn bytes(&self) -> u64 {
+    self.bytes
+  }
+
+  pub fn budget_bytes(&self) -> u64 {
+    self.budget_bytes
+  }
+
+  /// Caches `entry` as the most recently used. Returns the entries that were dropped for it,
+  /// including the one it replaced under the same key.
+  pub fn push(
+    &mut self,
+    key: WaveformCacheKey,
+    entry: WaveformCacheEntry,
+  ) -> Vec<(WaveformCacheKey, WaveformCacheEntry)> {
+    self.bytes += entry.bytes;
+    let mut evicted = Vec::new();
+    if let Some(replaced) = self.entries.push(key, entry) {
+      self.bytes -= replaced.1.bytes;
+      evicted.push(replaced);
+    }
+    evicted.extend(self.shrink());
+    evicted
+  }
+
+  /// Weighs the entry of `key` again once its waveform is synthesized, making it the most
+  /// recently used. Returns the entries dropped to stay within the budget.
+  pub fn reweigh(&mut self, key: &WaveformCacheKey) -> Vec<(WaveformCacheKey, WaveformCacheEntry)> {
+    let Some(entry) = self.entries.get_mut(key) else {
+      return Vec::new();
+    };
+    let bytes = wav_bytes(&entry.cell);
+    self.bytes = self.bytes - entry.bytes + bytes;
+    entry.bytes = bytes;
+    self.shrink()
+  }
+
+  fn shrink(&mut self) -> Vec<(WaveformCacheKey, WaveformCacheEntry)> {
+    let mut evicted = Vec::new();
+    while self.bytes > self.budget_bytes && self.entries.len() > 1 {
+      let Some(dropped) = self.entries.pop_lru() else {
+        break;
+      };
+      self.bytes -= dropped.1.bytes;
+      evicted.push(dropped);
+    }
+    evicted
+  }
+}
+
 pub(crate) fn eviction_events(
   entry: WaveformCacheEntry,
 ) -> impl Iterator<Item = SynthesisJobEvent> {
@@ -436,6 +563,56 @@ mod tests {
       .any(|event| event.block_id == "same" && event.generation_id == 2));
   }
 
+  #[test]
+  fn waveforms_are_evicted_by_size_keeping_the_last_one_used() {
+    let wav = |bytes: usize| Arc::new(OnceCell::new_with(Some(vec![0; bytes])));
+    let key = |name: &str| (name.to_owned(), StyleId(1));
+    let mut cache = WaveformCache::new(100);
+    let mut owned = WaveformCacheEntry::new(wav(40));
+    owned.add_owner(WaveformCacheOwner {
+      identity: job("owned", 1, 1.0).identity,
+    });
+    assert!(cache.push(key("owned"), owned).is_empty());
+    assert!(cache
+      .push(key("short"), WaveformCacheEntry::new(wav(40)))
+      .is_empty());
+    cache.get(&key("owned"));
+
+    // a pending waveform weighs nothing until it is synthesized
+    let pending = Arc::new(OnceCell::new());
+    assert!(cache
+      .push(key("long"), WaveformCacheEntry::new(pending.clone()))
+      .is_empty());
+    assert_eq!(cache.bytes(), 80);
+    pending.set(vec![0; 50]).unwrap();
+    let evicted = cache.reweigh(&key("long"));
+    assert_eq!(evicted.len(), 1);
+    assert_eq!(evicted[0].0, key("short"));
+    assert_eq!(cache.bytes(), 90);
+
+    // one waveform over the whole budget is kept on its own
+    let evicted = cache.push(key("paragraph"), WaveformCacheEntry::new(wav(150)));
+    assert_eq!(evicted.len(), 2);
+    assert_eq!(cache.bytes(), 150);
+    assert!(cache.contains(&key("paragraph")));
+    let events = evicted
+      .into_iter()
+      .flat_map(|(_, entry)| eviction_events(entry))
+      .collect::<Vec<_>>();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].block_id, "owned");
+
+    let evicted = cache.push(key("paragraph"), WaveformCacheEntry::new(wav(10)));
+    assert_eq!(evicted.len(), 1);
+    assert_eq!(cache.bytes(), 10);
+    cache.push(key("short"), WaveformCacheEntry::new(wav(40)));
+    assert!(cache.pop(&key("short")).is_some());
+    assert!(cache.pop(&key("short")).is_none());
+    assert_eq!(cache.bytes(), 10);
+    assert_eq!(cache.clear().len(), 1);
+    assert_eq!(cache.bytes(), 0);
+  }
+
   #[test]
   fn waiting_consumer_is_woken_by_a_concurrent_enqueue() {
     let queue = Arc::new(SynthesisQueue::default());
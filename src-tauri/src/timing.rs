@@ -0,0 +1,272 @@
+//! Duration math for audio queries, following how VOICEVOX Core turns phoneme lengths into
+//! decoder frames.
+use voicevox_core::AudioQuery;
+
+/// VOICEVOX decodes 256-sample frames at 24 kHz and rounds every phoneme to whole frames.
+const FRAMES_PER_SECOND: f64 = 24_000.0 / 256.0;
+
+/// Stretch bounds for fitting a duration, the 50-200% range of `Preset::speed`.
+const MIN_FIT_SPEED: f32 = 0.5;
+const MAX_FIT_SPEED: f32 = 2.0;
+
+/// Scaled phonemes never get shorter than this unless they already were.
+const MIN_PHONEME_LENGTH: f32 = 0.01;
+
+/// Where a block's speech sits inside its synthesized waveform, in seconds.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct QueryTiming {
+  /// Total waveform length, including the pre/post phoneme silences.
+  pub duration: f64,
+  /// End of the leading silence.
+  pub speech_start: f64,
+  /// Start of the trailing silence.
+  pub speech_end: f64,
+}
+
+fn phoneme_frames(length: f32, speed_scale: f32) -> f64 {
+  (length as f64 * FRAMES_PER_SECOND / speed_scale as f64).round()
+}
+
+/// Every consonant and vowel length of a query in synthesis order, pause moras included.
+fn mora_lengths(query: &AudioQuery) -> impl Iterator<Item = f32> + '_ {
+  query
+    .accent_phrases
+    .iter()
+    .flat_map(|phrase| phrase.moras.iter().chain(phrase.pause_mora.iter()))
+    .flat_map(|mora| mora.consonant_length.into_iter().chain([mora.vowel_length]))
+}
+
+pub(crate) fn query_timing(query: &AudioQuery) -> Result<QueryTiming, String> {
+  let speed_scale = query.speed_scale;
+  if !speed_scale.is_finite() || speed_scale <= 0.0 {
+    return Err("speedScale must be a finite, positive number".into());
+  }
+  let pre = phoneme_frames(query.pre_phoneme_length, speed_scale);
+  let speech = mora_lengths(query)
+    .map(|length| phoneme_frames(length, speed_scale))
+    .sum::<f64>();
+  let post = phoneme_frames(query.post_phoneme_length, speed_scale);
+  Ok(QueryTiming {
+    duration: (pre + speech + post) / FRAMES_PER_SECOND,
+    speech_start: pre / FRAMES_PER_SECOND,
+    speech_end: (pre + speech) / FRAMES_PER_SECOND,
+  })
+}
+
+/// Start offsets of blocks played back to back. A block with a cue start is delayed so its
+/// speech begins on the cue, but it never overlaps the block before it.
+pub(crate) fn block_offsets(blocks: &[(QueryTiming, Option<f64>)]) -> Vec<f64> {
+  blocks
+    .iter()
+    .scan(0.0, |elapsed: &mut f64, (timing, cue_start)| {
+      let start = cue_start.map_or(*elapsed, |cue_start| {
+        (cue_start - timing.speech_start).max(*elapsed)
+      });
+      *elapsed = start + timing.duration;
+      Some(start)
+    })
+    .collect()
+}
+
+/// Divides every consonant, vowel and pause length by `speed`.
+fn scale_lengths(query: &AudioQuery, speed: f32) -> AudioQuery {
+  let scale = |length: f32| (length / speed).max(length.min(MIN_PHONEME_LENGTH));
+  let mut query = query.clone();
+  for phrase in &mut query.accent_phrases {
+    for mora in phrase.moras.iter_mut().chain(phrase.pause_mora.iter_mut()) {
+      mora.consonant_length = mora.consonant_length.map(scale);
+      mora.vowel_length = scale(mora.vowel_length);
+    }
+  }
+  query
+}
+
+/// Scales the consonant, vowel and pause lengths of a query so its waveform lasts as close to
+/// `target` seconds as whole frames allow, keeping `speedScale` and the pre/post silences.
+///
+/// Lengths are stretched between 50% and 200% speed relative to their current values; targets
+/// outside that range are reported as errors.
+pub(crate) fn fit_duration(query: &AudioQuery, target: f64) -> Result<AudioQuery, String> {
+  if !target.is_finite() || target <= 0.0 {
+    return Err("Target duration must be a finite, positive number".into());
+  }
+  let duration =
+    |speed: f32| query_timing(&scale_lengths(query, speed)).map(|timing| timing.duration);
+
+  // durations shrink as the speed grows
+  let (shortest, longest) = (duration(MAX_FIT_SPEED)?, duration(MIN_FIT_SPEED)?);
+  let tolerance = 0.5 / FRAMES_PER_SECOND;
+  if target < shortest - tolerance || target > longest + tolerance {
+    return Err(format!(
+      "A duration of {target:.2}s is out of reach, this block lasts between \
+       {shortest:.2}s and {longest:.2}s at 50-200% speed"
+    ));
+  }
+  let (mut slow, mut fast) = (MIN_FIT_SPEED, MAX_FIT_SPEED);
+  for _ in 0..32 {
+    let speed = (slow + fast) / 2.0;
+    if duration(speed)? > target {
+      slow = speed;
+    } else {
+      fast = speed;
+    }
+  }
+  let speed = if duration(slow)? - target < target - duration(fast)? {
+    slow
+  } else {
+    fast
+  };
+  Ok(scale_lengths(query, speed))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use serde_json::json;
+
+  fn query(speed_scale: f32) -> AudioQuery {
+    serde_json::from_value(json!({
+      "accent_phrases": [{
+        "moras": [
+          {
+            "text": "コ",
+            "consonant": "k",
+            "consonant_length": 0.08,
+            "vowel": "o",
+            "vowel_length": 0.12,
+            "pitch": 5.4
+          },
+          {
+            "text": "ン",
+            "consonant": null,
+            "consonant_length": null,
+            "vowel": "N",
+            "vowel_length": 0.1,
+            "pitch": 5.5
+          }
+        ],
+        "accent": 1,
+        "pause_mora": {
+          "text": "、",
+          "consonant": null,
+          "consonant_length": null,
+          "vowel": "pau",
+          "vowel_length": 0.3,
+          "pitch": 0.0
+        },
+        "is_interrogative": false
+      }],
+      "speedScale": speed_scale,
+      "pitchScale": 0.0,
+      "intonationScale": 1.0,
+      "volumeScale": 1.0,
+      "prePhonemeLength": 0.1,
+      "postPhonemeLength": 0.2,
+      "outputSamplingRate": 24000,
+      "outputStereo": false
+    }))
+    .unwrap()
+  }
+
+  fn frames(seconds: f32) -> f64 {
+    phoneme_frames(seconds, 1.0) / FRAMES_PER_SECOND
+  }
+
+  #[test]
+  fn timing_sums_silences_moras_and_pauses_in_whole_frames() {
+    let timing = query_timing(&query(1.0)).unwrap();
+
+    let pre = frames(0.1);
+    let speech = frames(0.08) + frames(0.12) + frames(0.1) + frames(0.3);
+    let post = frames(0.2);
+    assert!((timing.speech_start - pre).abs() < 1e-9);
+    assert!((timing.speech_end - (pre + speech)).abs() < 1e-9);
+    assert!((timing.duration - (pre + speech + post)).abs() < 1e-9);
+  }
+
+  #[test]
+  fn speed_scale_shortens_every_phoneme() {
+    let normal = query_timing(&query(1.0)).unwrap();
+    let fast = query_timing(&query(2.0)).unwrap();
+
+    assert!((fast.duration - normal.duration / 2.0).abs() < 0.05);
+    assert!(fast.speech_start < normal.speech_start);
+  }
+
+  #[test]
+  fn timing_rejects_unusable_speed_scales() {
+    for speed_scale in [0.0, -1.0, f32::NAN, f32::INFINITY] {
+      let mut query = query(1.0);
+      query.speed_scale = speed_scale;
+      assert!(query_timing(&query).is_err());
+    }
+  }
+
+  fn block(duration: f64, speech_start: f64) -> QueryTiming {
+    QueryTiming {
+      duration,
+      speech_start,
+      speech_end: duration,
+    }
+  }
+
+  #[test]
+  fn untimed_blocks_play_back_to_back() {
+    let blocks = [1.5, 0.5, 2.0].map(|duration| (block(duration, 0.0), None));
+    assert_eq!(block_offsets(&blocks), vec![0.0, 1.5, 2.0]);
+    assert!(block_offsets(&[]).is_empty());
+  }
+
+  #[test]
+  fn timed_blocks_start_speaking_on_their_cue_without_overlapping() {
+    let blocks = [
+      (block(1.0, 0.25), Some(2.0)),
+      // this cue starts before the previous block has finished
+      (block(1.0, 0.0), Some(2.5)),
+      (block(0.5, 0.0), None),
+      (block(1.0, 0.5), Some(0.0)),
+    ];
+    assert_eq!(block_offsets(&blocks), vec![1.75, 2.75, 3.75, 4.25]);
+  }
+
+  #[test]
+  fn fitting_keeps_speed_and_silences() {
+    let query = query(1.0);
+    let natural = query_timing(&query).unwrap();
+    let target = natural.duration + 0.3;
+
+    let fitted = fit_duration(&query, target).unwrap();
+    let timing = query_timing(&fitted).unwrap();
+
+    assert!((timing.duration - target).abs() <= 1.0 / FRAMES_PER_SECOND);
+    assert_eq!(fitted.speed_scale, 1.0);
+    assert_eq!(timing.speech_start, natural.speech_start);
+    assert_eq!(
+      timing.duration - timing.speech_end,
+      natural.duration - natural.speech_end
+    );
+    let pause = fitted.accent_phrases[0].pause_mora.as_ref().unwrap();
+    assert!(pause.vowel_length > 0.3);
+  }
+
+  #[test]
+  fn compressed_phonemes_keep_a_minimum_length() {
+    let scaled = scale_lengths(&query(1.0), 100.0);
+    let mora = &scaled.accent_phrases[0].moras[0];
+    assert_eq!(mora.consonant_length, Some(MIN_PHONEME_LENGTH));
+    assert_eq!(mora.vowel_length, MIN_PHONEME_LENGTH);
+  }
+
+  #[test]
+  fn unreachable_targets_are_reported() {
+    let query = query(1.0);
+    let natural = query_timing(&query).unwrap().duration;
+
+    for target in [natural / 3.0, natural * 3.0] {
+      let error = fit_duration(&query, target).unwrap_err();
+      assert!(error.contains("out of reach"), "{error}");
+    }
+    assert!(fit_duration(&query, 0.0).is_err());
+    assert!(fit_duration(&query, f64::NAN).is_err());
+  }
+}
@@ -0,0 +1,339 @@
+//! An on-disk index of voice model metadata, so startup only opens the `.vvm` files that changed.
+//!
+//! Opening a voice model reads its whole archive, which dominates startup with a full model set.
+//! The index remembers the ID and metadata of every model by path, size and modification time.
+//! Core profiles share one index, so it holds the models of every directory they point to.
+use crate::atomic_write::write_atomically;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use voicevox_core::{StyleId, StyleType, VoiceModelId, VoiceModelMeta};
+
+const VVM_INDEX_VERSION: u32 = 1;
+
+/// Tells versions of a file apart without reading it.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub(crate) struct FileStamp {
+  size: u64,
+  modified_ns: u64,
+}
+
+impl FileStamp {
+  fn of(path: &Path) -> Option<Self> {
+    let metadata = fs::metadata(path).ok()?;
+    let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+    Some(Self {
+      size: metadata.len(),
+      modified_ns: modified.as_nanos() as u64,
+    })
+  }
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+struct IndexEntry {
+  stamp: FileStamp,
+  id: VoiceModelId,
+  metas: VoiceModelMeta,
+}
+
+/// A voice model as read from the index or from its file.
+pub(crate) struct IndexedVoiceModel {
+  pub path: String,
+  pub id: VoiceModelId,
+  pub metas: VoiceModelMeta,
+  /// The version of the file the model was read from, if it could be stamped.
+  pub stamp: Option<FileStamp>,
+  /// Whether the file was opened because it is new or changed.
+  pub reopened: bool,
+}
+
+/// The talk styles of the voice models in a directory.
+#[derive(Clone, Default)]
+pub struct VoiceModels {
+  /// Talk characters of every model, by file path.
+  pub metas: HashMap<String, VoiceModelMeta>,
+  pub speaker_to_vvm: HashMap<StyleId, VoiceModelId>,
+  /// The ID of every model, by file path.
+  pub ids: HashMap<String, VoiceModelId>,
+  /// The version of every model file that could be stamped, by file path.
+  pub(crate) stamps: HashMap<String, FileStamp>,
+}
+
+impl VoiceModels {
+  pub(crate) fn new(models: &[IndexedVoiceModel]) -> Self {
+    // TODO: organize the styles with character-styles mapping
+    let mut voice_models = Self::default();
+    for model in models {
+      let mut filtered_meta = HashMap::new();
+      for m in model.metas.iter() {
+        for style in &m.styles {
+          if style.r#type == StyleType::Talk {
+            voice_models.speaker_to_vvm.insert(style.id, model.id);
+            filtered_meta.insert(m.speaker_uuid.clone(), m.clone());
+          }
+        }
+      }
+      voice_models
+        .metas
+        .insert(model.path.clone(), filtered_meta.into_values().collect());
+      voice_models.ids.insert(model.path.clone(), model.id);
+      if let Some(stamp) = model.stamp {
+        voice_models.stamps.insert(model.path.clone(), stamp);
+      }
+    }
+    voice_models
+  }
+
+  /// The IDs of models that are gone from `models` or were changed since, which have to be
+  /// unloaded so a fresh copy is loaded when they are used again.
+  pub(crate) fn stale_ids(&self, models: &[IndexedVoiceModel]) -> HashSet<VoiceModelId> {
+    self
+      .ids
+      .iter()
+      .filter(|(path, _)| {
+        models
+          .iter()
+          .find(|model| &model.path == *path)
+          .is_none_or(|model| model.reopened)
+      })
+      .map(|(_, id)| *id)
+      .collect()
+  }
+}
+
+#[derive(Deserialize, Serialize)]
+pub(crate) struct VvmIndex {
+  version: u32,
+  models: BTreeMap<String, IndexEntry>,
+}
+
+impl Default for VvmIndex {
+  fn default() -> Self {
+    Self {
+      version: VVM_INDEX_VERSION,
+      models: BTreeMap::new(),
+    }
+  }
+}
+
+impl VvmIndex {
+  /// Reads the index at `path`. A missing, corrupt or outdated index reads as empty, so every
+  /// model is opened once more and the index is rebuilt.
+  pub fn load(path: &Path) -> Self {
+    let Ok(contents) = fs::read(path) else {
+      return Self::default();
+    };
+    match serde_json::from_slice::<Self>(&contents) {
+      Ok(index) if index.version == VVM_INDEX_VERSION => index,
+      Ok(_) => Self::default(),
+      Err(error) => {
+        eprintln!("Rebuilding the corrupt voice model index: {error}");
+        Self::default()
+      }
+    }
+  }
+
+  pub fn save(&self, path: &Path) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+      fs::create_dir_all(parent)
+        .map_err(|e| format!("Failed to create the voice model index directory: {e}"))?;
+    }
+    let contents = serde_json::to_vec(self).map_err(|e| e.to_string())?;
+    write_atomically(path, &contents, "voice model index")
+  }
+
+  /// Brings the index in line with the models at `paths`, calling `open` only for files that are
+  /// new or changed. Models outside `paths`, such as those of another profile, are kept until
+  /// their file is gone. Returns every model in `paths` and whether the index changed.
+  pub fn refresh<E>(
+    &mut self,
+    paths: impl IntoIterator<Item = PathBuf>,
+    mut open: impl FnMut(&Path) -> Result<(VoiceModelId, VoiceModelMeta), E>,
+  ) -> Result<(Vec<IndexedVoiceModel>, bool), E> {
+    let mut models = BTreeMap::new();
+    let mut changed = false;
+    let mut indexed = Vec::new();
+    for path in paths {
+      let key = path.to_string_lossy().into_owned();
+      let stamp = FileStamp::of(&path);
+      let (entry, reopened) = match self.models.remove(&key) {
+        Some(entry) if Some(entry.stamp) == stamp => (entry, false),
+        _ => {
+          let (id, metas) = open(&path)?;
+          changed = true;
+          let Some(stamp) = stamp else {
+            // a model that cannot be stamped is used, but opened again next time
+            indexed.push(IndexedVoiceModel {
+              path: key,
+              id,
+              metas,
+              stamp: None,
+              reopened: true,
+            });
+            continue;
+          };
+          (IndexEntry { stamp, id, metas }, true)
+        }
+      };
+      indexed.push(IndexedVoiceModel {
+        path: key.clone(),
+        id: entry.id,
+        metas: entry.metas.clone(),
+        stamp: Some(entry.stamp),
+        reopened,
+      });
+      models.insert(key, entry);
+    }
+    let unlisted = self.models.len();
+    self.models.retain(|path, _| Path::new(path).is_file());
+    changed |= self.models.len() != unlisted;
+    self.models.extend(models);
+    Ok((indexed, changed))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use serde_json::json;
+  use std::cell::RefCell;
+
+  fn model_id(n: u8) -> VoiceModelId {
+    serde_json::from_value(json!(format!("00000000-0000-0000-0000-0000000000{n:02x}"))).unwrap()
+  }
+
+  fn meta(name: &str) -> VoiceModelMeta {
+    serde_json::from_value(json!([{
+      "name": name,
+      "styles": [{ "id": 1, "name": "ノーマル", "type": "talk", "order": 0 }],
+      "version": "0.1.0",
+      "speaker_uuid": format!("{name}-uuid"),
+      "order": 0
+    }]))
+    .unwrap()
+  }
+
+  #[test]
+  fn only_new_and_changed_models_are_opened() {
+    let directory = tempfile::tempdir().unwrap();
+    let first = directory.path().join("0.vvm");
+    let second = directory.path().join("1.vvm");
+    fs::write(&first, "first").unwrap();
+    fs::write(&second, "second").unwrap();
+    let index_path = directory.path().join("index").join("vvm_index.json");
+    let opened = RefCell::new(Vec::new());
+    let open = |path: &Path| {
+      opened.borrow_mut().push(path.to_path_buf());
+      let name = path.file_stem().unwrap().to_string_lossy().into_owned();
+      Ok::<_, String>((model_id(name.len() as u8), meta(&name)))
+    };
+
+    let mut index = VvmIndex::load(&index_path);
+    let (models, changed) = index
+      .refresh([first.clone(), second.clone()], open)
+      .unwrap();
+    assert!(changed);
+    assert_eq!(models.len(), 2);
+    assert_eq!(opened.borrow().len(), 2);
+    index.save(&index_path).unwrap();
+
+    let mut index = VvmIndex::load(&index_path);
+    let (models, changed) = index
+      .refresh([first.clone(), second.clone()], open)
+      .unwrap();
+    assert!(!changed);
+    assert_eq!(opened.borrow().len(), 2);
+    assert_eq!(models[1].metas[0].name, "1");
+    assert_eq!(models[1].id, model_id(1));
+    assert!(models.iter().all(|model| !model.reopened));
+
+    fs::write(&second, "second, retrained").unwrap();
+    fs::remove_file(&first).unwrap();
+    let (models, changed) = index.refresh([second.clone()], open).unwrap();
+    assert!(changed);
+    assert_eq!(*opened.borrow().last().unwrap(), second);
+    assert_eq!(models.len(), 1);
+    assert!(models[0].reopened);
+    assert_eq!(index.models.len(), 1);
+  }
+
+  #[test]
+  fn models_of_other_directories_are_kept_until_their_files_are_gone() {
+    let directory = tempfile::tempdir().unwrap();
+    let stable = directory.path().join("stable.vvm");
+    let nightly = directory.path().join("nightly").join("0.vvm");
+    fs::create_dir_all(nightly.parent().unwrap()).unwrap();
+    fs::write(&stable, "stable").unwrap();
+    fs::write(&nightly, "nightly").unwrap();
+    let opened = RefCell::new(0);
+    let open = |_: &Path| {
+      *opened.borrow_mut() += 1;
+      Ok::<_, String>((model_id(1), meta("model")))
+    };
+    let mut index = VvmIndex::default();
+    index.refresh([stable.clone()], open).unwrap();
+    index.refresh([nightly.clone()], open).unwrap();
+
+    // switching back to the first directory opens nothing again
+    let (models, changed) = index.refresh([stable.clone()], open).unwrap();
+    assert_eq!(*opened.borrow(), 2);
+    assert!(!changed);
+    assert_eq!(models.len(), 1);
+    assert_eq!(index.models.len(), 2);
+
+    fs::remove_file(&nightly).unwrap();
+    let (_, changed) = index.refresh([stable.clone()], open).unwrap();
+    assert!(changed);
+    assert_eq!(index.models.len(), 1);
+  }
+
+  #[test]
+  fn removed_and_changed_models_are_stale() {
+    let model = |path: &str, id: u8, reopened: bool| IndexedVoiceModel {
+      path: path.into(),
+      id: model_id(id),
+      metas: meta(path),
+      stamp: None,
+      reopened,
+    };
+    let current = VoiceModels::new(&[
+      model("kept.vvm", 1, true),
+      model("changed.vvm", 2, true),
+      model("removed.vvm", 3, true),
+    ]);
+    assert_eq!(current.speaker_to_vvm.len(), 1);
+    assert_eq!(current.metas["kept.vvm"][0].name, "kept.vvm");
+
+    let next = [
+      model("kept.vvm", 1, false),
+      model("changed.vvm", 2, true),
+      model("added.vvm", 4, true),
+    ];
+
+    assert_eq!(
+      current.stale_ids(&next),
+      HashSet::from([model_id(2), model_id(3)])
+    );
+    assert_eq!(VoiceModels::new(&next).ids.len(), 3);
+  }
+
+  #[test]
+  fn corrupt_or_outdated_indexes_are_rebuilt() {
+    let directory = tempfile::tempdir().unwrap();
+    let index_path = directory.path().join("vvm_index.json");
+
+    fs::write(&index_path, "{").unwrap();
+    assert!(VvmIndex::load(&index_path).models.is_empty());
+
+    fs::write(&index_path, r#"{"version":0,"models":{}}"#).unwrap();
+    let mut index = VvmIndex::load(&index_path);
+    assert_eq!(index.version, VVM_INDEX_VERSION);
+
+    let result = index.refresh([directory.path().join("broken.vvm")], |_| {
+      Err::<(VoiceModelId, VoiceModelMeta), _>("cannot open")
+    });
+    assert_eq!(result.err(), Some("cannot open"));
+  }
+}
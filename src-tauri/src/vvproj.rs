@@ -0,0 +1,546 @@
+//! Conversion between Azalea projects and the official VOICEVOX editor's `.vvproj` files.
+//!
+//! VOICEVOX keeps presets in its own settings rather than in projects, so imported presets are
+//! rebuilt from the parameters of each item's query, and exported items carry their preset
+//! applied to the query instead.
+use std::collections::HashMap;
+
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use voicevox_core::{AudioQuery, CharacterMeta, StyleId, StyleMeta};
+
+use crate::config::types::{Preset, Project, TextBlockProps};
+
+/// VOICEVOX spells every query key in camelCase, Azalea's queries keep the phrase and mora keys
+/// in snake_case.
+const QUERY_KEYS: [(&str, &str); 5] = [
+  ("accentPhrases", "accent_phrases"),
+  ("pauseMora", "pause_mora"),
+  ("isInterrogative", "is_interrogative"),
+  ("consonantLength", "consonant_length"),
+  ("vowelLength", "vowel_length"),
+];
+
+/// The engine ID of the official VOICEVOX engine, whose style IDs match VOICEVOX Core's.
+const VOICEVOX_ENGINE_ID: &str = "074fc39e-678b-4c13-8916-ffca8d505d1d";
+
+/// The editor version whose project layout exported files follow.
+const VVPROJ_APP_VERSION: &str = "0.22.0";
+
+#[derive(Clone, Serialize, specta::Type)]
+pub struct ImportedProject {
+  pub project: Project,
+  /// Parts of the VOICEVOX project that could not be carried over.
+  pub warnings: Vec<String>,
+}
+
+fn rename_keys(value: &mut Value, renames: &[(&str, &str)]) {
+  match value {
+    Value::Object(object) => {
+      for (from, to) in renames {
+        if let Some(field) = object.remove(*from) {
+          object.insert((*to).to_string(), field);
+        }
+      }
+      object
+        .values_mut()
+        .for_each(|field| rename_keys(field, renames));
+    }
+    Value::Array(items) => items.iter_mut().for_each(|item| rename_keys(item, renames)),
+    _ => {}
+  }
+}
+
+fn query_from_vvproj(query: &Value) -> Result<AudioQuery, String> {
+  let mut query = query.clone();
+  rename_keys(&mut query, &QUERY_KEYS);
+  serde_json::from_value(query).map_err(|e| e.to_string())
+}
+
+fn query_to_vvproj(query: &AudioQuery) -> Result<Value, String> {
+  let mut query = serde_json::to_value(query).map_err(|e| e.to_string())?;
+  let renames = QUERY_KEYS.map(|(vvproj, azalea)| (azalea, vvproj));
+  rename_keys(&mut query, &renames);
+  if let Value::Object(query) = &mut query {
+    query.insert("pauseLength".into(), Value::Null);
+    query.insert("pauseLengthScale".into(), json!(1.0));
+  }
+  Ok(query)
+}
+
+/// The speaker UUID and style ID an audio item speaks with, in either the current `voice` layout
+/// or the older flat one.
+fn item_voice(item: &Map<String, Value>) -> Option<(Option<&str>, StyleId)> {
+  let voice = item.get("voice").and_then(Value::as_object).unwrap_or(item);
+  let style_id = voice.get("styleId")?.as_u64()?;
+  let speaker_uuid = voice.get("speakerId").and_then(Value::as_str);
+  Some((speaker_uuid, StyleId::new(u32::try_from(style_id).ok()?)))
+}
+
+/// A preset carrying the global parameters of an item's query.
+fn preset_parameters(query: Option<&AudioQuery>, style_id: StyleId) -> Preset {
+  let mut preset = Preset {
+    name: format!("VOICEVOX {}", style_id.0),
+    style_id,
+    ..Preset::default()
+  };
+  if let Some(query) = query {
+    preset.speed = (query.speed_scale * 100.0).round() as u32;
+    preset.pitch = query.pitch_scale;
+    preset.intonation = query.intonation_scale;
+    preset.volume = query.volume_scale;
+    preset.start_slience = query.pre_phoneme_length * 1000.0;
+    preset.end_slience = query.post_phoneme_length * 1000.0;
+  }
+  preset
+}
+
+fn installed_style<'a>(
+  speaker_uuid: Option<&str>,
+  style_id: StyleId,
+  characters: &'a [CharacterMeta],
+) -> Option<(&'a CharacterMeta, &'a StyleMeta)> {
+  characters
+    .iter()
+    .filter(|character| speaker_uuid.is_none_or(|uuid| character.speaker_uuid == uuid))
+    .find_map(|character| {
+      character
+        .styles
+        .iter()
+        .find(|style| style.id == style_id)
+        .map(|style| (character, style))
+    })
+}
+
+/// Reads a `.vvproj` document into an Azalea project, collecting whatever could not be mapped
+/// as warnings instead of failing.
+pub(crate) fn parse_vvproj(
+  document: &str,
+  characters: &[CharacterMeta],
+) -> Result<ImportedProject, String> {
+  let root: Value = serde_json::from_str(document).map_err(|e| e.to_string())?;
+  // projects from VOICEVOX 0.16 onwards keep their talk items under `talk`
+  let talk = root.get("talk").unwrap_or(&root);
+  let (Some(audio_keys), Some(audio_items)) = (
+    talk.get("audioKeys").and_then(Value::as_array),
+    talk.get("audioItems").and_then(Value::as_object),
+  ) else {
+    return Err("This file is not a VOICEVOX project".into());
+  };
+
+  let mut warnings = Vec::new();
+  let has_song_notes = root
+    .pointer("/song/tracks")
+    .and_then(Value::as_object)
+    .is_some_and(|tracks| {
+      tracks.values().any(|track| {
+        track
+          .get("notes")
+          .and_then(Value::as_array)
+          .is_some_and(|notes| !notes.is_empty())
+      })
+    });
+  if has_song_notes {
+    warnings.push("Song tracks are not supported and were skipped".to_string());
+  }
+
+  let mut presets: Vec<Preset> = Vec::new();
+  // identical parameters share one preset, keyed by their serialized form
+  let mut preset_ids = HashMap::new();
+  let mut blocks = Vec::with_capacity(audio_keys.len());
+  for (index, key) in audio_keys.iter().enumerate() {
+    let Some(item) = key
+      .as_str()
+      .and_then(|key| audio_items.get(key))
+      .and_then(Value::as_object)
+    else {
+      warnings.push(format!("Audio item {index} is missing and was skipped"));
+      continue;
+    };
+    let text = item
+      .get("text")
+      .and_then(Value::as_str)
+      .unwrap_or_default()
+      .to_string();
+
+    let query = match item.get("query") {
+      None | Some(Value::Null) => None,
+      Some(query) => match query_from_vvproj(query) {
+        Ok(query) => Some(query),
+        Err(error) => {
+          warnings.push(format!(
+            "Audio item {index} has an unreadable query and will be regenerated: {error}"
+          ));
+          None
+        }
+      },
+    };
+    if let Some(query) = item.get("query") {
+      let pause_length = query
+        .get("pauseLength")
+        .is_some_and(|value| !value.is_null());
+      let pause_scale = query
+        .get("pauseLengthScale")
+        .and_then(Value::as_f64)
+        .is_some_and(|scale| scale != 1.0);
+      if pause_length || pause_scale {
+        warnings.push(format!(
+          "Audio item {index} changes its pause lengths, which Azalea does not support"
+        ));
+      }
+    }
+    if item
+      .get("morphingInfo")
+      .is_some_and(|value| !value.is_null())
+    {
+      warnings.push(format!(
+        "Audio item {index} uses voice morphing, which Azalea does not support"
+      ));
+    }
+
+    let preset_id = match item_voice(item) {
+      None => {
+        warnings.push(format!(
+          "Audio item {index} has no voice and was left without a preset"
+        ));
+        None
+      }
+      Some((speaker_uuid, style_id)) => {
+        let mut preset = preset_parameters(query.as_ref(), style_id);
+        match installed_style(speaker_uuid, style_id, characters) {
+          Some((character, style)) => {
+            preset.name = format!("{} ({})", character.name, style.name);
+            preset.speaker_uuid = Some(character.speaker_uuid.clone());
+            preset.style_name = Some(style.name.clone());
+          }
+          None => warnings.push(format!(
+            "Audio item {index} uses style {} of speaker {}, which is not installed",
+            style_id.0,
+            speaker_uuid.unwrap_or("unknown")
+          )),
+        }
+        let signature = serde_json::to_string(&preset).map_err(|e| e.to_string())?;
+        let id = preset_ids.entry(signature).or_insert_with(|| {
+          let id = uuid::Uuid::new_v4().to_string();
+          presets.push(Preset {
+            id: id.clone(),
+            ..preset
+          });
+          id
+        });
+        Some(id.clone())
+      }
+    };
+
+    blocks.push(TextBlockProps {
+      id: uuid::Uuid::new_v4().to_string(),
+      text,
+      // VOICEVOX stores every query it synthesizes with, so each one may carry hand-tuned prosody
+      query_is_modified: query.is_some(),
+      query,
+      preset_id,
+      timing: None,
+      comment: None,
+      muted: false,
+      pause_after_ms: None,
+      section_id: None,
+    });
+  }
+
+  Ok(ImportedProject {
+    project: Project {
+      blocks,
+      presets,
+      metadata: Default::default(),
+      sections: Vec::new(),
+    },
+    warnings,
+  })
+}
+
+/// Writes blocks, each given as its text, its query with the preset applied and its style, as a
+/// `.vvproj` document the VOICEVOX editor can open.
+pub(crate) fn write_vvproj<'a>(
+  blocks: impl IntoIterator<Item = (&'a str, &'a AudioQuery, StyleId)>,
+  characters: &[CharacterMeta],
+) -> Result<String, String> {
+  let mut audio_keys = Vec::new();
+  let mut audio_items = Map::new();
+  for (index, (text, query, style_id)) in blocks.into_iter().enumerate() {
+    let (character, _) = installed_style(None, style_id, characters)
+      .ok_or_else(|| format!("Project block {index} uses a style that is not installed"))?;
+    let key = uuid::Uuid::new_v4().to_string();
+    audio_items.insert(
+      key.clone(),
+      json!({
+        "text": text,
+        "voice": {
+          "engineId": VOICEVOX_ENGINE_ID,
+          "speakerId": character.speaker_uuid,
+          "styleId": style_id.0,
+        },
+        "query": query_to_vvproj(query)?,
+      }),
+    );
+    audio_keys.push(key);
+  }
+
+  let track_id = uuid::Uuid::new_v4().to_string();
+  let document = json!({
+    "appVersion": VVPROJ_APP_VERSION,
+    "talk": {
+      "audioKeys": audio_keys,
+      "audioItems": audio_items,
+    },
+    // the editor expects a song section with one empty track
+    "song": {
+      "tpqn": 480,
+      "tempos": [{ "position": 0, "bpm": 120 }],
+      "timeSignatures": [{ "measureNumber": 1, "beats": 4, "beatType": 4 }],
+      "tracks": {
+        &track_id: {
+          "name": "",
+          "keyRangeAdjustment": 0,
+          "volumeRangeAdjustment": 0,
+          "notes": [],
+          "pitchEditData": [],
+          "phonemeTimingEditData": {},
+          "solo": false,
+          "mute": false,
+          "gain": 1,
+          "pan": 0,
+        }
+      },
+      "trackOrder": [track_id],
+    },
+  });
+  serde_json::to_string(&document).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use serde_json::json;
+
+  fn characters() -> Vec<CharacterMeta> {
+    serde_json::from_value(json!([{
+      "name": "First",
+      "speaker_uuid": "first-uuid",
+      "version": "0.1.0",
+      "order": null,
+      "styles": [
+        { "id": 1, "name": "Normal", "type": "talk", "order": null },
+        { "id": 2, "name": "Whisper", "type": "talk", "order": null }
+      ]
+    }]))
+    .unwrap()
+  }
+
+  fn query(speed_scale: f64) -> Value {
+    json!({
+      "accentPhrases": [{
+        "moras": [{
+          "text": "コ",
+          "consonant": "k",
+          "consonantLength": 0.08,
+          "vowel": "o",
+          "vowelLength": 0.12,
+          "pitch": 5.4
+        }],
+        "accent": 1,
+        "pauseMora": {
+          "text": "、",
+          "vowel": "pau",
+          "vowelLength": 0.3,
+          "pitch": 0.0
+        },
+        "isInterrogative": true
+      }],
+      "speedScale": speed_scale,
+      "pitchScale": 0.05,
+      "intonationScale": 1.2,
+      "volumeScale": 1.0,
+      "prePhonemeLength": 0.1,
+      "postPhonemeLength": 0.25,
+      "pauseLengthScale": 1.0,
+      "outputSamplingRate": 24000,
+      "outputStereo": false,
+      "kana": "コ'、"
+    })
+  }
+
+  fn item(text: &str, speaker_uuid: &str, style_id: u32, query: Value) -> Value {
+    json!({
+      "text": text,
+      "voice": { "engineId": "engine", "speakerId": speaker_uuid, "styleId": style_id },
+      "query": query,
+      "presetKey": "editor-preset"
+    })
+  }
+
+  #[test]
+  fn items_become_blocks_with_queries_kept_as_overrides() {
+    let document = json!({
+      "appVersion": "0.21.0",
+      "talk": {
+        "audioKeys": ["b", "a", "c"],
+        "audioItems": {
+          "a": item("二番目", "first-uuid", 1, query(1.5)),
+          "b": item("一番目", "first-uuid", 1, query(1.5)),
+          "c": item("三番目", "first-uuid", 2, query(1.0))
+        }
+      },
+      "song": { "tracks": { "track": { "notes": [] } } }
+    });
+
+    let imported = parse_vvproj(&document.to_string(), &characters()).unwrap();
+
+    assert!(imported.warnings.is_empty(), "{:?}", imported.warnings);
+    let Project {
+      blocks, presets, ..
+    } = imported.project;
+    assert_eq!(
+      blocks
+        .iter()
+        .map(|block| block.text.as_str())
+        .collect::<Vec<_>>(),
+      vec!["一番目", "二番目", "三番目"]
+    );
+    assert!(blocks.iter().all(|block| block.query_is_modified));
+    let query = blocks[0].query.as_ref().unwrap();
+    assert_eq!(
+      query.accent_phrases[0].moras[0].consonant_length,
+      Some(0.08)
+    );
+    assert!(query.accent_phrases[0].pause_mora.is_some());
+    assert!(query.accent_phrases[0].is_interrogative);
+
+    // the first two items share their voice and parameters
+    assert_eq!(presets.len(), 2);
+    assert_eq!(blocks[0].preset_id, blocks[1].preset_id);
+    assert_eq!(blocks[0].preset_id.as_ref(), Some(&presets[0].id));
+    assert_eq!(blocks[2].preset_id.as_ref(), Some(&presets[1].id));
+    assert_eq!(presets[0].name, "First (Normal)");
+    assert_eq!(presets[0].speed, 150);
+    assert_eq!(presets[0].pitch, 0.05);
+    assert_eq!(presets[0].start_slience, 100.0);
+    assert_eq!(presets[0].end_slience, 250.0);
+    assert_eq!(presets[1].speaker_uuid.as_deref(), Some("first-uuid"));
+    assert_eq!(presets[1].style_name.as_deref(), Some("Whisper"));
+  }
+
+  #[test]
+  fn unmappable_parts_are_reported_as_warnings() {
+    let mut paused = query(1.0);
+    paused["pauseLengthScale"] = json!(1.5);
+    let mut morphing = item("morph", "first-uuid", 1, query(1.0));
+    morphing["morphingInfo"] = json!({ "rate": 0.5, "targetStyleId": 2 });
+    let document = json!({
+      "talk": {
+        "audioKeys": ["missing", "uninstalled", "paused", "broken", "morphing"],
+        "audioItems": {
+          "uninstalled": item("who", "other-uuid", 7, query(1.0)),
+          "paused": item("pause", "first-uuid", 1, paused),
+          "broken": item("broken", "first-uuid", 1, json!({ "accentPhrases": 3 })),
+          "morphing": morphing
+        }
+      },
+      "song": { "tracks": { "track": { "notes": [{ "id": "note" }] } } }
+    });
+
+    let imported = parse_vvproj(&document.to_string(), &characters()).unwrap();
+
+    let warnings = imported.warnings.join("\n");
+    assert!(warnings.contains("Song tracks"));
+    assert!(warnings.contains("Audio item 0 is missing"));
+    assert!(warnings.contains("Audio item 1 uses style 7 of speaker other-uuid"));
+    assert!(warnings.contains("Audio item 2 changes its pause lengths"));
+    assert!(warnings.contains("Audio item 3 has an unreadable query"));
+    assert!(warnings.contains("Audio item 4 uses voice morphing"));
+
+    let Project {
+      blocks, presets, ..
+    } = imported.project;
+    assert_eq!(blocks.len(), 4);
+    assert!(blocks[2].query.is_none());
+    assert!(!blocks[2].query_is_modified);
+    let uninstalled = presets
+      .iter()
+      .find(|preset| Some(&preset.id) == blocks[0].preset_id.as_ref())
+      .unwrap();
+    assert_eq!(uninstalled.style_id, StyleId::new(7));
+    assert!(uninstalled.speaker_uuid.is_none());
+  }
+
+  #[test]
+  fn older_flat_projects_are_read_and_other_json_is_rejected() {
+    let document = json!({
+      "appVersion": "0.13.0",
+      "audioKeys": ["a"],
+      "audioItems": {
+        "a": { "text": "古い", "engineId": "engine", "styleId": 2, "query": query(1.0) }
+      }
+    });
+
+    let imported = parse_vvproj(&document.to_string(), &characters()).unwrap();
+    assert_eq!(imported.project.blocks[0].text, "古い");
+    assert_eq!(
+      imported.project.presets[0].style_name.as_deref(),
+      Some("Whisper")
+    );
+
+    assert!(matches!(
+      parse_vvproj("{}", &characters()),
+      Err(error) if error.contains("not a VOICEVOX project")
+    ));
+    assert!(parse_vvproj("not json", &characters()).is_err());
+  }
+
+  #[test]
+  fn exported_projects_import_back_with_their_voices_and_parameters() {
+    let characters = characters();
+    let mut whisper = query_from_vvproj(&query(1.5)).unwrap();
+    whisper.post_phoneme_length = 0.5;
+    let normal = query_from_vvproj(&query(1.0)).unwrap();
+
+    let document = write_vvproj(
+      [
+        ("ささやき", &whisper, StyleId::new(2)),
+        ("ふつう", &normal, StyleId::new(1)),
+      ],
+      &characters,
+    )
+    .unwrap();
+
+    let root: Value = serde_json::from_str(&document).unwrap();
+    let first_key = root["talk"]["audioKeys"][0].as_str().unwrap();
+    let first = &root["talk"]["audioItems"][first_key];
+    assert_eq!(first["voice"]["speakerId"], "first-uuid");
+    assert_eq!(first["voice"]["styleId"], 2);
+    assert_eq!(first["query"]["postPhonemeLength"], 0.5);
+    assert!(first["query"]["accentPhrases"][0]["moras"][0]["consonantLength"].is_number());
+    assert!(first["query"].get("accent_phrases").is_none());
+    assert_eq!(root["song"]["trackOrder"].as_array().unwrap().len(), 1);
+
+    let imported = parse_vvproj(&document, &characters).unwrap();
+    assert!(imported.warnings.is_empty(), "{:?}", imported.warnings);
+    let Project {
+      blocks, presets, ..
+    } = imported.project;
+    assert_eq!(blocks[0].text, "ささやき");
+    let as_json = |query: Option<&AudioQuery>| serde_json::to_value(query).unwrap();
+    assert_eq!(as_json(blocks[0].query.as_ref()), as_json(Some(&whisper)));
+    assert_eq!(as_json(blocks[1].query.as_ref()), as_json(Some(&normal)));
+    assert_eq!(presets[0].style_name.as_deref(), Some("Whisper"));
+    assert_eq!(presets[0].speed, 150);
+    assert_eq!(presets[0].end_slience, 500.0);
+    assert_eq!(presets[1].style_name.as_deref(), Some("Normal"));
+  }
+
+  #[test]
+  fn exporting_requires_installed_styles() {
+    let query = query_from_vvproj(&query(1.0)).unwrap();
+    let error = write_vvproj([("誰", &query, StyleId::new(9))], &characters()).unwrap_err();
+    assert!(error.contains("block 0 uses a style that is not installed"));
+  }
+}
@@ -0,0 +1,192 @@
+//! A disk cache of synthesized waveforms, so reopened projects are not synthesized again.
+//!
+//! Each waveform is stored in a file named after its `waveform_cache_key`, a hash of everything
+//! that determines it: the query, the style and the voice model. Files are pruned least recently
+//! used first once they go over the size budget, with the modification time of each file marking
+//! its last use.
+use crate::atomic_write::write_atomically;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::SystemTime;
+
+const WAVEFORM_EXTENSION: &str = "wav";
+
+struct StoredWaveform {
+  bytes: u64,
+  last_used: SystemTime,
+}
+
+pub(crate) struct WaveformStore {
+  dir: PathBuf,
+  budget_bytes: u64,
+  entries: Mutex<HashMap<String, StoredWaveform>>,
+}
+
+impl WaveformStore {
+  /// Opens the cache in `dir`, which is created on the first write. Waveforms beyond
+  /// `budget_bytes` are pruned right away.
+  pub fn open(dir: PathBuf, budget_bytes: u64) -> Self {
+    let mut entries = HashMap::new();
+    for entry in fs::read_dir(&dir).into_iter().flatten().flatten() {
+      let path = entry.path();
+      if path.extension() != Some(WAVEFORM_EXTENSION.as_ref()) {
+        continue;
+      }
+      let (Some(key), Ok(metadata)) = (path.file_stem(), entry.metadata()) else {
+        continue;
+      };
+      entries.insert(
+        key.to_string_lossy().into_owned(),
+        StoredWaveform {
+          bytes: metadata.len(),
+          last_used: metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+        },
+      );
+    }
+    let store = Self {
+      dir,
+      budget_bytes,
+      entries: Mutex::new(entries),
+    };
+    store.prune(&mut store.entries());
+    store
+  }
+
+  fn entries(&self) -> MutexGuard<'_, HashMap<String, StoredWaveform>> {
+    self.entries.lock().unwrap_or_else(PoisonError::into_inner)
+  }
+
+  fn path(&self, key: &str) -> PathBuf {
+    self.dir.join(key).with_extension(WAVEFORM_EXTENSION)
+  }
+
+  /// The stored waveform of `key`, which becomes the most recently used.
+  pub fn get(&self, key: &str) -> Option<Vec<u8>> {
+    if !self.entries().contains_key(key) {
+      return None;
+    }
+    let path = self.path(key);
+    let Ok(wav) = fs::read(&path) else {
+      // removed behind the cache's back
+      self.entries().remove(key);
+      return None;
+    };
+    let now = SystemTime::now();
+    if let Some(entry) = self.entries().get_mut(key) {
+      entry.last_used = now;
+    }
+    if let Err(error) = File::options()
+      .write(true)
+      .open(&path)
+      .and_then(|file| file.set_modified(now))
+    {
+      eprintln!("Failed to record the use of a stored waveform: {error}");
+    }
+    Some(wav)
+  }
+
+  /// Stores the waveform of `key`, pruning the least recently used ones beyond the budget.
+  pub fn put(&self, key: &str, wav: &[u8]) -> Result<(), String> {
+    fs::create_dir_all(&self.dir)
+      .map_err(|e| format!("Failed to create the waveform cache directory: {e}"))?;
+    write_atomically(&self.path(key), wav, "stored waveform")?;
+    let mut entries = self.entries();
+    entries.insert(
+      key.into(),
+      StoredWaveform {
+        bytes: wav.len() as u64,
+        last_used: SystemTime::now(),
+      },
+    );
+    self.prune(&mut entries);
+    Ok(())
+  }
+
+  fn prune(&self, entries: &mut HashMap<String, StoredWaveform>) {
+    let mut size = entries.values().map(|entry| entry.bytes).sum::<u64>();
+    if size <= self.budget_bytes {
+      return;
+    }
+    let mut by_last_use = entries
+      .iter()
+      .map(|(key, entry)| (entry.last_used, key.clone()))
+      .collect::<Vec<_>>();
+    by_last_use.sort();
+    for (_, key) in by_last_use {
+      if size <= self.budget_bytes {
+        break;
+      }
+      match fs::remove_file(self.path(&key)) {
+        Ok(()) => {}
+        Err(error) if error.kind() == ErrorKind::NotFound => {}
+        Err(error) => {
+          eprintln!("Failed to prune a stored waveform: {error}");
+          continue;
+        }
+      }
+      if let Some(entry) = entries.remove(&key) {
+        size -= entry.bytes;
+      }
+    }
+  }
+
+  pub fn budget_bytes(&self) -> u64 {
+    self.budget_bytes
+  }
+
+  /// Total size of the stored waveforms in bytes.
+  pub fn size(&self) -> u64 {
+    self.entries().values().map(|entry| entry.bytes).sum()
+  }
+
+  pub fn clear(&self) -> Result<(), String> {
+    let mut entries = self.entries();
+    match fs::remove_dir_all(&self.dir) {
+      Ok(()) => {}
+      Err(error) if error.kind() == ErrorKind::NotFound => {}
+      Err(error) => return Err(format!("Failed to clear the waveform cache: {error}")),
+    }
+    entries.clear();
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::time::Duration;
+
+  #[test]
+  fn waveforms_survive_reopening_and_the_least_recently_used_are_pruned() {
+    let directory = tempfile::tempdir().unwrap();
+    let dir = directory.path().join("waveforms");
+    let store = WaveformStore::open(dir.clone(), 25);
+    store.put("first", &[1; 10]).unwrap();
+    store.put("second", &[2; 10]).unwrap();
+    // file times can be coarse, so the order of use is set explicitly
+    let earlier = SystemTime::now() - Duration::from_secs(60);
+    File::options()
+      .write(true)
+      .open(store.path("second"))
+      .unwrap()
+      .set_modified(earlier)
+      .unwrap();
+
+    let store = WaveformStore::open(dir.clone(), 25);
+    assert_eq!(store.size(), 20);
+    assert_eq!(store.get("first"), Some(vec![1; 10]));
+
+    store.put("third", &[3; 10]).unwrap();
+    assert_eq!(store.get("second"), None);
+    assert!(!store.path("second").exists());
+    assert_eq!(store.size(), 20);
+
+    store.clear().unwrap();
+    assert_eq!(store.size(), 0);
+    assert!(!dir.exists());
+    assert_eq!(store.get("first"), None);
+  }
+}
@@ -22,7 +22,12 @@ fn test_core() -> Core {
 }
 
 fn talk_style_ids(core: &Core) -> Vec<StyleId> {
-  let mut ids = core.speaker_to_vvm.keys().copied().collect::<Vec<_>>();
+  let mut ids = core
+    .models()
+    .speaker_to_vvm
+    .keys()
+    .copied()
+    .collect::<Vec<_>>();
   ids.sort_unstable();
   assert!(!ids.is_empty(), "the installation has no talk styles");
   ids
@@ -61,14 +66,20 @@ fn decode_wav(wav: &[u8]) -> (WavSpec, Vec<i16>) {
 #[test]
 fn real_core_exposes_consistent_talk_metadata() {
   let core = test_core();
-  let mapped_ids = core.speaker_to_vvm.keys().copied().collect::<HashSet<_>>();
+  let mapped_ids = core
+    .models()
+    .speaker_to_vvm
+    .keys()
+    .copied()
+    .collect::<HashSet<_>>();
   let mut metadata_ids = HashSet::new();
 
+  let models = core.models();
   assert!(
-    !core.metas.is_empty(),
+    !models.metas.is_empty(),
     "the installation has no VVM metadata"
   );
-  for (model_path, characters) in &core.metas {
+  for (model_path, characters) in &models.metas {
     assert!(
       Path::new(model_path).is_file(),
       "metadata points to a missing VVM: {model_path}"
@@ -99,8 +110,9 @@ fn real_core_exposes_consistent_talk_metadata() {
 fn real_core_loads_lazily_and_unloads_models_idempotently() {
   let core = test_core();
   let style_id = first_talk_style_id(&core);
-  let model_id = core.speaker_to_vvm[&style_id];
+  let model_id = core.models().speaker_to_vvm[&style_id];
   let styles_in_model = core
+    .models()
     .speaker_to_vvm
     .iter()
     .filter_map(|(id, candidate)| (*candidate == model_id).then_some(*id))
@@ -139,6 +151,49 @@ fn real_core_loads_lazily_and_unloads_models_idempotently() {
     .expect("unloading all speakers twice should be harmless");
 }
 
+#[test]
+fn real_core_shares_loaded_models_between_blocking_and_nonblocking_synthesis() {
+  let core = test_core();
+  let style_id = first_talk_style_id(&core);
+
+  core
+    .audio_query(TEST_TEXT, style_id)
+    .expect("audio query failed");
+  tauri::async_runtime::block_on(core.prepare_nonblocking_synthesis(style_id))
+    .expect("preparing nonblocking synthesis failed");
+
+  let residency = core.model_residency();
+  assert_eq!(residency.models.len(), 1, "the model was loaded twice");
+  assert_eq!(residency.loaded_bytes, residency.models[0].bytes);
+}
+
+#[test]
+fn real_core_synthesizes_on_parallel_workers() {
+  let mut config = test_core_config();
+  config.synthesis_workers = 2;
+  config.max_loaded_models = 1;
+  let core = Arc::new(Core::init(&config).expect("failed to initialize VOICEVOX Core"));
+  let style_id = first_talk_style_id(&core);
+  let query = core
+    .audio_query(TEST_TEXT, style_id)
+    .expect("audio query failed");
+
+  let workers = (0..2)
+    .map(|_| {
+      let (core, query) = (core.clone(), query.clone());
+      std::thread::spawn(move || core.synthesis(&query, style_id))
+    })
+    .collect::<Vec<_>>();
+  let wavs = workers
+    .into_iter()
+    .map(|worker| worker.join().unwrap().expect("parallel synthesis failed"))
+    .collect::<Vec<_>>();
+
+  assert_eq!(core.workers, 2);
+  assert_eq!(wavs[0], wavs[1]);
+  assert_eq!(core.model_residency().models.len(), 1);
+}
+
 #[test]
 fn real_core_produces_valid_queries_and_interrogative_phrases() {
   let core = test_core();
@@ -1,4 +1,4 @@
-import { events } from "$binding";
+import { commands, events, type RecoverableSession } from "$binding";
 import Resizable from "@corvu/resizable";
 import ConfigPage from "@dialogs/config";
 import { TitleBar } from "@components/titleBar";
@@ -6,7 +6,9 @@ import InitPage from "@layout/InitPage";
 import MainPage from "@layout/MainPage";
 import Sidebar from "@layout/sidebar";
 import { getCurrentWindow, Theme } from "@tauri-apps/api/window";
+import { ask } from "@tauri-apps/plugin-dialog";
 import {
+  batch,
   createEffect,
   createSignal,
   onCleanup,
@@ -40,12 +42,15 @@ function App() {
     themeMode,
     customTitlebarEnabled,
   } = useConfigStore()!;
-  const { setMetas, availableStyleIds } = useMetaStore()!;
+  const { setMetas, replaceMetas, availableStyleIds } = useMetaStore()!;
   const { t1 } = usei18n()!;
   const { uiStore, setUIStore } = useUIStore()!;
-  const { newProject } = useTextStore()!;
+  const { newProject, replaceProject, setProjectPath } = useTextStore()!;
 
   const [initializing, setInitializing] = createSignal(true);
+  const [recoverableSessions, setRecoverableSessions] = createSignal<
+    RecoverableSession[]
+  >([]);
   const [customTitlebarVisible, setCustomTitlebarVisible] = createSignal(
     DEFAULT_CUSTOM_TITLEBAR,
   );
@@ -64,6 +69,7 @@ function App() {
         console.error("Failed to initialize application:", payload.error);
       }
       if (payload.config) {
+        setRecoverableSessions(payload.recoverable_sessions ?? []);
         setUIStore("coreInitialized", payload.core_initialized);
         setRange(Object.fromEntries(payload.range));
         if (payload.metas) setMetas(payload.metas);
@@ -76,6 +82,14 @@ function App() {
     await events.frontendReadyEvent.emit();
   });
 
+  onMount(async () => {
+    // voice models added to or removed from the model directory while running
+    const unlisten = await events.voiceModelsChangedEvent.listen(
+      ({ payload }) => replaceMetas(payload.metas),
+    );
+    onCleanup(unlisten);
+  });
+
   onMount(() => {
     const updateWindowWidth = () => setWindowWidth(window.innerWidth);
     window.addEventListener("resize", updateWindowWidth);
@@ -130,12 +144,47 @@ function App() {
     decorationRequest += 1;
   });
 
+  const offerRecovery = async (session: RecoverableSession) => {
+    const recover = await ask(t1("recovery.prompt"), {
+      title: t1("recovery.title"),
+      kind: "warning",
+    });
+    if (recover) {
+      const result = await commands.restoreRecoverySession(session.id);
+      if (result.status === "error") {
+        console.error("Failed to recover unsaved work:", result.error);
+        return;
+      }
+      batch(() => {
+        setProjectPath(session.project_path);
+        replaceProject(result.data);
+        setUIStore("selectedTextBlockIndex", 0);
+      });
+      // journal the restored work under this session before forgetting the old one
+      const recorded = await commands.recordRecoverySnapshot(
+        result.data,
+        session.project_path,
+      );
+      if (recorded.status === "error") {
+        console.error(recorded.error);
+        return;
+      }
+    }
+    const discarded = await commands.discardRecoverySession(session.id);
+    if (discarded.status === "error") console.error(discarded.error);
+  };
+
   createEffect(() => {
     const stylesReady = availableStyleIds().length > 0;
     if (!coreInitializeResource.loading && stylesReady) {
       // Project defaults read translations and metadata. Neither should become
       // an implicit reason to recreate the active project later.
       untrack(newProject);
+      const [latest] = untrack(recoverableSessions);
+      if (latest) {
+        setRecoverableSessions([]);
+        void offerRecovery(latest);
+      }
     }
   });
 
@@ -13,6 +13,30 @@ async clearCaches() : Promise<Result<null, string>> {
     else return { status: "error", error: e  as any };
 }
 },
+/**
+ * Lists what the query and waveform caches hold, most recently used first, and how lookups in
+ * them went since the app started.
+ */
+async inspectCaches() : Promise<Result<CacheReport, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("inspect_caches") };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+/**
+ * Drops one entry listed by `inspect_caches`. The owners of an evicted waveform are told, the
+ * same as when the cache makes room. Returns whether the entry was cached.
+ */
+async evictCacheEntry(cache: CacheKind, key: string, styleId: StyleId) : Promise<Result<boolean, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("evict_cache_entry", { cache, key, styleId }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
 async getCachedSpeakerIcons(requests: SpeakerIconRequest[]) : Promise<Result<SpeakerIconResult[], string>> {
     try {
     return { status: "ok", data: await TAURI_INVOKE("get_cached_speaker_icons", { requests }) };
@@ -45,6 +69,25 @@ async clearAssets() : Promise<Result<null, string>> {
     else return { status: "error", error: e  as any };
 }
 },
+/**
+ * Size of the waveforms cached on disk in bytes, 0 while the cache is disabled.
+ */
+async getWaveformCacheSize() : Promise<Result<number, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("get_waveform_cache_size") };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+async clearWaveformCache() : Promise<Result<null, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("clear_waveform_cache") };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
 async pickCore() : Promise<CoreConfig | null> {
     return await TAURI_INVOKE("pick_core");
 },
@@ -95,6 +138,19 @@ async reinitCore(config: CoreConfig) : Promise<Result<null, string>> {
     else return { status: "error", error: e  as any };
 }
 },
+/**
+ * Reloads the core from another profile and makes it the active one in the saved config. The
+ * config only changes once the core has loaded. ONNX Runtime is loaded once per run, so every
+ * profile keeps using the runtime library of the first one.
+ */
+async switchCoreProfile(name: string) : Promise<Result<ProfileMetas, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("switch_core_profile", { name }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
 /**
  * Gets metas from voicevox core
  */
@@ -106,6 +162,18 @@ async getMetas() : Promise<Result<CharacterMeta[], string>> {
     else return { status: "error", error: e  as any };
 }
 },
+/**
+ * Lists the voice models the core keeps loaded, most recently used first, with their estimated
+ * memory and the configured limits.
+ */
+async modelResidency() : Promise<Result<ModelResidencyReport, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("model_residency") };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
 async getRange() : Promise<Result<Partial<{ [key in StyleId]: [number, number] }>, string>> {
     try {
     return { status: "ok", data: await TAURI_INVOKE("get_range") };
@@ -169,6 +237,18 @@ async replaceMoraDuration(ap: AccentPhrase[], styleId: StyleId) : Promise<Result
     else return { status: "error", error: e  as any };
 }
 },
+/**
+ * Scales the phoneme lengths of a block's query so it lasts `target_duration` seconds once
+ * `preset` and its pause are applied, within 50-200% speed
+ */
+async fitQueryDuration(query: AudioQuery, preset: Preset, pauseAfterMs: number | null, targetDuration: number) : Promise<Result<AudioQuery, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("fit_query_duration", { query, preset, pauseAfterMs, targetDuration }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
 /**
  * Queues a synthesis request and returns without waiting for inference.
  */
@@ -246,6 +326,9 @@ async playAudio(audioQuery: AudioQuery, speakerId: StyleId, startTimeSeconds: nu
 },
 /**
  * Synthesizes and queues multiple audio queries for uninterrupted playback.
+ * 
+ * Items are played as sent: the editor applies presets and block pauses to each query and leaves
+ * muted blocks out, the same way `resolve_project_blocks` does for renders.
  */
 async playAudioSequence(items: AudioSequenceItem[], startTimeSeconds: number | null) : Promise<Result<null, string>> {
     try {
@@ -300,17 +383,241 @@ async readTextFile(path: string) : Promise<Result<string, string>> {
 async quit() : Promise<void> {
     await TAURI_INVOKE("quit");
 },
-async saveProject(project: Project, path: string, allowCreate: boolean) : Promise<Result<null, string>> {
+/**
+ * Saves a project by atomically replacing the file. When the contents change, up to
+ * `backup_count` previous versions are kept next to it as `<name>.<n>.azp.bak`, newest first.
+ * Returns the metadata as saved, with its creation and modification times filled in.
+ */
+async saveProject(project: Project, path: string, allowCreate: boolean, backupCount: number) : Promise<Result<ProjectMetadata, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("save_project", { project, path, allowCreate, backupCount }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+/**
+ * Loads a project, upgrading files from older schema versions in memory. With
+ * `backup_migrated`, the original of an upgraded file is kept next to it as
+ * `<name>.v<version>.azp.bak` before it can be saved over in the new format.
+ */
+async loadProject(path: string, backupMigrated: boolean) : Promise<Result<LoadedProject, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("load_project", { path, backupMigrated }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+/**
+ * Loads the voice models a project speaks with in the background, so its first playback does
+ * not wait for them. Progress is reported through `ModelPreloadEvent`s, and a newer preload
+ * cancels this one. Loading stops before the first model that would unload one loaded earlier.
+ * Returns the generation ID of the preload.
+ */
+async preloadProjectModels(project: Project) : Promise<Result<number, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("preload_project_models", { project }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+/**
+ * Cancels the running preload. A model that is already loading finishes first. Returns whether a
+ * preload was running.
+ */
+async cancelModelPreload() : Promise<Result<boolean, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("cancel_model_preload") };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+/**
+ * Lists the backups of a project file: the rotating ones newest first, then the originals of
+ * upgrades from the newest schema version down.
+ */
+async listProjectBackups(path: string) : Promise<Result<ProjectBackup[], string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("list_project_backups", { path }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+/**
+ * Replaces a project file with one of its backups and loads it. The version being replaced
+ * becomes the newest backup, so restoring can itself be undone.
+ */
+async restoreProjectBackup(backupPath: string, path: string, backupCount: number) : Promise<Result<LoadedProject, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("restore_project_backup", { backupPath, path, backupCount }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+/**
+ * Keeps the current project for crash recovery until it is saved.
+ */
+async recordRecoverySnapshot(project: Project, projectPath: string | null) : Promise<Result<null, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("record_recovery_snapshot", { project, projectPath }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+/**
+ * Drops this session's recovery snapshot once its work has been saved.
+ */
+async discardRecoverySnapshot() : Promise<Result<null, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("discard_recovery_snapshot") };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+/**
+ * Reads the project an earlier session left unsaved. The snapshot is kept until it is
+ * discarded, so a failed restore can be retried.
+ */
+async restoreRecoverySession(sessionId: string) : Promise<Result<Project, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("restore_recovery_session", { sessionId }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+/**
+ * Forgets unsaved work left by an earlier session.
+ */
+async discardRecoverySession(sessionId: string) : Promise<Result<null, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("discard_recovery_session", { sessionId }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+/**
+ * Reads an SRT, WebVTT or ASS/SSA file into new blocks that keep their cue timings.
+ */
+async importSubtitles(path: string, presetId: string | null) : Promise<Result<TextBlockProps[], string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("import_subtitles", { path, presetId }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+/**
+ * Imports a project from the official VOICEVOX editor, rebuilding presets from each item's
+ * query and matching them against the installed styles.
+ */
+async importVvproj(path: string) : Promise<Result<ImportedProject, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("import_vvproj", { path }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+/**
+ * Exports a project for the official VOICEVOX editor. Unedited queries are regenerated by the
+ * loaded core, and every item carries its preset applied to its query.
+ */
+async exportVvproj(project: Project, path: string) : Promise<Result<string, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("export_vvproj", { project, path }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+/**
+ * Packs a project into a `.azpz` bundle with the rendered take of every block, the icons of its
+ * speakers and the voice models it needs. Returns the path written.
+ */
+async exportBundle(project: Project, path: string) : Promise<Result<string, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("export_bundle", { project, path }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+/**
+ * Opens a `.azpz` bundle. Its takes are put into the waveform cache so nothing has to be
+ * synthesized again, and its icons into the assets cache.
+ */
+async loadBundle(path: string) : Promise<Result<LoadedBundle, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("load_bundle", { path }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+/**
+ * Renders every block of a project into one WAV file through the waveform cache.
+ * 
+ * Preset silences are part of each block's query, so blocks are joined back to back.
+ */
+async renderProject(project: Project, path: string) : Promise<Result<string, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("render_project", { project, path }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+/**
+ * Writes SRT or WebVTT subtitles timed to the audio `render_project` produces.
+ */
+async exportSubtitles(project: Project, path: string, format: SubtitleFormat) : Promise<Result<string, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("export_subtitles", { project, path, format }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+/**
+ * Searches the text or the readings of every block. Readings come from each block's query,
+ * or from the core for blocks that have none yet.
+ */
+async searchProject(project: Project, query: SearchQuery) : Promise<Result<ProjectSearch, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("search_project", { project, query }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+/**
+ * Replaces every match in the text of the project's blocks. With a regex query, `$1` and `$name`
+ * in `replacement` insert capture groups.
+ */
+async replaceInProject(project: Project, query: SearchQuery, replacement: string) : Promise<Result<ProjectReplacement, string>> {
     try {
-    return { status: "ok", data: await TAURI_INVOKE("save_project", { project, path, allowCreate }) };
+    return { status: "ok", data: await TAURI_INVOKE("replace_in_project", { project, query, replacement }) };
 } catch (e) {
     if(e instanceof Error) throw e;
     else return { status: "error", error: e  as any };
 }
 },
-async loadProject(path: string) : Promise<Result<Project, string>> {
+/**
+ * Reports the length, character and mora counts of a project per block, style and preset, and
+ * which blocks still have to be synthesized. Muted and empty blocks are left out, as in a render.
+ */
+async projectStatistics(project: Project) : Promise<Result<ProjectStatistics, string>> {
     try {
-    return { status: "ok", data: await TAURI_INVOKE("load_project", { path }) };
+    return { status: "ok", data: await TAURI_INVOKE("project_statistics", { project }) };
 } catch (e) {
     if(e instanceof Error) throw e;
     else return { status: "error", error: e  as any };
@@ -324,13 +631,19 @@ async loadProject(path: string) : Promise<Result<Project, string>> {
 export const events = __makeEvents__<{
 frontendReadyEvent: FrontendReadyEvent,
 initializationEvent: InitializationEvent,
+modelPreloadEvent: ModelPreloadEvent,
+renderProgressEvent: RenderProgressEvent,
 spectrogramJobEvent: SpectrogramJobEvent,
-synthesisJobEvent: SynthesisJobEvent
+synthesisJobEvent: SynthesisJobEvent,
+voiceModelsChangedEvent: VoiceModelsChangedEvent
 }>({
 frontendReadyEvent: "frontend-ready-event",
 initializationEvent: "initialization-event",
+modelPreloadEvent: "model-preload-event",
+renderProgressEvent: "render-progress-event",
 spectrogramJobEvent: "spectrogram-job-event",
-synthesisJobEvent: "synthesis-job-event"
+synthesisJobEvent: "synthesis-job-event",
+voiceModelsChangedEvent: "voice-models-changed-event"
 })
 
 /** user-defined constants **/
@@ -500,7 +813,71 @@ outputStereo: boolean;
  */
 kana: string | null }
 export type AudioSequenceItem = { audio_query: AudioQuery; speaker_id: StyleId }
-export type AzaleaConfig = { core: CoreConfig | null; ui: UIConfig; system_presets?: Preset[] }
+export type AzaleaConfig = { 
+/**
+ * The configuration the core runs with. While a profile is active, this is its working copy.
+ */
+core: CoreConfig | null; 
+/**
+ * Named core installations to switch between, such as a stable and a nightly VOICEVOX.
+ */
+core_profiles?: CoreProfile[]; 
+/**
+ * The name of the profile `core` belongs to.
+ */
+active_core_profile?: string | null; ui: UIConfig; system_presets?: Preset[] }
+export type BlockStatistics = { block_id: string; style_id: StyleId; preset_id: string; characters: number; moras: number; seconds: number; speech_seconds: number; 
+/**
+ * Whether the waveform cache holds this block's current take.
+ */
+synthesized: boolean }
+/**
+ * Where a block sat on the subtitle track it was imported from, in seconds.
+ */
+export type BlockTiming = { start: number; end: number }
+export type CacheCounts = { hits: number; misses: number; 
+/**
+ * Entries dropped to stay within the limits or evicted one by one. Clearing a whole cache is
+ * not counted.
+ */
+evictions: number; 
+/**
+ * Lookups that waited for a synthesis of the same waveform already running and got its
+ * waveform. A lookup left to synthesize it after that synthesis was cancelled is a miss.
+ * Queries are not shared while they are made, so this stays 0 for them.
+ */
+coalesced_waits: number }
+export type CacheEntryReport = { 
+/**
+ * The hash of the entry, which `evict_cache_entry` takes with the style.
+ */
+key: string; style_id: StyleId; 
+/**
+ * Size of the WAV file, or of the query as JSON. 0 while a waveform is synthesized.
+ */
+bytes: number; 
+/**
+ * Whether the waveform is synthesized. Queries always are.
+ */
+initialized: boolean; owners: CacheOwnerReport[] }
+export type CacheKind = "Query" | "Waveform"
+/**
+ * A block waiting to hear about the eviction of a waveform.
+ */
+export type CacheOwnerReport = { block_id: string; generation_id: number }
+export type CacheReport = { 
+/**
+ * Cached waveforms, most recently used first.
+ */
+waveforms: CacheEntryReport[]; waveform_bytes: number; waveform_budget_bytes: number; waveform_counts: CacheCounts; 
+/**
+ * Cached queries, most recently used first.
+ */
+queries: CacheEntryReport[]; 
+/**
+ * Most queries kept.
+ */
+query_capacity: number; query_counts: CacheCounts }
 /**
  * <i>キャラクター</i>のメタ情報。
  */
@@ -539,12 +916,117 @@ export type CoreConfig = {
  * For example, if the lib is in `/home/user/VOICEVOX/vv-engine/libvoicevox_core.so`,
  * the path should be `/home/user/VOICEVOX/vv-engine`.
  */
-ort_path: string; ojt_dir: string; vvm_dir: string; cache_size?: number; cpu_num_threads?: number }
+ort_path: string; ojt_dir: string; vvm_dir: string; 
+/**
+ * Most audio queries kept in memory. 0 disables both the query and the waveform cache.
+ */
+cache_size?: number; 
+/**
+ * Memory budget for synthesized waveforms in megabytes, weighed by the size of their WAV
+ * files. Past it the least recently used waveforms are dropped. Must not be 0, `cache_size`
+ * turns the cache off.
+ */
+waveform_cache_mb?: number; cpu_num_threads?: number; 
+/**
+ * Most voice models kept loaded at once. Past it the least recently used model is unloaded.
+ * 0 means no limit.
+ */
+max_loaded_models?: number; 
+/**
+ * Memory budget for loaded voice models in megabytes, estimated from their file sizes. 0 means
+ * no limit.
+ */
+model_memory_budget_mb?: number; 
+/**
+ * Synthesis tasks the core runs at once. With `cpu_num_threads` left at 0, the CPU cores are
+ * shared evenly between them.
+ */
+synthesis_workers?: number; 
+/**
+ * Size of the waveform cache on disk in megabytes. Past it the least recently used waveforms
+ * are removed. 0 disables the cache.
+ */
+waveform_store_mb?: number }
+/**
+ * A named core installation.
+ */
+export type CoreProfile = { name: string; config: CoreConfig }
 export type FrontendReadyEvent = null
-export type InitializationEvent = { config: AzaleaConfig | null; core_initialized: boolean; metas: CharacterMeta[] | null; range: ([StyleId, [number, number]])[]; error: string | null }
+export type ImportedProject = { project: Project; 
+/**
+ * Parts of the VOICEVOX project that could not be carried over.
+ */
+warnings: string[] }
+export type InitializationEvent = { config: AzaleaConfig | null; core_initialized: boolean; metas: CharacterMeta[] | null; 
+/**
+ * The core profile `metas` came from.
+ */
+core_profile?: string | null; range: ([StyleId, [number, number]])[]; error: string | null; 
+/**
+ * Unsaved work left behind by earlier sessions, newest first.
+ */
+recoverable_sessions?: RecoverableSession[] }
 export type KeyboardShortcut = { key: string; primary?: boolean; secondary?: boolean; shift?: boolean; alt?: boolean }
 export type KeyboardShortcuts = { save_project?: KeyboardShortcut; toggle_playback?: KeyboardShortcut; play_current?: KeyboardShortcut; play_next?: KeyboardShortcut }
+export type LoadedBundle = { project: Project; 
+/**
+ * The migrations that upgraded the bundled project file, oldest first.
+ */
+migrations: string[]; 
+/**
+ * Styles the project uses that no installed voice model provides.
+ */
+missing_style_ids: StyleId[]; 
+/**
+ * Voice model files the project was made with that are not loaded.
+ */
+missing_vvm_files: string[]; 
+/**
+ * How many rendered takes went into the waveform cache.
+ */
+cached_takes: number }
+export type LoadedProject = { project: Project; 
+/**
+ * The migrations that upgraded an older project file, oldest first.
+ */
+migrations: string[]; 
+/**
+ * Where the original file was copied before being upgraded.
+ */
+backup_path: string | null }
 export type Locale = "Ja" | "En" | "ZhCn"
+export type ModelPreloadEvent = { generationId: number; state: ModelPreloadState; 
+/**
+ * The style whose model was just loaded.
+ */
+styleId: StyleId | null; completed: number; total: number; 
+/**
+ * Why loading `style_id` failed. The preload goes on with the other styles.
+ */
+error: string | null; 
+/**
+ * Styles left out because their models would push the ones loaded before them past
+ * `max_loaded_models` or `model_memory_budget_mb`. Not counted in `total`.
+ */
+skipped: StyleId[] }
+export type ModelPreloadState = "Running" | "Completed" | "Cancelled"
+export type ModelResidencyReport = { 
+/**
+ * Loaded models, most recently used first.
+ */
+models: ResidentModel[]; 
+/**
+ * Estimated memory of every loaded model.
+ */
+loaded_bytes: number; 
+/**
+ * 0 when the number of models is unlimited.
+ */
+max_loaded_models: number; 
+/**
+ * 0 when the memory of models is unlimited.
+ */
+memory_budget_bytes: number }
 /**
  * モーラ（子音＋母音）ごとの情報。
  * 
@@ -608,12 +1090,134 @@ start_slience: number;
  * in seconds, 0.0-3.0, 0 is default for no slience
  */
 end_slience: number; speaker_uuid?: string | null; style_name?: string | null }
-export type Project = { blocks: TextBlockProps[]; presets: Preset[] }
+export type PresetStatistics = { preset_id: string; totals: StatisticsTotals }
+/**
+ * The characters of the running core, tagged with the profile it was started from.
+ */
+export type ProfileMetas = { profile: string | null; metas: CharacterMeta[] }
+export type Project = { blocks: TextBlockProps[]; presets: Preset[]; metadata?: ProjectMetadata; sections?: ProjectSection[] }
+export type ProjectBackup = { path: string; 
+/**
+ * 1 for the version saved over most recently, none for the original of an upgraded file.
+ */
+generation: number | null; 
+/**
+ * The schema version of a file kept before it was upgraded.
+ */
+migrated_from: number | null; 
+/**
+ * When the backup was made, in milliseconds since the Unix epoch.
+ */
+modified_ms: number | null; size: number }
+/**
+ * Describes a project for people and tools that index project files.
+ */
+export type ProjectMetadata = { title?: string; author?: string; description?: string; 
+/**
+ * When the project was first saved, in milliseconds since the Unix epoch.
+ */
+created_ms?: number | null; 
+/**
+ * When the project was last saved with changes, in milliseconds since the Unix epoch.
+ */
+modified_ms?: number | null; tags?: string[]; 
+/**
+ * The core profile the project was last saved with.
+ */
+core_profile?: string | null }
+export type ProjectReplacement = { project: Project; 
+/**
+ * Blocks whose text changed. Those without a hand-edited query have lost it and need a new one.
+ */
+changed_block_ids: string[]; replacements: number }
+export type ProjectSearch = { 
+/**
+ * Matches in block order, then in order within each block.
+ */
+matches: SearchMatch[]; 
+/**
+ * Blocks whose reading could not be made because their preset has no installed style.
+ */
+unreadable_block_ids: string[] }
+/**
+ * A named group of blocks, such as a chapter.
+ */
+export type ProjectSection = { id: string; name: string }
+export type ProjectStatistics = { totals: StatisticsTotals; 
+/**
+ * Length of the rendered project in seconds. Blocks timed to subtitle cues can leave gaps, so
+ * this can be longer than `totals.seconds`.
+ */
+render_seconds: number; blocks: BlockStatistics[]; 
+/**
+ * Time per style, in the order the styles first speak.
+ */
+styles: StyleStatistics[]; 
+/**
+ * Time per preset, in the order the presets are first used.
+ */
+presets: PresetStatistics[]; 
+/**
+ * Blocks whose current take has not been synthesized yet.
+ */
+unsynthesized_block_ids: string[] }
+export type RecoverableSession = { id: string; 
+/**
+ * The file the project was last loaded from or saved to, if any.
+ */
+project_path: string | null; 
+/**
+ * When the snapshot was taken, in milliseconds since the Unix epoch.
+ */
+saved_ms: number; block_count: number }
+export type RenderProgressEvent = { path: string; blockId: string; completed: number; total: number }
+export type ResidentModel = { path: string; 
+/**
+ * Size of the model file, the estimated memory of the loaded model.
+ */
+bytes: number }
+export type SearchMatch = { block_id: string; 
+/**
+ * Where the match starts in the searched text or reading, in UTF-16 code units like
+ * JavaScript string indices.
+ */
+start: number; end: number; matched: string }
+export type SearchQuery = { pattern: string; 
+/**
+ * Treats `pattern` as a regular expression instead of literal text.
+ */
+regex?: boolean; case_sensitive?: boolean; target?: SearchTarget }
+export type SearchTarget = 
+/**
+ * The text of each block.
+ */
+"Text" | 
+/**
+ * The katakana reading of each block, as spoken by the core.
+ */
+"Reading"
 export type SpeakerIconRequest = { speaker_uuid: string; style_id: number }
 export type SpeakerIconResult = { speaker_uuid: string; data_url: string | null; error: string | null }
 export type SpectrogramJobEvent = { blockId: string; generationId: number; hash: string; state: SynthesisJobState; error: string | null; preview: SpectrogramPreview | null }
 export type SpectrogramJobRequest = { blockId: string; generationId: number; audioQuery: AudioQuery; speakerId: StyleId; hash: string }
 export type SpectrogramPreview = { values: number[]; frameCount: number; melBins: number; durationSeconds: number }
+export type StatisticsTotals = { blocks: number; 
+/**
+ * Characters of text, not counting whitespace.
+ */
+characters: number; 
+/**
+ * Spoken moras, not counting pauses.
+ */
+moras: number; 
+/**
+ * Synthesized length in seconds, including the silences around each block.
+ */
+seconds: number; 
+/**
+ * Length of the speech alone in seconds.
+ */
+speech_seconds: number }
 /**
  * スタイルID。
  * 
@@ -645,6 +1249,7 @@ type?: StyleType;
  * [`CharacterMeta::styles`]は、この値に対して昇順に並んでいるべきである。
  */
 order: number | null }
+export type StyleStatistics = { style_id: StyleId; totals: StatisticsTotals }
 /**
  * [<i>スタイル</i>]に対応するモデルの種類。
  * 
@@ -696,6 +1301,7 @@ export type StyleType =
  * [Serde]: serde
  */
 "sing"
+export type SubtitleFormat = "Srt" | "WebVtt"
 export type SynthState = 
 /**
  * not started yet or not present in cache (dropped automatically)
@@ -712,9 +1318,38 @@ export type SynthState =
 export type SynthesisJobEvent = { blockId: string; generationId: number; hash: string; state: SynthesisJobState; error: string | null }
 export type SynthesisJobRequest = { blockId: string; generationId: number; audioQuery: AudioQuery; speakerId: StyleId; hash: string }
 export type SynthesisJobState = "Queued" | "Running" | "Completed" | "Failed" | "Cancelled" | "Evicted"
-export type TextBlockProps = { id: string; text: string; query: AudioQuery | null; query_is_modified: boolean; preset_id: string | null }
+export type TextBlockProps = { id: string; text: string; query: AudioQuery | null; query_is_modified: boolean; preset_id: string | null; 
+/**
+ * Blocks with a timing are rendered at their cue start instead of right after the previous block.
+ */
+timing?: BlockTiming | null; 
+/**
+ * A note about the block that is not spoken.
+ */
+comment?: string | null; 
+/**
+ * Muted blocks stay in the project but are left out of playback and export.
+ */
+muted?: boolean; 
+/**
+ * Silence after the block in milliseconds, replacing the end silence of its preset.
+ */
+pause_after_ms?: number | null; 
+/**
+ * The section the block belongs to, if any.
+ */
+section_id?: string | null }
 export type ThemeMode = "System" | "Light" | "Dark"
-export type UIConfig = { locale?: Locale; theme_mode?: ThemeMode; custom_titlebar?: boolean; primary_color?: string; bottom_scale?: number; auto_save?: boolean; bottom_ratio?: number; side_width?: number; buffer_render?: boolean; nonblocking_synthesis?: boolean; synthesis_delay_ms?: number; spectrogram_preview?: boolean; playback_timeline?: boolean; name_truncation_len?: number; default_export_dir?: string | null; default_export_dir_enabled?: boolean; last_exported_dir?: string | null; shortcuts?: KeyboardShortcuts }
+export type UIConfig = { locale?: Locale; theme_mode?: ThemeMode; custom_titlebar?: boolean; primary_color?: string; bottom_scale?: number; auto_save?: boolean; 
+/**
+ * How many previous versions of a project are kept as `.azp.bak` files when saving over it.
+ */
+project_backup_count?: number; bottom_ratio?: number; side_width?: number; buffer_render?: boolean; nonblocking_synthesis?: boolean; synthesis_delay_ms?: number; spectrogram_preview?: boolean; playback_timeline?: boolean; name_truncation_len?: number; default_export_dir?: string | null; default_export_dir_enabled?: boolean; last_exported_dir?: string | null; shortcuts?: KeyboardShortcuts }
+/**
+ * Sent when voice models were added to, removed from or changed in the running core's model
+ * directory.
+ */
+export type VoiceModelsChangedEvent = { profile: string | null; metas: CharacterMeta[] }
 
 /** tauri-specta globals **/
 
@@ -21,6 +21,7 @@ export function TextBlockView(props: {
   splitDisabled: boolean;
   splitText: () => void;
   saveAudio: () => void;
+  toggleMuted: () => void;
   moveUp: () => void;
   moveDown: () => void;
   remove: () => void;
@@ -65,6 +66,19 @@ export function TextBlockView(props: {
                 disabled={props.splitDisabled}
                 onClick={props.splitText}
               />
+              <IconButton
+                icon={
+                  props.currentText.muted
+                    ? "i-lucide:volume-x"
+                    : "i-lucide:volume-2"
+                }
+                label={t1(
+                  props.currentText.muted
+                    ? "text_block.controls.unmute"
+                    : "text_block.controls.mute",
+                )}
+                onClick={props.toggleMuted}
+              />
               <IconButton
                 icon="i-lucide:save"
                 label={t1("text_block.controls.save_audio")}
@@ -94,6 +108,7 @@ export function TextBlockView(props: {
         </div>
         <div
           class="flex flex-row items-start justify-center pt-sm"
+          classList={{ "opacity-50": props.currentText.muted === true }}
           onFocus={props.setSelected}
         >
           <AutogrowInput
@@ -224,6 +224,24 @@ describe("TextBlock", () => {
     expect(getTextStore().textStore[0]).toMatchObject({ text: "" });
   });
 
+  it("mutes and unmutes a text cell", async () => {
+    mockIPC((cmd) => (cmd === "audio_query" ? audioQuery() : null), {
+      shouldMockEvents: true,
+    });
+    const { getTextStore } = renderBlock(false);
+
+    fireEvent.click(
+      await screen.findByRole("button", { name: "Mute text cell" }),
+    );
+    expect(getTextStore().textStore[0].muted).toBe(true);
+    expect(
+      screen.getByLabelText("Text to synthesize").parentElement!.parentElement,
+    ).toHaveClass("opacity-50");
+
+    fireEvent.click(screen.getByRole("button", { name: "Unmute text cell" }));
+    expect(getTextStore().textStore[0].muted).toBe(false);
+  });
+
   it("splits a text cell at the caret position into the cell below", async () => {
     mockIPC((cmd) => (cmd === "audio_query" ? audioQuery() : null), {
       shouldMockEvents: true,
@@ -167,7 +167,11 @@ function TextBlock(props: { index: number }) {
     if (!path.endsWith(".wav")) path = path.concat(".wav");
     const result = await commands.saveAudio(
       path,
-      getModifiedQuery(unwrap(currentText().query!), preset),
+      getModifiedQuery(
+        unwrap(currentText().query!),
+        preset,
+        currentText().pause_after_ms,
+      ),
       preset.style_id,
     );
     if (result.status === "ok") {
@@ -253,7 +257,7 @@ function TextBlock(props: { index: number }) {
     const query = currentQuery();
     return preset === null || query === null
       ? null
-      : getModifiedQuery(query, preset);
+      : getModifiedQuery(query, preset, currentText().pause_after_ms);
   });
   const { synthState, synthStateText, synthStateIcon } = useTextBlockSynthesis({
     index: props.index,
@@ -277,6 +281,9 @@ function TextBlock(props: { index: number }) {
       splitDisabled={splitDisabled()}
       splitText={splitText}
       saveAudio={saveAudio}
+      toggleMuted={() =>
+        setTextStore(props.index, "muted", !currentText().muted)
+      }
       moveUp={moveUp}
       moveDown={moveDown}
       remove={remove}
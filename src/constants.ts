@@ -4,6 +4,8 @@ export const DEFAULT_CUSTOM_TITLEBAR = true;
 export const DEFAULT_PRIMARY_COLOR = "#3b82f6";
 export const PRIMARY_COLOR_PATTERN = /^#[0-9a-f]{6}$/i;
 export const DEFAULT_BOTTOM_SCALE = 360;
+export const DEFAULT_PROJECT_BACKUP_COUNT = 3;
+export const MAX_PROJECT_BACKUP_COUNT = 20;
 export const DEFAULT_BOTTOM_RATIO = 0.3;
 export const DEFAULT_SIDEBAR_WIDTH = 200;
 export const MIN_SIDEBAR_WIDTH = 175;
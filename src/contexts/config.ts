@@ -15,7 +15,7 @@ import { useUIStore } from "@contexts/ui";
 
 const [ConfigProvider, useConfigStore] = createContextProvider(() => {
   const { uiStore, setUIStore } = useUIStore()!;
-  const { setMetas } = useMetaStore()!;
+  const { setMetas, replaceMetas } = useMetaStore()!;
 
   const [config, setConfig] = createStore<AzaleaConfig>({
     core: null,
@@ -86,6 +86,28 @@ const [ConfigProvider, useConfigStore] = createContextProvider(() => {
     return true;
   };
 
+  const switchCoreProfile = async (name: string): Promise<boolean> => {
+    const res = await commands.switchCoreProfile(name);
+    if (res.status === "error") {
+      console.error("Failed to switch core profile:", res.error);
+      return false;
+    }
+    // the backend kept the previous profile's settings and activated the new one
+    const saved = await commands.getConfig();
+    if (saved.status === "ok") {
+      setConfig({
+        core: saved.data.core,
+        core_profiles: saved.data.core_profiles,
+        active_core_profile: saved.data.active_core_profile,
+      });
+    } else {
+      console.error("Failed to get config:", saved.error);
+    }
+    replaceMetas(res.data.metas);
+    await load_range();
+    return true;
+  };
+
   const [coreInitializeResource] = createResource(
     () => (uiStore.coreInitialized ? undefined : config.core),
     async (cfg) => {
@@ -130,6 +152,7 @@ const [ConfigProvider, useConfigStore] = createContextProvider(() => {
     range,
     setRange,
     reinitializeCore,
+    switchCoreProfile,
     spectrogramPreviewEnabled,
     setSpectrogramPreviewEnabled,
     playbackTimelineEnabled,
@@ -8,7 +8,7 @@ import {
 import { createContextProvider } from "@solid-primitives/context";
 import _ from "lodash";
 import { createSignal, onCleanup } from "solid-js";
-import { createStore } from "solid-js/store";
+import { createStore, reconcile } from "solid-js/store";
 
 function speakerIconKey(request: SpeakerIconRequest) {
   return JSON.stringify([request.speaker_uuid, request.style_id]);
@@ -153,35 +153,42 @@ const [MetaProvider, useMetaStore] = createContextProvider(() => {
     setSpeakerIconRevision((revision) => revision + 1);
     revokeObjectUrls(urls.values());
   };
+  // combine all styles for metas with the same speaker_uuid
+  const combineMetas = (newMetas: CharacterMeta[]): CharacterMeta[] => {
+    const combinedMetas: CharacterMeta[] = [];
+    newMetas.forEach((newMeta) => {
+      const existingMetaIndex = combinedMetas.findIndex(
+        (meta) => meta.speaker_uuid === newMeta.speaker_uuid,
+      );
+      if (existingMetaIndex !== -1) {
+        // combine styles
+        combinedMetas[existingMetaIndex].styles = [
+          ...combinedMetas[existingMetaIndex].styles,
+          ..._.cloneDeep(newMeta.styles),
+        ];
+      } else {
+        combinedMetas.push(_.cloneDeep(newMeta));
+      }
+    });
+    // sort styles by id for each meta
+    combinedMetas.forEach((meta) => {
+      meta.styles.sort((a, b) => (a.id < b.id ? -1 : 1));
+    });
+    combinedMetas.sort((a, b) => a.styles[0].id - b.styles[0].id);
+    return combinedMetas;
+  };
   const setMetas = (newMetas: CharacterMeta[]): undefined | Error => {
     // don't accept new metas if we already have some, it's read-only
     if (metas.length === 0) {
-      // combine all styles for metas with the same speaker_uuid
-      const combinedMetas: CharacterMeta[] = [];
-      newMetas.forEach((newMeta) => {
-        const existingMetaIndex = combinedMetas.findIndex(
-          (meta) => meta.speaker_uuid === newMeta.speaker_uuid,
-        );
-        if (existingMetaIndex !== -1) {
-          // combine styles
-          combinedMetas[existingMetaIndex].styles = [
-            ...combinedMetas[existingMetaIndex].styles,
-            ..._.cloneDeep(newMeta.styles),
-          ];
-        } else {
-          combinedMetas.push(_.cloneDeep(newMeta));
-        }
-      });
-      // sort styles by id for each meta
-      combinedMetas.forEach((meta) => {
-        meta.styles.sort((a, b) => (a.id < b.id ? -1 : 1));
-      });
-      combinedMetas.sort((a, b) => a.styles[0].id - b.styles[0].id);
-      _setMetas(combinedMetas);
+      _setMetas(combineMetas(newMetas));
     } else {
       return new Error("Metas are read-only and we already have some");
     }
   };
+  // for when the running core's models change, e.g. after switching core profiles
+  const replaceMetas = (newMetas: CharacterMeta[]) => {
+    _setMetas(reconcile(combineMetas(newMetas)));
+  };
   const availableStyleIds = () =>
     _.flattenDeep(metas.map((meta) => meta.styles.map((style) => style.id)));
 
@@ -190,6 +197,7 @@ const [MetaProvider, useMetaStore] = createContextProvider(() => {
   return {
     metas,
     setMetas,
+    replaceMetas,
     availableStyleIds,
     speakerIconRevision,
     speakerIconUrl,
@@ -66,6 +66,24 @@ describe("MetaProvider", () => {
     );
   });
 
+  it("replaces metas when the running core's models change", () => {
+    const { store } = renderMetaStore();
+    store.setMetas(metas);
+    const added = {
+      ...metas[0],
+      name: "Added",
+      speaker_uuid: "speaker-added",
+      styles: [{ id: 0, name: "Zero", order: 0, type: "talk" as const }],
+    };
+
+    store.replaceMetas([...metas, added]);
+
+    expect(store.metas[0].name).toBe("Added");
+    expect(store.availableStyleIds()).toContain(0);
+    store.replaceMetas([added]);
+    expect(store.metas.map((speaker) => speaker.name)).toEqual(["Added"]);
+  });
+
   it("owns speaker icon Blob URLs across replacements, clears, and cleanup", () => {
     const createObjectUrl = vi
       .spyOn(URL, "createObjectURL")
@@ -361,6 +379,56 @@ describe("ConfigProvider", () => {
     expect(commands.getRange).toHaveBeenCalledOnce();
   });
 
+  it("switches core profiles and adopts the saved profile settings", async () => {
+    // adopting the profile's core config makes the provider load it
+    vi.spyOn(commands, "initCore").mockResolvedValue({
+      status: "error",
+      error: "Core already loaded",
+    });
+    vi.spyOn(commands, "getMetas").mockResolvedValue({
+      status: "ok",
+      data: metas,
+    });
+    vi.spyOn(commands, "getRange").mockResolvedValue({
+      status: "ok",
+      data: { 1: [4, 6] },
+    });
+    const stable = {
+      ort_path: "/core",
+      ojt_dir: "/dict",
+      vvm_dir: "/stable",
+      cache_size: 128,
+      cpu_num_threads: 4,
+    };
+    const nightly = { ...stable, vvm_dir: "/nightly" };
+    const switchProfile = vi
+      .spyOn(commands, "switchCoreProfile")
+      .mockResolvedValue({
+        status: "ok",
+        data: { profile: "nightly", metas },
+      });
+    vi.spyOn(commands, "getConfig").mockResolvedValue({
+      status: "ok",
+      data: {
+        ...config(),
+        core: nightly,
+        core_profiles: [
+          { name: "stable", config: stable },
+          { name: "nightly", config: nightly },
+        ],
+        active_core_profile: "nightly",
+      },
+    });
+    const configStore = renderConfigStore();
+
+    await expect(configStore.switchCoreProfile("nightly")).resolves.toBe(true);
+    expect(switchProfile).toHaveBeenCalledWith("nightly");
+    expect(configStore.config.active_core_profile).toBe("nightly");
+    expect(configStore.config.core?.vvm_dir).toBe("/nightly");
+    expect(configStore.config.core_profiles).toHaveLength(2);
+    expect(commands.getRange).toHaveBeenCalled();
+  });
+
   it("refuses to reinitialize before a core config is set", async () => {
     vi.spyOn(console, "error").mockImplementation(() => undefined);
     const configStore = renderConfigStore();
@@ -2,11 +2,13 @@ import {
   CharacterMeta,
   Preset,
   Project,
+  ProjectMetadata,
+  ProjectSection,
   TextBlockProps as ProjectTextBlockProps,
 } from "$binding";
 import { createContextProvider } from "@solid-primitives/context";
 import { batch, createEffect, createSignal } from "solid-js";
-import { createStore, produce } from "solid-js/store";
+import { createStore, produce, reconcile } from "solid-js/store";
 import { usei18n } from "@contexts/i18n";
 import { useMetaStore } from "@contexts/meta";
 import { useUIStore } from "@contexts/ui";
@@ -108,6 +110,8 @@ const [TextProvider, useTextStore] = createContextProvider(() => {
       createTextBlock(null, import.meta.env.DEV ? "こんにちは、世界！" : ""),
     ],
     presets: [],
+    metadata: {},
+    sections: [],
   });
   const [textStore, setTextStore] = createStore<TextBlockProps[]>(
     project.blocks as TextBlockProps[],
@@ -115,6 +119,12 @@ const [TextProvider, useTextStore] = createContextProvider(() => {
   const [projectPresetStore, setProjectPresetStore] = createStore<Preset[]>(
     project.presets,
   );
+  const [projectMetadata, setProjectMetadata] = createStore<ProjectMetadata>(
+    {},
+  );
+  const [projectSections, setProjectSections] = createStore<ProjectSection[]>(
+    [],
+  );
 
   const [projectPath, setProjectPath] = createSignal<string | null>(null);
 
@@ -137,6 +147,11 @@ const [TextProvider, useTextStore] = createContextProvider(() => {
     setProject({
       blocks: textStore.map((block) => ({ ...block })),
       presets: projectPresetStore.map((item) => ({ ...item })),
+      metadata: {
+        ...projectMetadata,
+        tags: [...(projectMetadata.tags ?? [])],
+      },
+      sections: projectSections.map((section) => ({ ...section })),
     });
   });
 
@@ -157,6 +172,19 @@ const [TextProvider, useTextStore] = createContextProvider(() => {
     setTextStore(blocks.map((block) => ({ ...block })));
   };
 
+  const replaceProjectMetadata = (metadata: ProjectMetadata = {}) => {
+    setProjectMetadata(reconcile(metadata));
+  };
+
+  const replaceProject = (loaded: Project) => {
+    batch(() => {
+      replaceTextBlocks(loaded.blocks);
+      setProjectPresetStore(loaded.presets);
+      replaceProjectMetadata(loaded.metadata);
+      setProjectSections(loaded.sections ?? []);
+    });
+  };
+
   const removeProjectPreset = (presetId: string) => {
     const index = projectPresetStore.findIndex(
       (preset) => preset.id === presetId,
@@ -211,6 +239,8 @@ const [TextProvider, useTextStore] = createContextProvider(() => {
     const presetId = createPresetId();
     batch(() => {
       setProjectPath(null);
+      replaceProjectMetadata();
+      setProjectSections([]);
       setTextStore([
         createTextBlock(
           presetId,
@@ -241,6 +271,10 @@ const [TextProvider, useTextStore] = createContextProvider(() => {
     setTextStore,
     projectPresetStore,
     setProjectPresetStore,
+    projectMetadata,
+    setProjectMetadata,
+    projectSections,
+    setProjectSections,
     project,
     setProject,
     projectPath,
@@ -251,6 +285,8 @@ const [TextProvider, useTextStore] = createContextProvider(() => {
     createFirstTextBlock,
     markQueryModified,
     replaceTextBlocks,
+    replaceProjectMetadata,
+    replaceProject,
     removeProjectPreset,
     newProject,
   };
@@ -16,7 +16,9 @@ import _ from "lodash";
 import { createSignal, Show } from "solid-js";
 import {
   DEFAULT_CPU_NUM_THREADS,
+  DEFAULT_PROJECT_BACKUP_COUNT,
   DEFAULT_SYNTHESIS_DELAY_MS,
+  MAX_PROJECT_BACKUP_COUNT,
   MAX_SYNTHESIS_DELAY_MS,
 } from "$constants";
 import { useConfigStore } from "@contexts/config";
@@ -147,6 +149,9 @@ export function ConfigPage() {
           </ConfigItem>
           <ConfigSectionTitle label={t1("config.storage")} />
           <AssetCacheSetting open={uiStore.page === "config"} />
+          <ConfigItem label={t1("config.project_backup_count")}>
+            <ProjectBackupCountField />
+          </ConfigItem>
           <ConfigSectionTitle label={t1("config.synthesis")} />
           <ConfigItem label={t1("config.cpu_num_threads")}>
             <CPUThreadSetting />
@@ -308,6 +313,55 @@ function SynthesisDelayField() {
   );
 }
 
+function ProjectBackupCountField() {
+  const { config, setConfig } = useConfigStore()!;
+  const { t1 } = usei18n()!;
+  const count = () =>
+    config.ui.project_backup_count ?? DEFAULT_PROJECT_BACKUP_COUNT;
+  return (
+    <NumberField
+      minValue={0}
+      maxValue={MAX_PROJECT_BACKUP_COUNT}
+      step={1}
+      value={count()}
+      onChange={(value) => {
+        const parsed = Number.parseInt(value, 10);
+        if (Number.isFinite(parsed)) {
+          setConfig(
+            "ui",
+            "project_backup_count",
+            Math.min(Math.max(parsed, 0), MAX_PROJECT_BACKUP_COUNT),
+          );
+        }
+      }}
+      changeOnWheel={true}
+      format={false}
+      class="flex items-center gap-2"
+    >
+      <div class="flex w-16 items-center gap-1">
+        <NumberField.Input
+          aria-label={t1("config.project_backup_count")}
+          class="h-8 w-full rounded-lg b b-slate-2 px-1 outline-none focus:b-primary-3 dark:(b-slate-6 bg-slate-8)"
+        />
+        <div class="flex flex-col">
+          <NumberField.IncrementTrigger
+            aria-label="Increment"
+            class="size-4 bg-transparent group"
+          >
+            <div class="i-lucide:chevron-up size-full group-hover:bg-primary-5 group-active:bg-primary-7" />
+          </NumberField.IncrementTrigger>
+          <NumberField.DecrementTrigger
+            aria-label="Decrement"
+            class="size-4 bg-transparent group"
+          >
+            <div class="i-lucide:chevron-down size-full group-hover:bg-primary-5 group-active:bg-primary-7" />
+          </NumberField.DecrementTrigger>
+        </div>
+      </div>
+    </NumberField>
+  );
+}
+
 function CPUThreadSetting() {
   const { config, setConfig, reinitializeCore } = useConfigStore()!;
   const { t1 } = usei18n()!;
@@ -531,6 +531,33 @@ describe("BottomPanel playback", () => {
     ).toBeInTheDocument();
   });
 
+  it("leaves muted cells out of play-all and applies cell pauses", async () => {
+    mockIPC((cmd) => (cmd === "get_os" ? "Linux" : null), {
+      shouldMockEvents: true,
+    });
+    const sequence = vi
+      .spyOn(commands, "playAudioSequence")
+      .mockResolvedValue({ status: "ok", data: null });
+    const { getTextStore } = renderPanel();
+    await screen.findByRole("button", { name: "Play selected cell" });
+    const [first, second] = getTextStore().textStore;
+    getTextStore().replaceTextBlocks([
+      { ...first, muted: true },
+      { ...second, pause_after_ms: 900 },
+    ]);
+
+    fireEvent.click(
+      screen.getByRole("button", {
+        name: "Play selected cell and all cells below",
+      }),
+    );
+    await waitFor(() => expect(sequence).toHaveBeenCalledOnce());
+    expect(sequence.mock.calls[0][0]).toHaveLength(1);
+    expect(sequence.mock.calls[0][0][0].audio_query).toMatchObject({
+      postPhonemeLength: 0.9,
+    });
+  });
+
   it("focuses each playable text cell as play-all advances", async () => {
     mockIPC((cmd) => (cmd === "get_os" ? "Linux" : null), {
       shouldMockEvents: true,
@@ -35,7 +35,7 @@ const BufferedWaveformJob: Component = () => {
     const selectedPreset = currentPreset();
     return query === null || selectedPreset === null
       ? null
-      : getModifiedQuery(query, selectedPreset);
+      : getModifiedQuery(query, selectedPreset, currentText().pause_after_ms);
   });
   useTextBlockSynthesis({
     index: 0,
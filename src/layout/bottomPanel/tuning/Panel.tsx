@@ -1,4 +1,6 @@
+import { IconButton } from "@components/iconButton";
 import { usei18n } from "@contexts/i18n";
+import { NumberField } from "@kobalte/core/number-field";
 import { Slider } from "@kobalte/core/slider";
 import {
   SpectrogramCanvas,
@@ -129,6 +131,31 @@ export function TuningPanel(props: {
               </Slider.Thumb>
             </Slider.Track>
           </Slider>
+          <div class="flex flex-row items-center gap-1">
+            <NumberField
+              minValue={0.1}
+              step={0.1}
+              rawValue={panel.fitTarget()}
+              onRawValueChange={panel.setFitTarget}
+              format={false}
+            >
+              <NumberField.Input
+                aria-label={t1("bottom.fit_duration_seconds")}
+                placeholder={t1("bottom.fit_duration_seconds")}
+                class="h-5 w-24 text-xs outline-none rounded b b-slate-2 dark:(b-slate-6 bg-slate-7) focus:b-primary-3 px-1"
+                onKeyDown={(event) => {
+                  if (event.key === "Enter") void panel.fitDuration();
+                }}
+              />
+            </NumberField>
+            <IconButton
+              icon="i-lucide:timer"
+              label={t1("bottom.fit_duration")}
+              size="sm"
+              onClick={() => void panel.fitDuration()}
+              disabled={!(panel.fitTarget() > 0)}
+            />
+          </div>
         </Show>
       </div>
     </>
@@ -11,6 +11,7 @@ import {
   MAX_SYNTHESIS_DELAY_MS,
 } from "$constants";
 import { useConfigStore } from "@contexts/config";
+import { usei18n } from "@contexts/i18n";
 import { useMetaStore } from "@contexts/meta";
 import { useSpectrogramStore } from "@contexts/spectrogram";
 import { findPresetById, findPresetStyle, useTextStore } from "@contexts/text";
@@ -21,6 +22,7 @@ import type {
 } from "@layout/bottomPanel/types";
 import { getModifiedQuery, renderRequestFingerprint } from "$utils";
 import { debounce, type Scheduled } from "@solid-primitives/scheduled";
+import { message } from "@tauri-apps/plugin-dialog";
 import _ from "lodash";
 import {
   type Accessor,
@@ -52,6 +54,7 @@ export function useTuningPanel(
     selectedTextBlockIndex,
   } = useTextStore()!;
   const { metas } = useMetaStore()!;
+  const { t1 } = usei18n()!;
   const { uiStore, setUIStore } = useUIStore()!;
   const { config, setConfig, spectrogramPreviewEnabled, range } =
     useConfigStore()!;
@@ -91,7 +94,7 @@ export function useTuningPanel(
     const preset = currentPreset();
     return query == null || preset === null
       ? null
-      : getModifiedQuery(query, preset);
+      : getModifiedQuery(query, preset, currentText()?.pause_after_ms);
   });
   const timelineDuration = createMemo(() =>
     (currentText()?.query?.accent_phrases ?? []).reduce(
@@ -473,6 +476,33 @@ export function useTuningPanel(
     }
   });
 
+  // seconds the selected block should last once its preset is applied
+  const [fitTarget, setFitTarget] = createSignal(Number.NaN);
+  const fitDuration = async () => {
+    const block = currentText();
+    const preset = currentPreset();
+    const target = fitTarget();
+    if (block?.query == null || preset === null || !(target > 0)) return;
+    const result = await commands.fitQueryDuration(
+      block.query,
+      preset,
+      block.pause_after_ms ?? null,
+      target,
+    );
+    if (result.status === "error") {
+      await message(result.error, {
+        title: t1("bottom.fit_duration"),
+        kind: "error",
+      });
+      return;
+    }
+    // the selection may have moved on while the query was fitted
+    const index = selectedIdx();
+    if (index === null || currentText()?.id !== block.id) return;
+    setTextStore(index, "query", result.data);
+    markQueryModified(index);
+  };
+
   return {
     setScrollAreaRef: (element: HTMLDivElement) => {
       scrollAreaRef = element;
@@ -503,5 +533,8 @@ export function useTuningPanel(
     maxScale,
     scale,
     setScale,
+    fitTarget,
+    setFitTarget,
+    fitDuration,
   };
 }
@@ -110,7 +110,7 @@ export function usePlaybackControls(
     const preset = currentPreset();
     return block?.query == null || preset === null
       ? null
-      : getModifiedQuery(block.query, preset);
+      : getModifiedQuery(block.query, preset, block.pause_after_ms);
   });
   const playbackPhrases = createMemo(() => {
     const query = currentModifiedQuery();
@@ -217,7 +217,11 @@ export function usePlaybackControls(
     try {
       if (isPlaying()) await stop();
       activePlaybackSequence = null;
-      const audioQuery = getModifiedQuery(unwrap(block.query!), preset);
+      const audioQuery = getModifiedQuery(
+        unwrap(block.query!),
+        preset,
+        block.pause_after_ms,
+      );
       const result = await commands.playAudio(
         audioQuery,
         preset.style_id,
@@ -327,6 +331,7 @@ export function usePlaybackControls(
     textStore.slice(selectedTextBlockIndex()).flatMap((block) => {
       const preset = findPresetById(projectPresetStore, block.preset_id);
       if (
+        block.muted ||
         block.query === null ||
         block.query.accent_phrases.length === 0 ||
         preset === null ||
@@ -337,7 +342,11 @@ export function usePlaybackControls(
       return [
         {
           blockId: block.id,
-          audioQuery: getModifiedQuery(unwrap(block.query), unwrap(preset)),
+          audioQuery: getModifiedQuery(
+            unwrap(block.query),
+            unwrap(preset),
+            block.pause_after_ms,
+          ),
           speakerId: preset.style_id,
         },
       ];
@@ -50,6 +50,30 @@ export function SidebarFooter(props: { controls: SidebarControls }) {
               >
                 {t1("menu.import_srt")}
               </DropdownMenu.Item>
+              <DropdownMenu.Item
+                class={style.menu_item}
+                onClick={controls.importVvproj}
+              >
+                {t1("menu.import_vvproj")}
+              </DropdownMenu.Item>
+              <DropdownMenu.Item
+                class={style.menu_item}
+                onClick={controls.exportVvproj}
+              >
+                {t1("menu.export_vvproj")}
+              </DropdownMenu.Item>
+              <DropdownMenu.Item
+                class={style.menu_item}
+                onClick={controls.loadBundle}
+              >
+                {t1("menu.load_bundle")}
+              </DropdownMenu.Item>
+              <DropdownMenu.Item
+                class={style.menu_item}
+                onClick={controls.exportBundle}
+              >
+                {t1("menu.export_bundle")}
+              </DropdownMenu.Item>
               <DropdownMenu.Separator class="mx-2 my-1" />
               <DropdownMenu.CheckboxItem
                 checked={controls.autoSave()}
@@ -21,6 +21,7 @@ const secondSpeaker: CharacterMeta = {
 };
 
 const dialogs = vi.hoisted(() => ({
+  message: vi.fn(),
   open: vi.fn(),
   save: vi.fn(),
 }));
@@ -38,21 +39,29 @@ describe("Sidebar project lifecycle", () => {
     mockIPC((cmd, args) => {
       invocations.push({ cmd, args: args as Record<string, unknown> });
       if (cmd === "get_os") return "Linux";
+      if (cmd === "save_project") {
+        return { title: "", tags: [], created_ms: 1_000, modified_ms: 2_000 };
+      }
       if (cmd === "load_project") {
         if ((args as { path?: string }).path === "/tmp/rejected.azp") {
           throw "unsupported project schema";
         }
         return {
-          blocks: [
-            {
-              id: "loaded-block",
-              text: "Loaded block",
-              query: audioQuery({ speedScale: 1.2 }),
-              query_is_modified: true,
-              preset_id: "preset-1",
-            },
-          ],
-          presets: [preset({ name: "Loaded preset" })],
+          project: {
+            blocks: [
+              {
+                id: "loaded-block",
+                text: "Loaded block",
+                query: audioQuery({ speedScale: 1.2 }),
+                query_is_modified: true,
+                preset_id: "preset-1",
+              },
+            ],
+            presets: [preset({ name: "Loaded preset" })],
+            metadata: { title: "Loaded title", tags: ["loaded"] },
+          },
+          migrations: [],
+          backup_path: null,
         };
       }
       return null;
@@ -68,6 +77,7 @@ describe("Sidebar project lifecycle", () => {
       text = textStore;
       batch(() => {
         appConfig.setConfig(config({ auto_save: false }));
+        appConfig.setConfig("active_core_profile", "nightly");
         meta.setMetas(metas);
         text.setProjectPresetStore([preset()]);
         text.replaceTextBlocks([
@@ -102,11 +112,14 @@ describe("Sidebar project lifecycle", () => {
           },
         ],
         presets: [{ id: "preset-1", name: "Default" }],
+        metadata: { core_profile: "nightly" },
       },
     });
     expect(
       (firstSave.args.project as { blocks: unknown[] }).blocks[0],
     ).toHaveProperty("id", "current-block");
+    await waitFor(() => expect(text.projectMetadata.modified_ms).toBe(2_000));
+    expect(text.project.metadata?.created_ms).toBe(1_000);
 
     const input = document.createElement("input");
     document.body.append(input);
@@ -141,6 +154,15 @@ describe("Sidebar project lifecycle", () => {
     await waitFor(() => expect(text.textStore[0].text).toBe("Loaded block"));
     expect(text.projectPresetStore[0].name).toBe("Loaded preset");
     expect(text.projectPath()).toBe("/tmp/loaded.azp");
+    expect(text.projectMetadata.title).toBe("Loaded title");
+    expect(text.projectMetadata.created_ms).toBeUndefined();
+    await waitFor(() =>
+      expect(
+        invocations.find(({ cmd }) => cmd === "preload_project_models")?.args,
+      ).toMatchObject({
+        project: { blocks: [{ id: "loaded-block" }] },
+      }),
+    );
 
     const savesBeforeAutosave = invocations.filter(
       ({ cmd }) => cmd === "save_project",
@@ -185,11 +207,20 @@ describe("Sidebar SRT import", () => {
     const user = userEvent.setup({ pointerEventsCheck: 0 });
     mockIPC((cmd, args) => {
       if (cmd === "get_os") return "Linux";
-      if (cmd === "read_text_file") {
-        if ((args as { path?: string }).path === "/tmp/missing.srt") {
-          throw "file not found";
-        }
-        return "1\n00:00:00,000 --> 00:00:02,000\nFirst cue\n\n2\n00:00:02,500 --> 00:00:05,000\n<i>Second</i> cue";
+      if (cmd === "import_subtitles") {
+        const { path, presetId } = args as { path: string; presetId: string };
+        if (path === "/tmp/missing.srt") throw "file not found";
+        return [
+          ["cue-1", "First cue", { start: 0, end: 2 }],
+          ["cue-2", "Second cue", { start: 2.5, end: 5 }],
+        ].map(([id, text, timing]) => ({
+          id,
+          text,
+          query: null,
+          query_is_modified: false,
+          preset_id: presetId,
+          timing,
+        }));
       }
       return null;
     });
@@ -255,11 +286,79 @@ describe("Sidebar SRT import", () => {
     await waitFor(() =>
       expect(errorSpy).toHaveBeenCalledWith("file not found"),
     );
+    await waitFor(() =>
+      expect(dialogs.message).toHaveBeenCalledWith("file not found", {
+        title: "Import SRT",
+        kind: "error",
+      }),
+    );
     expect(text.textStore).toHaveLength(3);
     errorSpy.mockRestore();
   });
 });
 
+describe("Sidebar VOICEVOX project import", () => {
+  it("replaces the project and shows what could not be carried over", async () => {
+    const user = userEvent.setup({ pointerEventsCheck: 0 });
+    mockIPC((cmd) => {
+      if (cmd === "get_os") return "Linux";
+      if (cmd === "import_vvproj") {
+        return {
+          project: {
+            blocks: [
+              {
+                id: "imported-block",
+                text: "Imported block",
+                query: null,
+                query_is_modified: false,
+                preset_id: "preset-1",
+              },
+            ],
+            presets: [preset({ name: "Imported preset" })],
+          },
+          warnings: [
+            "Dropped the song track",
+            "Item 2 uses a style that is not installed",
+          ],
+        };
+      }
+      return null;
+    });
+    dialogs.open.mockResolvedValueOnce("/tmp/project.vvproj");
+    dialogs.message.mockClear();
+    const warnSpy = vi
+      .spyOn(console, "warn")
+      .mockImplementation(() => undefined);
+    let text!: NonNullable<ReturnType<typeof useTextStore>>;
+    renderSidebar(({ config: appConfig, meta, text: textStore }) => {
+      text = textStore;
+      batch(() => {
+        appConfig.setConfig(config({ auto_save: false }));
+        meta.setMetas(metas);
+        text.setProjectPresetStore([preset()]);
+      });
+    });
+
+    await screen.findByText("Default");
+    await user.click(
+      await screen.findByRole("button", { name: "Project actions" }),
+    );
+    await user.click(
+      await screen.findByRole("menuitem", { name: "Import VOICEVOX Project" }),
+    );
+    await waitFor(() =>
+      expect(dialogs.message).toHaveBeenCalledWith(
+        "Dropped the song track\nItem 2 uses a style that is not installed",
+        { title: "Import VOICEVOX Project", kind: "warning" },
+      ),
+    );
+    expect(text.textStore.map((block) => block.text)).toEqual([
+      "Imported block",
+    ]);
+    warnSpy.mockRestore();
+  });
+});
+
 describe("Sidebar controls", () => {
   it("handles preset controller edge cases", async () => {
     mockIPC((cmd) => (cmd === "get_os" ? "Linux" : null));
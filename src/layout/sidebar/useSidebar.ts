@@ -1,6 +1,8 @@
 import { commands, type Preset, type StyleId } from "$binding";
+import { DEFAULT_PROJECT_BACKUP_COUNT } from "$constants";
 import { createScheduled, throttle } from "@solid-primitives/scheduled";
 import {
+  message,
   open as openDialog,
   save as saveDialog,
 } from "@tauri-apps/plugin-dialog";
@@ -9,6 +11,7 @@ import {
   createEffect,
   createMemo,
   createSignal,
+  on,
   onCleanup,
   onMount,
 } from "solid-js";
@@ -19,13 +22,11 @@ import { useMetaStore } from "@contexts/meta";
 import { useSystemStore } from "@contexts/system";
 import {
   createPresetId,
-  createTextBlock,
   findPresetById,
   findPresetStyle,
   useTextStore,
 } from "@contexts/text";
 import { useUIStore } from "@contexts/ui";
-import { parseSrt } from "$utils";
 import {
   isApplicationShortcutAllowed,
   matchesShortcut,
@@ -45,7 +46,8 @@ export function useSidebar() {
     setProjectPath,
     selectedTextBlock,
     selectedTextBlockIndex,
-    replaceTextBlocks,
+    replaceProject,
+    replaceProjectMetadata,
     removeProjectPreset,
     newProject,
   } = useTextStore()!;
@@ -195,8 +197,28 @@ export function useSidebar() {
       if (path === null) return;
       setProjectPath(path);
     }
-    const result = await commands.saveProject(project, path, true);
-    if (result.status === "error") console.error(result.error);
+    const result = await commands.saveProject(
+      {
+        ...project,
+        metadata: {
+          ...project.metadata,
+          core_profile:
+            config.active_core_profile ?? project.metadata?.core_profile,
+        },
+      },
+      path,
+      true,
+      config.ui.project_backup_count ?? DEFAULT_PROJECT_BACKUP_COUNT,
+    );
+    if (result.status === "error") {
+      console.error(result.error);
+      return;
+    }
+    // the saved times change the project, so they go in before it counts as saved
+    replaceProjectMetadata(result.data);
+    setUnsavedChanges(false);
+    const discarded = await commands.discardRecoverySnapshot();
+    if (discarded.status === "error") console.error(discarded.error);
   };
 
   onMount(() => {
@@ -224,47 +246,157 @@ export function useSidebar() {
       filters: [{ name: "Azalea Poject Files", extensions: ["azp"] }],
     });
     if (path === null) return;
-    const result = await commands.loadProject(path);
+    const result = await commands.loadProject(path, true);
     if (result.status === "error") {
       console.error(result.error);
       return;
     }
+    for (const migration of result.data.migrations) console.info(migration);
     batch(() => {
       setProjectPath(path);
-      replaceTextBlocks(result.data.blocks);
-      setProjectPresetStore(result.data.presets);
+      replaceProject(result.data.project);
       setUIStore("selectedTextBlockIndex", 0);
     });
+    // load the project's voice models before its first playback needs them
+    const preload = await commands.preloadProjectModels(result.data.project);
+    if (preload.status === "error") console.error(preload.error);
+  };
+
+  // problems with an imported file go in a dialog, the console is out of the user's sight
+  const reportImportProblems = async (
+    title: string,
+    problems: string[],
+    kind: "error" | "warning",
+  ) => {
+    if (problems.length === 0) return;
+    await message(problems.join("\n"), { title, kind });
   };
 
   const importSrt = async () => {
     const path = await openDialog({
       title: t1("menu.import_srt"),
-      filters: [{ name: "SRT Subtitle Files", extensions: ["srt"] }],
+      filters: [
+        { name: "Subtitle Files", extensions: ["srt", "vtt", "ass", "ssa"] },
+      ],
     });
     if (path === null) return;
-    const result = await commands.readTextFile(path);
+    const presetId =
+      currentText()?.preset_id ?? projectPresetStore[0]?.id ?? null;
+    const result = await commands.importSubtitles(path, presetId);
     if (result.status === "error") {
       console.error(result.error);
+      await reportImportProblems(
+        t1("menu.import_srt"),
+        [result.error],
+        "error",
+      );
       return;
     }
-    const texts = parseSrt(result.data);
-    if (texts.length === 0) return;
-    const presetId =
-      currentText()?.preset_id ?? projectPresetStore[0]?.id ?? null;
+    if (result.data.length === 0) return;
     const startIndex = textStore.length;
     batch(() => {
       setTextStore(
         produce((blocks) => {
-          for (const text of texts) {
-            blocks.push(createTextBlock(presetId, text));
-          }
+          blocks.push(...result.data);
         }),
       );
       setUIStore("selectedTextBlockIndex", startIndex);
     });
   };
 
+  const importVvproj = async () => {
+    const path = await openDialog({
+      title: t1("menu.import_vvproj"),
+      filters: [{ name: "VOICEVOX Project Files", extensions: ["vvproj"] }],
+    });
+    if (path === null) return;
+    const result = await commands.importVvproj(path);
+    if (result.status === "error") {
+      console.error(result.error);
+      await reportImportProblems(
+        t1("menu.import_vvproj"),
+        [result.error],
+        "error",
+      );
+      return;
+    }
+    for (const warning of result.data.warnings) console.warn(warning);
+    batch(() => {
+      setProjectPath(null);
+      replaceProject(result.data.project);
+      setUIStore("selectedTextBlockIndex", 0);
+    });
+    await reportImportProblems(
+      t1("menu.import_vvproj"),
+      result.data.warnings,
+      "warning",
+    );
+  };
+
+  const exportVvproj = async () => {
+    const path = await saveDialog({
+      title: t1("menu.export_vvproj"),
+      filters: [{ name: "VOICEVOX Project Files", extensions: ["vvproj"] }],
+    });
+    if (path === null) return;
+    const result = await commands.exportVvproj(project, path);
+    if (result.status === "error") console.error(result.error);
+  };
+
+  const loadBundle = async () => {
+    const path = await openDialog({
+      title: t1("menu.load_bundle"),
+      filters: [{ name: "Azalea Project Bundles", extensions: ["azpz"] }],
+    });
+    if (path === null) return;
+    const result = await commands.loadBundle(path);
+    if (result.status === "error") {
+      console.error(result.error);
+      return;
+    }
+    for (const migration of result.data.migrations) console.info(migration);
+    for (const file of result.data.missing_vvm_files) {
+      console.warn(`Bundled project needs the voice model ${file}`);
+    }
+    batch(() => {
+      // a bundle is a snapshot to hand around, edits are saved as a regular project
+      setProjectPath(null);
+      replaceProject(result.data.project);
+      setUIStore("selectedTextBlockIndex", 0);
+    });
+  };
+
+  const exportBundle = async () => {
+    const path = await saveDialog({
+      title: t1("menu.export_bundle"),
+      filters: [{ name: "Azalea Project Bundles", extensions: ["azpz"] }],
+    });
+    if (path === null) return;
+    const result = await commands.exportBundle(project, path);
+    if (result.status === "error") console.error(result.error);
+  };
+
+  // keep unsaved edits where the next start can recover them after a crash
+  const [unsavedChanges, setUnsavedChanges] = createSignal(false);
+  createEffect(
+    on(
+      () => JSON.stringify(project),
+      () => setUnsavedChanges(true),
+      { defer: true },
+    ),
+  );
+  const scheduledSnapshot = createScheduled((fn) => throttle(fn, 2_000));
+  createEffect(() => {
+    JSON.stringify(project);
+    if (scheduledSnapshot() && unsavedChanges()) {
+      void commands
+        .recordRecoverySnapshot(project, projectPath())
+        .then((result) => {
+          if (result.status === "error") console.error(result.error);
+        });
+    }
+  });
+
   const scheduledSave = createScheduled((fn) => throttle(fn, 500));
   createEffect(() => {
     JSON.stringify(project);
@@ -329,6 +461,10 @@ export function useSidebar() {
     loadProject,
     saveProject,
     importSrt,
+    importVvproj,
+    exportVvproj,
+    loadBundle,
+    exportBundle,
   };
 }
 
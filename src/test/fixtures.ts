@@ -12,6 +12,7 @@ import {
   DEFAULT_PLAYBACK_TIMELINE,
   DEFAULT_LOCALE,
   DEFAULT_PRIMARY_COLOR,
+  DEFAULT_PROJECT_BACKUP_COUNT,
   DEFAULT_SIDEBAR_WIDTH,
   DEFAULT_SPECTROGRAM_PREVIEW,
   DEFAULT_SYNTHESIS_DELAY_MS,
@@ -77,6 +78,7 @@ export const config = (
     primary_color: DEFAULT_PRIMARY_COLOR,
     bottom_scale: DEFAULT_BOTTOM_SCALE,
     auto_save: false,
+    project_backup_count: DEFAULT_PROJECT_BACKUP_COUNT,
     bottom_ratio: DEFAULT_BOTTOM_RATIO,
     side_width: DEFAULT_SIDEBAR_WIDTH,
     buffer_render: false,
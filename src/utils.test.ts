@@ -29,6 +29,18 @@ describe("getModifiedQuery", () => {
     });
     expect(source).toEqual(sourceSnapshot);
   });
+
+  it("lets a block pause replace the preset end silence", () => {
+    const adjusted = preset({ start_slience: 250, end_slience: 750 });
+
+    expect(getModifiedQuery(audioQuery(), adjusted, 0)).toMatchObject({
+      prePhonemeLength: 0.25,
+      postPhonemeLength: 0,
+    });
+    expect(getModifiedQuery(audioQuery(), adjusted, null)).toMatchObject({
+      postPhonemeLength: 0.75,
+    });
+  });
 });
 
 describe("useSideEffect", () => {
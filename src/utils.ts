@@ -1,9 +1,14 @@
 import type { AudioQuery, Preset } from "$binding";
 import _ from "lodash";
 
+/**
+ * Applies a preset to a query. A block's own pause after it replaces the
+ * preset's end silence.
+ */
 export function getModifiedQuery(
   query: AudioQuery,
   preset: Preset,
+  pauseAfterMs?: number | null,
 ): AudioQuery {
   const newQuery = _.cloneDeep(query);
   newQuery.pitchScale = preset.pitch;
@@ -11,7 +16,7 @@ export function getModifiedQuery(
   newQuery.intonationScale = preset.intonation;
   newQuery.volumeScale = preset.volume;
   newQuery.prePhonemeLength = preset.start_slience / 1000.0;
-  newQuery.postPhonemeLength = preset.end_slience / 1000.0;
+  newQuery.postPhonemeLength = (pauseAfterMs ?? preset.end_slience) / 1000.0;
   return newQuery;
 }
 